@@ -0,0 +1,613 @@
+//! The REST API behind `todo serve`. Requests are handled one at a time, so
+//! each one sees the list as the previous one left it.
+//!
+//! Every item has an ETag, and so does the list as a whole. A write sent with
+//! `If-Match` is refused with 412 if the item (or, for a create, the list) has
+//! changed since that ETag was read.
+//!
+//! Only pages served from the API's own address can use it from a browser:
+//! requests must name that address or localhost as their `Host`, which stops
+//! DNS rebinding, and writes must be sent as `application/json`, which a
+//! cross-site form cannot do. Beyond localhost a bearer token is required too.
+
+use crate::error::{Result, TodoError};
+use crate::filter::ListFilter;
+use crate::ops::{self, NewItem, Patch};
+use crate::sort::SortSpec;
+use crate::storage::{Storage, TodoItem};
+use serde::Serialize;
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+
+/// The OpenAPI description of the API, served at `/openapi.json`.
+pub const OPENAPI: &str = include_str!("openapi.json");
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// Path and query string, still percent-encoded.
+    pub url: String,
+    pub host: Option<String>,
+    pub content_type: Option<String>,
+    pub authorization: Option<String>,
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+    pub body: String,
+}
+
+/// Who may use the API served on `bind`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Access {
+    bind: SocketAddr,
+    token: Option<String>,
+}
+
+impl Access {
+    /// Refuses to serve beyond this machine without a token.
+    pub fn new(bind: SocketAddr, token: Option<String>) -> Result<Self> {
+        if !bind.ip().is_loopback() && token.is_none() {
+            return Err(TodoError::InvalidInput(format!(
+                "refusing to serve on {} without a token: set serve.token \
+                 or $CRIX_TODO_SERVE_TOKEN, or bind to 127.0.0.1",
+                bind
+            )));
+        }
+        Ok(Access { bind, token })
+    }
+
+    fn check(&self, request: &Request) -> Outcome<()> {
+        let host = request.host.as_deref().unwrap_or_default();
+        if !self.allows_host(host) {
+            return Err(Response::error(
+                421,
+                format!("this server does not answer for host '{}'", host),
+            ));
+        }
+        if let Some(token) = &self.token {
+            let given = request
+                .authorization
+                .as_deref()
+                .and_then(|value| value.strip_prefix("Bearer "));
+            if !given.is_some_and(|given| same_secret(given.trim(), token)) {
+                return Err(Response::error(401, "a valid bearer token is required"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `host` names this server: localhost, a loopback address or
+    /// the bound one (any address, when bound to all of them), with its port.
+    fn allows_host(&self, host: &str) -> bool {
+        let (name, port) = match host.rsplit_once(':') {
+            Some((name, port)) if !port.contains(']') => (name, port.parse().ok()),
+            _ => (host, Some(80)),
+        };
+        if port != Some(self.bind.port()) {
+            return false;
+        }
+        if name.eq_ignore_ascii_case("localhost") {
+            return true;
+        }
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        name.parse::<IpAddr>().is_ok_and(|ip| {
+            ip.is_loopback() || ip == self.bind.ip() || self.bind.ip().is_unspecified()
+        })
+    }
+}
+
+/// Compares a token without giving away, through timing, how much matched.
+fn same_secret(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub etag: Option<String>,
+    pub location: Option<String>,
+    /// JSON, or `None` for an empty body.
+    pub body: Option<String>,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T, etag: Option<String>) -> Self {
+        Response {
+            status,
+            etag,
+            location: None,
+            body: Some(serde_json::to_string_pretty(value).expect("items serialize")),
+        }
+    }
+
+    fn empty(status: u16) -> Self {
+        Response {
+            status,
+            etag: None,
+            location: None,
+            body: None,
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response::json(status, &json!({ "error": message.into() }), None)
+    }
+}
+
+impl From<TodoError> for Response {
+    fn from(e: TodoError) -> Self {
+        let status = match e {
+            TodoError::InvalidInput(_) => 400,
+            TodoError::Conflict(_) => 409,
+            _ => 500,
+        };
+        Response::error(status, e.to_string())
+    }
+}
+
+type Outcome<T = Response> = std::result::Result<T, Response>;
+
+pub fn handle(storage: &impl Storage, access: &Access, request: &Request) -> Response {
+    if let Err(refused) = access.check(request) {
+        return refused;
+    }
+    let writes = matches!(request.method.as_str(), "POST" | "PATCH" | "PUT");
+    let media_type = request
+        .content_type
+        .as_deref()
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+    if writes && !media_type.is_some_and(|t| t.eq_ignore_ascii_case("application/json")) {
+        return Response::error(415, "writes must be sent as application/json");
+    }
+
+    let (path, query) = match request.url.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (request.url.as_str(), ""),
+    };
+    let segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(decode)
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let outcome = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["openapi.json"]) => Ok(Response {
+            status: 200,
+            etag: None,
+            location: None,
+            body: Some(OPENAPI.to_string()),
+        }),
+        ("GET", ["todos"]) => list(storage, request, query),
+        ("POST", ["todos"]) => create(storage, request),
+        ("GET", ["todos", id]) => get(storage, request, id),
+        ("PATCH", ["todos", id]) => modify(storage, request, id, |item| {
+            let patch: Patch = parse_body(&request.body)?;
+            patch.apply(item).map_err(Response::from)
+        }),
+        ("DELETE", ["todos", id]) => delete(storage, request, id),
+        ("POST", ["todos", id, "complete"]) => modify(storage, request, id, |item| {
+            item.done = true;
+            Ok(())
+        }),
+        (_, ["openapi.json"] | ["todos"] | ["todos", _] | ["todos", _, "complete"]) => Err(
+            Response::error(405, format!("{} is not allowed here", request.method)),
+        ),
+        _ => Err(Response::error(404, format!("no such endpoint: {}", path))),
+    };
+    outcome.unwrap_or_else(|response| response)
+}
+
+fn list(storage: &impl Storage, request: &Request, query: &str) -> Outcome {
+    let mut filter = ListFilter::default();
+    let mut sort = None;
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value);
+        match decode(key).as_str() {
+            "all" => filter.show_all = parse_param("all", &value)?,
+            "priority" => filter.priority = Some(parse_param("priority", &value)?),
+            "tag" => filter.tag = Some(value),
+            "due" => filter.due = Some(value),
+            "sort" => sort = Some(value.parse::<SortSpec>().map_err(bad_param("sort"))?),
+            other => {
+                return Err(Response::error(
+                    400,
+                    format!("unknown parameter '{}'", other),
+                ));
+            }
+        }
+    }
+
+    let items = storage.load_items()?;
+    let etag = etag(&items);
+    if matches_etag(request.if_none_match.as_deref(), &etag) {
+        return Ok(not_modified(etag));
+    }
+    let selected = ops::select(items, &filter, sort.as_ref());
+    Ok(Response::json(200, &selected, Some(etag)))
+}
+
+fn create(storage: &impl Storage, request: &Request) -> Outcome {
+    let new: NewItem = parse_body(&request.body)?;
+    let item = new.into_item()?;
+    if let Some(expected) = &request.if_match {
+        let items = storage.load_items()?;
+        check_precondition(expected, &etag(&items))?;
+    }
+    let item = ops::add(storage, item)?;
+
+    let mut response = Response::json(201, &item, Some(etag(&item)));
+    response.location = Some(format!("/todos/{}", item.id));
+    Ok(response)
+}
+
+fn get(storage: &impl Storage, request: &Request, id: &str) -> Outcome {
+    let items = storage.load_items()?;
+    let item = &items[find(&items, id)?];
+    let etag = etag(item);
+    if matches_etag(request.if_none_match.as_deref(), &etag) {
+        return Ok(not_modified(etag));
+    }
+    Ok(Response::json(200, item, Some(etag)))
+}
+
+/// Changes the item with `id` and saves the list.
+fn modify(
+    storage: &impl Storage,
+    request: &Request,
+    id: &str,
+    change: impl FnOnce(&mut TodoItem) -> Outcome<()>,
+) -> Outcome {
+    let changed = ops::update(storage, id, |item| {
+        if let Some(expected) = &request.if_match {
+            check_precondition(expected, &etag(item))?;
+        }
+        change(item)
+    })?;
+    let item = changed.ok_or_else(|| not_found(id))?;
+    Ok(Response::json(200, &item, Some(etag(&item))))
+}
+
+fn delete(storage: &impl Storage, request: &Request, id: &str) -> Outcome {
+    let deleted = ops::delete(storage, id, |item| match &request.if_match {
+        Some(expected) => check_precondition(expected, &etag(item)),
+        None => Ok(()),
+    })?;
+    deleted.ok_or_else(|| not_found(id))?;
+    Ok(Response::empty(204))
+}
+
+fn find(items: &[TodoItem], id: &str) -> Outcome<usize> {
+    ops::position(items, id).ok_or_else(|| not_found(id))
+}
+
+fn not_found(id: &str) -> Response {
+    Response::error(404, format!("no item with ID {}", id))
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &str) -> Outcome<T> {
+    serde_json::from_str(body)
+        .map_err(|e| Response::error(400, format!("invalid request body: {}", e)))
+}
+
+fn parse_param<T: std::str::FromStr>(name: &'static str, value: &str) -> Outcome<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value.parse().map_err(bad_param(name))
+}
+
+fn bad_param<E: std::fmt::Display>(name: &'static str) -> impl Fn(E) -> Response {
+    move |e| Response::error(400, format!("invalid {}: {}", name, e))
+}
+
+/// A strong ETag for `value`, stable across runs and builds.
+pub fn etag<T: Serialize + ?Sized>(value: &T) -> String {
+    let json = serde_json::to_vec(value).expect("items serialize");
+    // FNV-1a, for the same reason as the legacy IDs: `DefaultHasher` may change.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in json {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("\"{:016x}\"", hash)
+}
+
+/// Whether an `If-Match` or `If-None-Match` header value names `etag`.
+fn matches_etag(header: Option<&str>, etag: &str) -> bool {
+    header.is_some_and(|header| {
+        header
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag)
+    })
+}
+
+fn check_precondition(expected: &str, current: &str) -> Outcome<()> {
+    if matches_etag(Some(expected), current) {
+        Ok(())
+    } else {
+        Err(Response {
+            etag: Some(current.to_string()),
+            ..Response::error(412, "the item has changed since it was read")
+        })
+    }
+}
+
+fn not_modified(etag: String) -> Response {
+    Response {
+        etag: Some(etag),
+        ..Response::empty(304)
+    }
+}
+
+/// Percent-decodes a path segment or query component, reading `+` as a space.
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use serde_json::Value;
+    use tempfile::TempDir;
+
+    fn local() -> Access {
+        Access::new("127.0.0.1:7373".parse().unwrap(), None).unwrap()
+    }
+
+    /// A request as curl on this machine would send it.
+    fn request(method: &str, url: &str) -> Request {
+        Request {
+            method: method.into(),
+            url: url.into(),
+            host: Some("localhost:7373".into()),
+            content_type: Some("application/json".into()),
+            ..Request::default()
+        }
+    }
+
+    fn send(storage: &FileStorage, method: &str, url: &str, body: &str) -> Response {
+        let request = Request {
+            body: body.into(),
+            ..request(method, url)
+        };
+        handle(storage, &local(), &request)
+    }
+
+    fn body(response: &Response) -> Value {
+        serde_json::from_str(response.body.as_deref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_are_refused_once_the_item_has_changed() {
+        let dir = TempDir::new().unwrap();
+        let storage = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+
+        let created = send(&storage, "POST", "/todos", r#"{"description":"Walk dog"}"#);
+        assert_eq!(created.status, 201);
+        let id = body(&created)["id"].as_str().unwrap().to_string();
+        assert_eq!(created.location, Some(format!("/todos/{}", id)));
+        let url = format!("/todos/{}", id);
+        let read = send(&storage, "GET", &url, "");
+        assert_eq!(read.etag, created.etag);
+
+        let patched = send(&storage, "PATCH", &url, r#"{"priority":1}"#);
+        assert_eq!(body(&patched)["priority"], 1);
+        let stale = handle(
+            &storage,
+            &local(),
+            &Request {
+                if_match: read.etag.clone(),
+                ..request("POST", &format!("{}/complete", url))
+            },
+        );
+        assert_eq!(stale.status, 412);
+        assert_eq!(stale.etag, patched.etag);
+
+        let completed = handle(
+            &storage,
+            &local(),
+            &Request {
+                if_match: patched.etag.clone(),
+                ..request("POST", &format!("{}/complete", url))
+            },
+        );
+        assert_eq!(body(&completed)["done"], true);
+        let unchanged = handle(
+            &storage,
+            &local(),
+            &Request {
+                if_none_match: completed.etag.clone(),
+                ..request("GET", &url)
+            },
+        );
+        assert_eq!(unchanged.status, 304);
+
+        assert_eq!(send(&storage, "DELETE", &url, "").status, 204);
+        assert_eq!(send(&storage, "GET", &url, "").status, 404);
+    }
+
+    #[test]
+    fn list_takes_the_same_filters_as_the_list_command() {
+        let dir = TempDir::new().unwrap();
+        let storage = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+        for body in [
+            r#"{"description":"a","priority":2,"tags":["home work"]}"#,
+            r#"{"description":"b","priority":1,"tags":["home work"]}"#,
+            r#"{"description":"c","priority":1}"#,
+        ] {
+            send(&storage, "POST", "/todos", body);
+        }
+
+        let listed = send(&storage, "GET", "/todos?tag=home+work&sort=priority", "");
+        let descriptions = body(&listed)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["description"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(descriptions, ["b", "a"]);
+
+        assert_eq!(
+            send(&storage, "GET", "/todos?priority=high", "").status,
+            400
+        );
+        assert_eq!(send(&storage, "GET", "/todos?colour=red", "").status, 400);
+        assert_eq!(send(&storage, "PUT", "/todos", "").status, 405);
+        assert_eq!(send(&storage, "GET", "/tasks", "").status, 404);
+        let invalid = send(
+            &storage,
+            "POST",
+            "/todos",
+            r#"{"description":"d","due":"soon"}"#,
+        );
+        assert_eq!(invalid.status, 400);
+    }
+
+    /// Storage that, like a hook, files every new or changed item in the inbox.
+    struct Inbox(FileStorage);
+
+    impl Storage for Inbox {
+        fn load_items(&self) -> crate::error::Result<Vec<TodoItem>> {
+            self.0.load_items()
+        }
+
+        fn save_items(&self, items: &[TodoItem]) -> crate::error::Result<()> {
+            let before = self.0.load_items()?;
+            let items = items
+                .iter()
+                .map(|item| {
+                    if before.contains(item) {
+                        item.clone()
+                    } else {
+                        file(item.clone())
+                    }
+                })
+                .collect::<Vec<_>>();
+            self.0.save_items(&items)
+        }
+
+        fn add_item(&self, item: TodoItem) -> crate::error::Result<()> {
+            self.0.add_item(file(item))
+        }
+    }
+
+    fn file(item: TodoItem) -> TodoItem {
+        TodoItem {
+            tags: Some(vec!["inbox".into()]),
+            ..item
+        }
+    }
+
+    #[test]
+    fn writes_answer_with_the_item_as_stored() {
+        let dir = TempDir::new().unwrap();
+        let storage = Inbox(FileStorage::new(
+            dir.path().join("todo.json").to_str().unwrap(),
+        ));
+        let send = |method: &str, url: &str, body: &str| {
+            let request = Request {
+                body: body.into(),
+                ..request(method, url)
+            };
+            handle(&storage, &local(), &request)
+        };
+
+        let created = send("POST", "/todos", r#"{"description":"Walk dog"}"#);
+        assert_eq!(body(&created)["tags"], json!(["inbox"]));
+        let url = created.location.clone().unwrap();
+        assert_eq!(send("GET", &url, "").etag, created.etag);
+
+        let patched = send("PATCH", &url, r#"{"tags":null}"#);
+        assert_eq!(body(&patched)["tags"], json!(["inbox"]));
+        assert_eq!(send("GET", &url, "").etag, patched.etag);
+    }
+
+    #[test]
+    fn only_this_machine_or_token_holders_are_answered() {
+        let dir = TempDir::new().unwrap();
+        let storage = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+        let status = |access: &Access, request: Request| handle(&storage, access, &request).status;
+
+        assert_eq!(status(&local(), request("GET", "/todos")), 200);
+        for host in ["127.0.0.1:7373", "[::1]:7373"] {
+            let request = Request {
+                host: Some(host.into()),
+                ..request("GET", "/todos")
+            };
+            assert_eq!(status(&local(), request), 200, "{}", host);
+        }
+        // A name rebound to 127.0.0.1 by an attacker's DNS, or the wrong port.
+        for host in [Some("evil.example:7373"), Some("localhost:80"), None] {
+            let request = Request {
+                host: host.map(String::from),
+                ..request("GET", "/todos")
+            };
+            assert_eq!(status(&local(), request), 421, "{:?}", host);
+        }
+        // What a cross-site form can send.
+        let form = Request {
+            content_type: Some("text/plain".into()),
+            body: r#"{"description":"x"}"#.into(),
+            ..request("POST", "/todos")
+        };
+        assert_eq!(status(&local(), form), 415);
+
+        let everywhere = "0.0.0.0:7373".parse().unwrap();
+        assert!(matches!(
+            Access::new(everywhere, None),
+            Err(TodoError::InvalidInput(_))
+        ));
+        let shared = Access::new(everywhere, Some("s3cret".into())).unwrap();
+        let remote = |authorization: Option<&str>| Request {
+            host: Some("192.168.1.20:7373".into()),
+            authorization: authorization.map(String::from),
+            ..request("GET", "/todos")
+        };
+        assert_eq!(status(&shared, remote(None)), 401);
+        assert_eq!(status(&shared, remote(Some("Bearer guess"))), 401);
+        assert_eq!(status(&shared, remote(Some("Bearer s3cret"))), 200);
+    }
+
+    #[test]
+    fn openapi_describes_every_route() {
+        let description: Value = serde_json::from_str(OPENAPI).unwrap();
+        let paths = description["paths"].as_object().unwrap();
+        for path in [
+            "/todos",
+            "/todos/{id}",
+            "/todos/{id}/complete",
+            "/openapi.json",
+        ] {
+            assert!(paths.contains_key(path), "{} is not described", path);
+        }
+    }
+}
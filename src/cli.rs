@@ -1,4 +1,9 @@
+use crate::output::{Column, OutputFormat};
 use clap::{Parser, Subcommand};
+use crix_todo::formats::{ExportFormat, GroupBy, ImportFormat};
+use crix_todo::sort::SortSpec;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "todo")]
@@ -49,8 +54,160 @@ pub enum Commands {
         /// Filter by due date (YYYY-MM-DD)
         #[arg(long)]
         due: Option<String>,
+
+        /// Comma-separated sort keys (due, priority, created, description, done);
+        /// prefix a key with '-' to reverse it
+        #[arg(long)]
+        sort: Option<SortSpec>,
+
+        /// Output format; defaults to human-readable text
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// Comma-separated columns to output, or keys to keep in JSON
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fields: Option<Vec<Column>>,
+    },
+
+    /// Fuzzy search descriptions, notes and tags
+    Search {
+        /// Text to look for
+        query: String,
+
+        /// Include completed tasks
+        #[arg(long)]
+        all: bool,
+
+        /// Output format; defaults to human-readable text
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// Comma-separated columns to output, or keys to keep in JSON
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fields: Option<Vec<Column>>,
+    },
+
+    /// Write todos, including completed ones, to stdout
+    Export {
+        /// Format to write
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// Leave out completed tasks
+        #[arg(long)]
+        pending: bool,
+
+        /// Filter by priority
+        #[arg(long)]
+        priority: Option<u8>,
+
+        /// Filter by tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Filter by due date (YYYY-MM-DD)
+        #[arg(long)]
+        due: Option<String>,
+
+        /// Headings for Markdown output
+        #[arg(long, value_enum, default_value_t)]
+        group_by: GroupBy,
+    },
+
+    /// Import todos from another tool's file
+    Import {
+        /// Format of the file
+        #[arg(long, value_enum)]
+        from: ImportFormat,
+
+        /// File to read
+        file: PathBuf,
+
+        /// Show what would be imported without saving
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Launch TUI editor to complete/edit todos
     Edit,
+
+    /// Serve the list as a JSON REST API over HTTP
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7373")]
+        bind: SocketAddr,
+    },
+
+    /// Answer JSON-RPC requests on stdin, for editor integrations
+    Rpc,
+
+    /// Check the todo file for problems and repair it
+    Doctor {
+        /// Apply the repairs without asking
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Show the change history of the list, or of one item
+    Log {
+        /// ID of the item to follow
+        id: Option<String>,
+    },
+
+    /// Put the list back the way it was at an earlier revision
+    Restore {
+        /// Git revision to restore, as shown by `todo log`
+        #[arg(long)]
+        at: String,
+    },
+
+    /// Serve the list to other commands and TUIs, keeping them in sync
+    Daemon,
+
+    /// Encrypt the todo list with a passphrase or key file
+    Encrypt,
+
+    /// Turn an encrypted todo list back into plain text
+    Decrypt,
+
+    /// Show or change settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Three-way merge of todo.json for git (`todo merge-driver %O %A %B`)
+    MergeDriver {
+        /// Common ancestor version
+        base: PathBuf,
+
+        /// Our version; the merge result is written here
+        ours: PathBuf,
+
+        /// Their version
+        theirs: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ConfigAction {
+    /// Print the value of a setting
+    Get { key: String },
+
+    /// Change a setting in the user's config file
+    Set {
+        key: String,
+        value: String,
+
+        /// Write to the system-wide file instead
+        #[arg(long, conflicts_with = "project")]
+        system: bool,
+
+        /// Write to the project's .crix-todo.toml instead
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// Print every setting, its value and where it comes from
+    List,
 }
@@ -1,4 +1,6 @@
-use crate::storage::{Storage, TodoItem};
+use crix_todo::error::Result;
+use crix_todo::ops::NewItem;
+use crix_todo::storage::Storage;
 
 pub fn run(
     storage: impl Storage,
@@ -7,28 +9,39 @@ pub fn run(
     due: Option<String>,
     tags: Option<Vec<String>>,
     notes: Option<String>,
-) {
-    let item = TodoItem {
+) -> Result<()> {
+    let item = NewItem {
         description,
         priority,
         due,
         tags,
-        done: false,
         notes,
-    };
-
-    match storage.add_item(item) {
-        Ok(_) => println!("Item added successfully"),
-        Err(e) => println!("Error adding item: {}", e),
     }
+    .into_item()?;
+
+    storage.add_item(item)?;
+    println!("Item added successfully");
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::MockStorage;
-    use mockall::predicate::eq;
-    use std::io;
+    use crix_todo::error::TodoError;
+    use crix_todo::storage::{MockStorage, TodoItem};
+
+    // `id` and `created` are generated, so compare everything else
+    fn matches_ignoring_generated(expected: TodoItem) -> impl Fn(&TodoItem) -> bool {
+        move |item| {
+            !item.id.is_empty()
+                && item.created.is_some()
+                && TodoItem {
+                    id: String::new(),
+                    created: None,
+                    ..item.clone()
+                } == expected
+        }
+    }
 
     #[test]
     fn test_add_item_success() {
@@ -39,16 +52,16 @@ mod tests {
             priority: Some(2),
             due: Some("2025-07-09".into()),
             tags: Some(vec!["test".into()]),
-            done: false,
             notes: Some("This is a test".into()),
+            ..TodoItem::default()
         };
 
         mock.expect_add_item()
-            .with(eq(expected_item.clone()))
+            .withf(matches_ignoring_generated(expected_item.clone()))
             .times(1)
             .returning(|_| Ok(()));
 
-        run(
+        let result = run(
             mock,
             expected_item.description.clone(),
             expected_item.priority,
@@ -56,6 +69,7 @@ mod tests {
             expected_item.tags.clone(),
             expected_item.notes.clone(),
         );
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -67,17 +81,16 @@ mod tests {
             priority: Some(1),
             due: Some("2025-07-10".into()),
             tags: Some(vec!["fail".into()]),
-            done: false,
             notes: Some("Should fail".into()),
+            ..TodoItem::default()
         };
 
         mock.expect_add_item()
-            .with(eq(expected_item.clone()))
+            .withf(matches_ignoring_generated(expected_item.clone()))
             .times(1)
-            .returning(|_| Err(io::Error::new(io::ErrorKind::Other, "Simulated failure")));
+            .returning(|_| Err(TodoError::InvalidInput("Simulated failure".into())));
 
-        // shouldn't panic
-        run(
+        let result = run(
             mock,
             expected_item.description.clone(),
             expected_item.priority,
@@ -85,5 +98,15 @@ mod tests {
             expected_item.tags.clone(),
             expected_item.notes.clone(),
         );
+        assert!(matches!(result, Err(TodoError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_add_rejects_malformed_due_date() {
+        let mut mock = MockStorage::new();
+        mock.expect_add_item().times(0);
+
+        let result = run(mock, "x".into(), None, Some("next week".into()), None, None);
+        assert!(matches!(result, Err(TodoError::InvalidInput(_))));
     }
 }
@@ -0,0 +1,126 @@
+use crate::cli::ConfigAction;
+use crix_todo::config::{self, KEYS, Layers, Places};
+use crix_todo::error::{Result, TodoError};
+use toml::Value;
+
+pub fn run(action: ConfigAction) -> Result<()> {
+    let places = Places::standard();
+    let layers = || Layers::load(&places, |name| std::env::var(name).ok());
+    match action {
+        ConfigAction::Get { key } => {
+            let key = config::key(&key)?;
+            if let Some((value, _)) = layers()?.get(key) {
+                println!("{}", plain(value));
+            }
+        }
+        ConfigAction::Set {
+            key,
+            value,
+            system,
+            project,
+        } => {
+            let key = config::key(&key)?;
+            if project && config::USER_ONLY.contains(&key.name) {
+                return Err(TodoError::InvalidInput(format!(
+                    "{} cannot be set in a project file; set it in your user config instead",
+                    key.name
+                )));
+            }
+            let file = if system {
+                places.system.clone()
+            } else if project {
+                let cwd = std::env::current_dir()
+                    .map_err(|e| TodoError::io(std::path::Path::new("."), e))?;
+                Some(
+                    places
+                        .project
+                        .clone()
+                        .unwrap_or(cwd.join(config::PROJECT_FILE)),
+                )
+            } else {
+                places.user.clone()
+            }
+            .ok_or_else(|| {
+                TodoError::InvalidInput("there is no config file to write to here".into())
+            })?;
+            config::set(&file, key, &value)?;
+            println!("Set {} in {}", key.name, file.display());
+            if std::env::var(key.env).is_ok_and(|v| !v.is_empty()) {
+                println!("note: ${} overrides it for now", key.env);
+            }
+        }
+        ConfigAction::List => print!("{}", list(&layers()?)),
+    }
+    Ok(())
+}
+
+/// Every key as `key = value  # source`, lined up.
+fn list(layers: &Layers) -> String {
+    let rows = KEYS
+        .iter()
+        .map(|key| match layers.get(key) {
+            Some((value, source)) => (format!("{} = {}", key.name, value), source.to_string()),
+            None => (key.name.to_string(), "not set".to_string()),
+        })
+        .collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|(setting, _)| setting.len())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(setting, source)| format!("{:<width$}  # {}\n", setting, source, width = width))
+        .collect()
+}
+
+/// A value as a user would type it back into `todo config set`.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn list_shows_every_key_with_its_source() {
+        let dir = TempDir::new().unwrap();
+        let user = dir.path().join("config.toml");
+        fs::write(&user, "[defaults]\npriority = 3\n").unwrap();
+        let places = Places {
+            user: Some(user.clone()),
+            ..Places::default()
+        };
+        let env = |name: &str| (name == "CRIX_TODO_HISTORY").then(|| "git".to_string());
+        let listing = list(&Layers::load(&places, env).unwrap());
+
+        let line = |name: &str| {
+            listing
+                .lines()
+                .find(|line| line.starts_with(name))
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(
+            line("storage.backend"),
+            "storage.backend = \"json\" # default"
+        );
+        assert_eq!(
+            line("storage.history"),
+            "storage.history = \"git\" # $CRIX_TODO_HISTORY"
+        );
+        assert_eq!(
+            line("defaults.priority"),
+            format!("defaults.priority = 3 # {}", user.display())
+        );
+        assert_eq!(line("defaults.sort"), "defaults.sort # not set");
+        assert_eq!(listing.lines().count(), KEYS.len());
+    }
+}
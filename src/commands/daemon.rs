@@ -0,0 +1,19 @@
+use crix_todo::daemon;
+use crix_todo::error::Result;
+use crix_todo::storage::Storage;
+use std::path::Path;
+
+/// Serves the list at `path` until stopped.
+pub fn run(storage: impl Storage, path: &Path) -> Result<()> {
+    // Fail now, and ask for a passphrase now, rather than on the first request.
+    let count = storage.load_items()?.len();
+    let socket = daemon::socket_path(&path.to_string_lossy());
+    let listener = daemon::bind(&socket)?;
+    println!(
+        "Serving {} ({} items) on {}; press Ctrl-C to stop",
+        path.display(),
+        count,
+        socket.display()
+    );
+    daemon::serve(storage, listener, path)
+}
@@ -0,0 +1,247 @@
+use crix_todo::doctor::{self, Diagnosis};
+use crix_todo::error::{Result, TodoError};
+use crix_todo::storage::{Backend, DirStorage, FileStorage, Storage};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Checks the list at `path` and repairs it with `fix`, or after asking.
+///
+/// A JSON file, or each file of a `dir` list, is read as text, so that it can
+/// be salvaged even when it no longer parses; other backends are checked
+/// through `storage`.
+pub fn run(storage: impl Storage, backend: Backend, path: &Path, fix: bool) -> Result<()> {
+    let mut unreadable = Vec::new();
+    let diagnosis = match backend {
+        Backend::Json => match fs::read_to_string(path) {
+            Ok(text) => doctor::diagnose_json(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                println!("{}: no todo file yet, nothing to check", path.display());
+                return Ok(());
+            }
+            Err(e) => return Err(TodoError::io(path, e)),
+        },
+        Backend::Dir => diagnose_dir(path, &mut unreadable)?,
+        _ => {
+            let items = storage.load_items()?;
+            doctor::check_items(items.into_iter().map(|item| (None, item)).collect())
+        }
+    };
+
+    if diagnosis.problems.is_empty() {
+        println!("{}: no problems found", path.display());
+        return Ok(());
+    }
+    for problem in &diagnosis.problems {
+        println!("{}: {}", path.display(), problem);
+    }
+
+    let count = diagnosis.problems.len();
+    if !fix && !confirm(count)? {
+        return Err(TodoError::InvalidInput(format!(
+            "{} problem{} left; run `todo doctor --fix` to repair",
+            count,
+            if count == 1 { "" } else { "s" }
+        )));
+    }
+    repair(storage, backend, path, diagnosis, &unreadable)
+}
+
+/// Diagnoses each item file in `dir` on its own, adding those that cannot be
+/// loaded as they are to `unreadable`.
+fn diagnose_dir(dir: &Path, unreadable: &mut Vec<PathBuf>) -> Result<Diagnosis> {
+    let mut files = DirStorage::new(&dir.to_string_lossy()).item_files()?;
+    files.sort();
+
+    let mut problems = Vec::new();
+    let mut items = Vec::new();
+    for (path, id) in files {
+        let text = fs::read_to_string(&path).map_err(|e| TodoError::io(&path, e))?;
+        let (item, mut found) = doctor::diagnose_item(&text);
+        if !found.is_empty() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            for problem in &mut found {
+                problem.file = Some(name.to_string());
+            }
+            problems.append(&mut found);
+            unreadable.push(path);
+        }
+        if let Some(mut item) = item {
+            if item.id.is_empty() {
+                item.id = id;
+            }
+            items.push((None, item));
+        }
+    }
+
+    let mut diagnosis = doctor::check_items(items);
+    problems.append(&mut diagnosis.problems);
+    diagnosis.problems = problems;
+    Ok(diagnosis)
+}
+
+fn confirm(count: usize) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    print!(
+        "Apply {} fix{}? [y/N] ",
+        count,
+        if count == 1 { "" } else { "es" }
+    );
+    io::stdout().flush().map_err(TodoError::Terminal)?;
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(TodoError::Terminal)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn repair(
+    storage: impl Storage,
+    backend: Backend,
+    path: &Path,
+    diagnosis: Diagnosis,
+    unreadable: &[PathBuf],
+) -> Result<()> {
+    let backup = backup_path(path);
+    let backed_up = if path.is_file() {
+        fs::copy(path, &backup).map_err(|e| TodoError::io(&backup, e))?;
+        true
+    } else if backend == Backend::Dir && path.is_dir() {
+        copy_dir(path, &backup)?;
+        true
+    } else {
+        false
+    };
+
+    // JSON and `dir` lists are written directly: they may be too broken to
+    // load, and items that are encrypted are kept exactly as they are.
+    match backend {
+        Backend::Json => FileStorage::new(&path.to_string_lossy()).save_items(&diagnosis.items)?,
+        Backend::Dir => {
+            for file in unreadable {
+                fs::remove_file(file).map_err(|e| TodoError::io(file, e))?;
+            }
+            DirStorage::new(&path.to_string_lossy()).save_items(&diagnosis.items)?;
+        }
+        _ => storage.save_items(&diagnosis.items)?,
+    }
+
+    print!("Repaired {}", path.display());
+    if backed_up {
+        print!("; the original is in {}", backup.display());
+    }
+    println!();
+    Ok(())
+}
+
+/// `<path>.bak`, or `<path>.bak.N` for the first N not taken, so that the
+/// backup of an earlier repair is never overwritten.
+fn backup_path(path: &Path) -> PathBuf {
+    let name = format!(
+        "{}.bak",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let mut backup = path.with_file_name(&name);
+    let mut n = 1;
+    while backup.exists() {
+        backup = path.with_file_name(format!("{}.{}", name, n));
+        n += 1;
+    }
+    backup
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to).map_err(|e| TodoError::io(to, e))?;
+    let entries = fs::read_dir(from).map_err(|e| TodoError::io(from, e))?;
+    for entry in entries {
+        let source = entry.map_err(|e| TodoError::io(from, e))?.path();
+        if source.is_file() {
+            let target = to.join(source.file_name().unwrap_or_default());
+            fs::copy(&source, &target).map_err(|e| TodoError::io(&target, e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crix_todo::storage::MockStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn fix_salvages_a_broken_file_and_keeps_a_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        let broken = "[\n  {\"id\": \"a\", \"description\": \"One\", \"done\": false},\n]";
+        fs::write(&path, broken).unwrap();
+
+        run(MockStorage::new(), Backend::Json, &path, true).unwrap();
+
+        let items = FileStorage::new(path.to_str().unwrap())
+            .load_items()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("todo.json.bak")).unwrap(),
+            broken
+        );
+        // A repaired file has nothing left to report.
+        run(MockStorage::new(), Backend::Json, &path, false).unwrap();
+    }
+
+    #[test]
+    fn earlier_backups_are_kept() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        fs::write(&path, "[{\"description\": \"One\", \"done\": false,}]").unwrap();
+        run(MockStorage::new(), Backend::Json, &path, true).unwrap();
+        fs::write(&path, "[{\"description\": \"Two\", \"done\": false,}]").unwrap();
+        run(MockStorage::new(), Backend::Json, &path, true).unwrap();
+
+        let first = fs::read_to_string(dir.path().join("todo.json.bak")).unwrap();
+        let second = fs::read_to_string(dir.path().join("todo.json.bak.1")).unwrap();
+        assert!(first.contains("One"));
+        assert!(second.contains("Two"));
+    }
+
+    #[test]
+    fn fix_repairs_a_broken_item_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".todo");
+        fs::create_dir(&path).unwrap();
+        let good = "{\"id\": \"a\", \"description\": \"One\", \"done\": false}";
+        let broken = "{\"id\": \"b\", \"description\": \"Two\", \"done\": true,}";
+        fs::write(path.join("a.json"), good).unwrap();
+        fs::write(path.join("b.json"), broken).unwrap();
+        fs::write(path.join("c.json"), "{\"description\": ").unwrap();
+        let storage = DirStorage::new(path.to_str().unwrap());
+        assert!(storage.load_items().is_err());
+
+        run(MockStorage::new(), Backend::Dir, &path, true).unwrap();
+
+        let items = storage.load_items().unwrap();
+        let ids: Vec<&str> = items.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(items[1].done);
+        assert_eq!(fs::read_to_string(path.join("a.json")).unwrap(), good);
+        let backup = dir.path().join(".todo.bak");
+        assert_eq!(fs::read_to_string(backup.join("b.json")).unwrap(), broken);
+        assert!(backup.join("c.json").exists());
+        run(MockStorage::new(), Backend::Dir, &path, false).unwrap();
+    }
+
+    #[test]
+    fn problems_without_fix_are_an_error() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        fs::write(&path, "[{\"description\": \"One\", \"done\": false,}]").unwrap();
+
+        let err = run(MockStorage::new(), Backend::Json, &path, false).unwrap_err();
+        assert!(err.to_string().contains("todo doctor --fix"));
+        assert!(!dir.path().join("todo.json.bak").exists());
+    }
+}
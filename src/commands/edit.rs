@@ -1,10 +1,16 @@
-use std::io;
-use std::time::Duration;
+use std::io::{self, Stdout};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-use crate::storage::Storage;
+use crate::tui::app::Status;
+use crate::tui::state::field_buffer::{FieldBuffer, FieldMode};
 use crate::tui::{app::App, events::poll_input, ui::render};
+use crix_todo::config::Config;
+use crix_todo::error::{Result, TodoError};
+use crix_todo::storage::Storage;
+use crix_todo::watcher::Changes;
 
-use crate::tui::app::InputMode::{Editing, Normal};
+use crate::tui::app::InputMode::{Editing, Normal, Searching};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
@@ -12,68 +18,192 @@ use crossterm::{
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
 
-pub fn run(storage: impl Storage) {
-    if let Err(e) = launch_ui(storage) {
-        eprintln!("Error: {}", e);
-    }
-}
+type Term = Terminal<CrosstermBackend<Stdout>>;
 
-fn launch_ui(storage: impl Storage) -> Result<(), Box<dyn std::error::Error>> {
+/// Runs the TUI on `storage`, reloading it whenever the list at `path`
+/// changes underneath.
+pub fn run(storage: impl Storage, path: &Path, config: Config) -> Result<()> {
+    let mut changes = Changes::new(path);
     let todos = storage.load_items()?;
-    let mut app = App::new(todos);
+    let mut app = App::new(todos).with_config(config)?;
+
+    let mut terminal = setup_terminal().map_err(TodoError::Terminal)?;
+    let result = launch_ui(&mut terminal, &mut app, &storage, &mut changes);
+    // restore the terminal even when the loop failed, so errors are readable
+    restore_terminal(&mut terminal).map_err(TodoError::Terminal)?;
+    result
+}
 
+fn setup_terminal() -> io::Result<Term> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
 
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    Terminal::new(backend)
+}
+
+fn restore_terminal(terminal: &mut Term) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()
+}
+
+/// Merges in changes made outside the TUI, if there are any.
+fn reload_if_changed(app: &mut App, storage: &impl Storage, changes: &mut Changes) {
+    if !changes.changed() {
+        return;
+    }
+    match storage.load_items() {
+        // Our own save, as reported back by the daemon.
+        Ok(items) if items == app.on_disk => {}
+        Ok(items) => app.reload(items, Instant::now()),
+        Err(e) => app.status = Some(Status::Error(format!("Reload failed: {}", e))),
+    }
+}
 
+/// Saves without overwriting changes made outside the TUI since the last
+/// reload, and without mistaking our own write for one of those.
+fn save(app: &mut App, storage: &impl Storage, changes: &mut Changes) -> Result<()> {
+    reload_if_changed(app, storage, changes);
+    let result = app.save(storage);
+    changes.sync();
+    // Hooks may have changed or refused part of what was saved.
+    if let Ok(saved) = storage.load_items()
+        && saved != app.todos
+    {
+        app.replace_with_saved(saved, Instant::now());
+    }
+    result
+}
+
+fn launch_ui(
+    terminal: &mut Term,
+    app: &mut App,
+    storage: &impl Storage,
+    changes: &mut Changes,
+) -> Result<()> {
     loop {
-        terminal.draw(|f| render(f, &app))?;
+        app.expire_status(Instant::now());
+        // An open edit modal would be left pointing at the wrong item, so
+        // outside changes wait until it is closed.
+        if app.mode != Editing {
+            reload_if_changed(app, storage, changes);
+        }
 
+        terminal
+            .draw(|f| render(f, app))
+            .map_err(TodoError::Terminal)?;
+
+        let event = poll_input(Duration::from_millis(200), app).map_err(TodoError::Terminal)?;
+        let count = app.keymap.take_count();
+        let times = count.unwrap_or(1);
         match app.mode {
-            Normal => match poll_input(Duration::from_millis(200), Normal)? {
+            Normal => match event {
                 crate::tui::events::InputEvent::Quit => {
-                    app.save(&storage);
-                    break;
+                    // a failure here is reported on stderr once the screen is restored
+                    return save(app, storage, changes);
+                }
+                crate::tui::events::InputEvent::Down => {
+                    app.go_to(app.selected.saturating_add(times))
+                }
+                crate::tui::events::InputEvent::Up => app.go_to(app.selected.saturating_sub(times)),
+                crate::tui::events::InputEvent::Top => app.go_to(count.map_or(0, |n| n - 1)),
+                crate::tui::events::InputEvent::Bottom => {
+                    app.go_to(count.map_or(usize::MAX, |n| n - 1))
                 }
-                crate::tui::events::InputEvent::Down => app.next(),
-                crate::tui::events::InputEvent::Up => app.previous(),
                 crate::tui::events::InputEvent::ToggleDone => {
                     app.toggle_done();
-                    app.save(&storage);
+                    // failures are shown in the status line
+                    let _ = save(app, storage, changes);
                 }
                 crate::tui::events::InputEvent::ToggleExpand => {
                     app.toggle_expanded();
                 }
-                crate::tui::events::InputEvent::Backspace => app.remove_selected(),
+                crate::tui::events::InputEvent::Backspace => match count {
+                    Some(count) => app.remove_below(count),
+                    None => app.remove_selected(),
+                },
                 crate::tui::events::InputEvent::EnableEditing => app.toggle_mode(),
-                crate::tui::events::InputEvent::PromotePriority => app.promote_selected(),
-                crate::tui::events::InputEvent::DemotePriority => app.demote_selected(),
+                crate::tui::events::InputEvent::AddBelow => app.add_below(),
+                crate::tui::events::InputEvent::PromotePriority => {
+                    (0..times).for_each(|_| app.promote_selected())
+                }
+                crate::tui::events::InputEvent::DemotePriority => {
+                    (0..times).for_each(|_| app.demote_selected())
+                }
+                crate::tui::events::InputEvent::StartSearch => app.start_search(),
+                crate::tui::events::InputEvent::NextMatch => {
+                    (0..times).for_each(|_| app.next_match())
+                }
+                crate::tui::events::InputEvent::PreviousMatch => {
+                    (0..times).for_each(|_| app.previous_match())
+                }
+                crate::tui::events::InputEvent::CycleSort => app.cycle_sort(),
+                crate::tui::events::InputEvent::Undo => {
+                    (0..times).for_each(|_| app.undo(Instant::now()))
+                }
+                crate::tui::events::InputEvent::Redo => {
+                    (0..times).for_each(|_| app.redo(Instant::now()))
+                }
                 _ => {}
             },
-            Editing => match poll_input(Duration::from_millis(200), Editing)? {
-                crate::tui::events::InputEvent::Down => app.next(),
-                crate::tui::events::InputEvent::Up => app.previous(),
-                crate::tui::events::InputEvent::Left => app.left(),
-                crate::tui::events::InputEvent::Right => app.right(),
+            Editing => match event {
+                crate::tui::events::InputEvent::Down => (0..times).for_each(|_| app.next()),
+                crate::tui::events::InputEvent::Up => (0..times).for_each(|_| app.previous()),
+                crate::tui::events::InputEvent::Left => (0..times).for_each(|_| app.left()),
+                crate::tui::events::InputEvent::Right => (0..times).for_each(|_| app.right()),
+                crate::tui::events::InputEvent::WordForward => {
+                    (0..times).for_each(|_| app.edit_field(FieldBuffer::word_forward))
+                }
+                crate::tui::events::InputEvent::WordBackward => {
+                    (0..times).for_each(|_| app.edit_field(FieldBuffer::word_backward))
+                }
+                crate::tui::events::InputEvent::WordEnd => {
+                    (0..times).for_each(|_| app.edit_field(FieldBuffer::word_end))
+                }
+                crate::tui::events::InputEvent::LineStart => {
+                    app.edit_field(FieldBuffer::move_to_start)
+                }
+                crate::tui::events::InputEvent::LineEnd => {
+                    app.edit_field(FieldBuffer::move_to_last)
+                }
+                crate::tui::events::InputEvent::DeleteChar => {
+                    (0..times).for_each(|_| app.edit_field(FieldBuffer::delete_char))
+                }
+                crate::tui::events::InputEvent::NormalMode => app.set_field_mode(FieldMode::Normal),
+                crate::tui::events::InputEvent::Insert => app.set_field_mode(FieldMode::Insert),
+                crate::tui::events::InputEvent::Append => {
+                    app.edit_field(FieldBuffer::move_right);
+                    app.set_field_mode(FieldMode::Insert);
+                }
+                crate::tui::events::InputEvent::InsertAtStart => {
+                    app.edit_field(FieldBuffer::move_to_start);
+                    app.set_field_mode(FieldMode::Insert);
+                }
+                crate::tui::events::InputEvent::AppendAtEnd => {
+                    app.edit_field(FieldBuffer::reset_cursor);
+                    app.set_field_mode(FieldMode::Insert);
+                }
                 crate::tui::events::InputEvent::DisableEditing => app.toggle_mode(),
                 crate::tui::events::InputEvent::Backspace => app.edit_backspace(),
                 crate::tui::events::InputEvent::ToggleDone => app.toggle_done(),
                 crate::tui::events::InputEvent::Char(c) => app.edit_insert(c),
                 _ => {}
             },
+            Searching => match event {
+                crate::tui::events::InputEvent::Down => app.next(),
+                crate::tui::events::InputEvent::Up => app.previous(),
+                crate::tui::events::InputEvent::ConfirmSearch => app.confirm_search(),
+                crate::tui::events::InputEvent::CancelSearch => app.cancel_search(),
+                crate::tui::events::InputEvent::Backspace => app.search_backspace(),
+                crate::tui::events::InputEvent::Char(c) => app.search_insert(c),
+                _ => {}
+            },
         }
     }
-
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
-    Ok(())
 }
@@ -0,0 +1,8 @@
+use crix_todo::error::Result;
+use crix_todo::storage::{EncryptedStorage, Storage};
+
+pub fn run(storage: EncryptedStorage<impl Storage>) -> Result<()> {
+    let count = storage.encrypt()?;
+    println!("Encrypted {} items", count);
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crix_todo::error::Result;
+use crix_todo::filter::ListFilter;
+use crix_todo::formats::{self, ExportFormat, GroupBy};
+use crix_todo::storage::{Storage, TodoItem};
+
+pub fn run(
+    storage: impl Storage,
+    format: ExportFormat,
+    filter: ListFilter,
+    group_by: GroupBy,
+) -> Result<()> {
+    let items: Vec<TodoItem> = storage
+        .load_items()?
+        .into_iter()
+        .filter(|item| filter.matches(item))
+        .collect();
+    print!("{}", formats::render(format, &items, group_by));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crix_todo::error::TodoError;
+    use crix_todo::storage::MockStorage;
+
+    #[test]
+    fn load_errors_are_returned() {
+        let mut mock = MockStorage::new();
+        mock.expect_load_items()
+            .returning(|| Err(TodoError::InvalidInput("boom".into())));
+
+        let result = run(
+            mock,
+            ExportFormat::Ics,
+            ListFilter::default(),
+            GroupBy::Priority,
+        );
+        assert!(result.is_err());
+    }
+}
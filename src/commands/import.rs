@@ -0,0 +1,160 @@
+use crix_todo::error::{Result, TodoError};
+use crix_todo::formats::{self, ImportFormat};
+use crix_todo::storage::{Storage, TodoItem, new_id, now_timestamp};
+use std::collections::HashSet;
+use std::path::Path;
+
+pub fn run(storage: impl Storage, from: ImportFormat, file: &Path, dry_run: bool) -> Result<()> {
+    let input = std::fs::read_to_string(file).map_err(|e| TodoError::io(file, e))?;
+    let imported = formats::parse(from, &input)
+        .map_err(|message| TodoError::InvalidInput(format!("{}: {}", file.display(), message)))?;
+
+    for notice in &imported.notices {
+        println!("{}: {}", notice.location, notice.message);
+    }
+
+    let mut items = storage.load_items()?;
+    let (new_items, duplicates) = dedup(&items, imported.items);
+
+    if dry_run {
+        for item in &new_items {
+            println!("+ {}", item.description);
+        }
+        println!(
+            "Would import {} items ({} duplicates, {} entries skipped)",
+            new_items.len(),
+            duplicates,
+            imported.skipped
+        );
+        return Ok(());
+    }
+
+    let count = new_items.len();
+    if count > 0 {
+        items.extend(new_items);
+        storage.save_items(&items)?;
+    }
+    println!(
+        "Imported {} items ({} duplicates, {} entries skipped)",
+        count, duplicates, imported.skipped
+    );
+    Ok(())
+}
+
+/// Drops incoming items that match an existing one, or an earlier incoming
+/// one, by ID or by description and due date, and fills in `id` and
+/// `created` on the rest. Returns the new items and the number dropped.
+fn dedup(existing: &[TodoItem], incoming: Vec<TodoItem>) -> (Vec<TodoItem>, usize) {
+    let mut ids: HashSet<String> = existing.iter().map(|i| i.id.clone()).collect();
+    let mut keys: HashSet<(String, Option<String>)> = existing.iter().map(dedup_key).collect();
+    let mut fresh = Vec::new();
+    let mut duplicates = 0;
+
+    for mut item in incoming {
+        let id_taken = !item.id.is_empty() && ids.contains(&item.id);
+        if id_taken || !keys.insert(dedup_key(&item)) {
+            duplicates += 1;
+            continue;
+        }
+        if item.id.is_empty() {
+            item.id = new_id();
+        }
+        item.created.get_or_insert_with(now_timestamp);
+        ids.insert(item.id.clone());
+        fresh.push(item);
+    }
+
+    (fresh, duplicates)
+}
+
+// Case and spacing differences between tools should not defeat dedup.
+fn dedup_key(item: &TodoItem) -> (String, Option<String>) {
+    let description = item
+        .description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    (description, item.due.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crix_todo::storage::MockStorage;
+    use std::io::Write;
+
+    fn item(id: &str, description: &str, due: Option<&str>) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: description.into(),
+            due: due.map(String::from),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn dedup_against_existing_and_batch() {
+        let existing = vec![item("aaaa0001", "Buy milk", None)];
+        let incoming = vec![
+            item("", "buy   MILK", None),
+            item("", "Buy milk", Some("2025-01-01")),
+            item("", "Buy milk", Some("2025-01-01")),
+            item("aaaa0001", "Renamed", None),
+            item("", "Walk dog", None),
+        ];
+
+        let (fresh, duplicates) = dedup(&existing, incoming);
+
+        assert_eq!(duplicates, 3);
+        let descriptions: Vec<_> = fresh.iter().map(|i| i.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["Buy milk", "Walk dog"]);
+        assert!(
+            fresh
+                .iter()
+                .all(|i| !i.id.is_empty() && i.created.is_some())
+        );
+    }
+
+    #[test]
+    fn run_saves_existing_plus_new() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "(A) Buy milk +shop\nWalk dog").unwrap();
+
+        let mut mock = MockStorage::new();
+        mock.expect_load_items()
+            .returning(|| Ok(vec![item("aaaa0001", "Walk dog", None)]));
+        mock.expect_save_items()
+            .withf(|items| {
+                items.len() == 2
+                    && items[1].description == "Buy milk"
+                    && items[1].priority == Some(0)
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        assert!(run(mock, ImportFormat::Todotxt, file.path(), false).is_ok());
+    }
+
+    #[test]
+    fn dry_run_does_not_save() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "Buy milk").unwrap();
+
+        let mut mock = MockStorage::new();
+        mock.expect_load_items().returning(|| Ok(vec![]));
+        mock.expect_save_items().times(0);
+
+        assert!(run(mock, ImportFormat::Todotxt, file.path(), true).is_ok());
+    }
+
+    #[test]
+    fn unreadable_input_is_invalid_input() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "not json").unwrap();
+
+        let mock = MockStorage::new();
+        let result = run(mock, ImportFormat::Taskwarrior, file.path(), false);
+        assert!(matches!(result, Err(TodoError::InvalidInput(_))));
+    }
+}
@@ -1,54 +1,42 @@
-use crate::storage::{Storage, TodoItem};
+use crate::output::{self, Column, OutputFormat, PrettyOptions};
+use crate::style;
+use crix_todo::config::Config;
+use crix_todo::error::Result;
+use crix_todo::filter::ListFilter;
+use crix_todo::sort::SortSpec;
+use crix_todo::storage::Storage;
 
 pub fn run(
     storage: impl Storage,
-    show_all: bool,
-    filter_priority: Option<u8>,
-    filter_tag: Option<String>,
-    filter_due: Option<String>,
-) {
-    match storage.load_items() {
-        Ok(items) => {
-            let filtered = items
-                .into_iter()
-                .enumerate()
-                .filter(|(_, item)| {
-                    (show_all || !item.done)
-                        && filter_priority.map_or(true, |p| item.priority == Some(p))
-                        && filter_tag.as_ref().map_or(true, |tag| {
-                            item.tags.as_ref().map_or(false, |tags| tags.contains(tag))
-                        })
-                        && filter_due
-                            .as_ref()
-                            .map_or(true, |due| item.due.as_deref() == Some(due.as_str()))
-                })
-                .collect::<Vec<_>>();
+    filter: ListFilter,
+    sort: Option<SortSpec>,
+    format: Option<OutputFormat>,
+    fields: Option<Vec<Column>>,
+    config: &Config,
+) -> Result<()> {
+    let items = storage.load_items()?;
+    let mut rows = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| filter.matches(item))
+        .collect::<Vec<_>>();
 
-            if filtered.is_empty() {
-                println!("No matching todos.");
-            } else {
-                for (i, item) in filtered {
-                    print_item(i, &item);
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to load todos: {}", e);
-        }
+    if let Some(spec) = sort.as_ref().or(config.defaults.sort.as_ref()) {
+        rows.sort_by(|(_, a), (_, b)| spec.compare(a, b));
     }
-}
-
-fn print_item(index: usize, item: &TodoItem) {
-    let status = if item.done { "[X]" } else { "[ ]" };
-    println!("{}. {} {}", index + 1, status, item.description);
 
-    if let Some(p) = item.priority {
-        println!("   Priority: {}", p);
-    }
-    if let Some(due) = &item.due {
-        println!("   Due: {}", due);
-    }
-    if let Some(tags) = &item.tags {
-        println!("   Tags: {:?}", tags);
+    if let Some(format) = format {
+        print!("{}", output::render(format, fields.as_deref(), &rows));
+    } else if rows.is_empty() {
+        println!("No matching todos.");
+    } else {
+        let options = PrettyOptions {
+            today: chrono::Local::now().date_naive(),
+            width: style::terminal_width(),
+            styled: style::enabled(config.display.color),
+            config: config.clone(),
+        };
+        print!("{}", output::pretty(&rows, &options));
     }
+    Ok(())
 }
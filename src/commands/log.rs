@@ -0,0 +1,22 @@
+use crix_todo::error::Result;
+use crix_todo::storage::history;
+use std::path::Path;
+
+/// Prints the commits that changed the list, or with `id` just that item.
+pub fn run(path: &Path, id: Option<String>) -> Result<()> {
+    let revisions = history::log(path, id.as_deref())?;
+    if revisions.is_empty() {
+        println!("No history yet");
+    }
+    for revision in revisions {
+        match id {
+            Some(_) => {
+                for change in &revision.changes {
+                    println!("{}  {}  {}", revision.rev, revision.date, change);
+                }
+            }
+            None => println!("{}  {}  {}", revision.rev, revision.date, revision.subject),
+        }
+    }
+    Ok(())
+}
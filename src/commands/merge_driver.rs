@@ -0,0 +1,104 @@
+use crix_todo::error::{Result, TodoError};
+use crix_todo::merge;
+use crix_todo::storage::{FileStorage, Storage};
+use std::fs;
+use std::path::Path;
+
+/// Git merge driver for `todo.json`: merges `ours` and `theirs` against
+/// `base` and leaves the result in `ours`, as git expects.
+pub fn run(base: &Path, ours: &Path, theirs: &Path) -> Result<()> {
+    let load = |path: &Path| FileStorage::new(&path.to_string_lossy()).load_items();
+    let merged = merge::merge(&load(base)?, &load(ours)?, &load(theirs)?);
+
+    fs::write(ours, merged.render()).map_err(|e| TodoError::io(ours, e))?;
+
+    match merged.conflicts() {
+        0 => Ok(()),
+        1 => Err(TodoError::Conflict(format!(
+            "{}: 1 conflicting item left to resolve",
+            ours.display()
+        ))),
+        n => Err(TodoError::Conflict(format!(
+            "{}: {} conflicting items left to resolve",
+            ours.display(),
+            n
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn writes_merge_to_ours_and_reports_conflicts() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path().join("base");
+        let ours = dir.path().join("ours");
+        let theirs = dir.path().join("theirs");
+        fs::write(
+            &base,
+            r#"[{"id":"a","description":"Write report","done":false}]"#,
+        )
+        .unwrap();
+        fs::write(
+            &ours,
+            r#"[{"id":"a","description":"Write report","done":true}]"#,
+        )
+        .unwrap();
+        fs::write(
+            &theirs,
+            r#"[{"id":"a","description":"Write report","done":false,"priority":1}]"#,
+        )
+        .unwrap();
+
+        run(&base, &ours, &theirs).unwrap();
+        let items = FileStorage::new(ours.to_str().unwrap())
+            .load_items()
+            .unwrap();
+        assert!(items[0].done);
+        assert_eq!(items[0].priority, Some(1));
+
+        fs::write(
+            &theirs,
+            r#"[{"id":"a","description":"Write the report","done":false}]"#,
+        )
+        .unwrap();
+        fs::write(
+            &ours,
+            r#"[{"id":"a","description":"Write a report","done":false}]"#,
+        )
+        .unwrap();
+        let err = run(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+        assert!(fs::read_to_string(&ours).unwrap().contains("<<<<<<< ours"));
+    }
+
+    #[test]
+    fn items_without_ids_are_matched_by_description() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path().join("base");
+        let ours = dir.path().join("ours");
+        let theirs = dir.path().join("theirs");
+        let todo = |description: &str, done: bool| {
+            format!(r#"{{"description":"{}","done":{}}}"#, description, done)
+        };
+        let list = |items: &[String]| format!("[{}]", items.join(","));
+        let (a, b, c) = (todo("A", false), todo("B", false), todo("C", false));
+        fs::write(&base, list(&[a.clone(), b.clone(), c.clone()])).unwrap();
+        // we deleted the first item, they completed the last one
+        fs::write(&ours, list(&[b.clone(), c])).unwrap();
+        fs::write(&theirs, list(&[a, b, todo("C", true)])).unwrap();
+
+        run(&base, &ours, &theirs).unwrap();
+        let items = FileStorage::new(ours.to_str().unwrap())
+            .load_items()
+            .unwrap();
+        let merged: Vec<(&str, bool)> = items
+            .iter()
+            .map(|i| (i.description.as_str(), i.done))
+            .collect();
+        assert_eq!(merged, vec![("B", false), ("C", true)]);
+    }
+}
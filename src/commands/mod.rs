@@ -1,3 +1,17 @@
 pub mod add;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod decrypt;
+pub mod doctor;
 pub mod edit;
+pub mod encrypt;
+pub mod export;
+pub mod import;
 pub mod list;
+pub mod log;
+pub mod merge_driver;
+pub mod restore;
+pub mod rpc;
+pub mod search;
+pub mod serve;
@@ -0,0 +1,12 @@
+use crix_todo::error::Result;
+use crix_todo::storage::history;
+use std::path::Path;
+
+pub fn run(path: &Path, rev: String) -> Result<()> {
+    if history::restore(path, &rev)? {
+        println!("Restored {} as of {}", path.display(), rev);
+    } else {
+        println!("{} already matches {}", path.display(), rev);
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crix_todo::error::{Result, TodoError};
+use crix_todo::rpc::{self, Session};
+use crix_todo::storage::Storage;
+use crix_todo::watcher::Changes;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Answers JSON-RPC requests on stdin until it is closed.
+pub fn run(storage: impl Storage, path: &Path) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if tx.send(line).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut session = Session::new(storage);
+    let mut changes = Changes::new(path);
+    let mut stdout = io::stdout().lock();
+    let write_error = |e| TodoError::io(Path::new("<stdout>"), e);
+    loop {
+        let reply = match rx.recv_timeout(Duration::from_millis(250)) {
+            Ok(line) => {
+                let line = line.map_err(|e| TodoError::io(Path::new("<stdin>"), e))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                session.handle(&line)
+            }
+            // Checked even when not subscribed, so a later subscriber is
+            // not told about a change made before it asked.
+            Err(RecvTimeoutError::Timeout) => {
+                (changes.changed() && session.subscribed()).then(rpc::changed_notification)
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        if let Some(reply) = reply {
+            writeln!(stdout, "{}", reply).map_err(write_error)?;
+            stdout.flush().map_err(write_error)?;
+        }
+    }
+}
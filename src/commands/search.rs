@@ -0,0 +1,112 @@
+use crate::output::{self, Column, OutputFormat};
+use crate::style::{self, BOLD, RESET};
+use crix_todo::config::Config;
+use crix_todo::error::Result;
+use crix_todo::search::{self, Field, SearchHit};
+use crix_todo::storage::{Storage, TodoItem};
+
+pub fn run(
+    storage: impl Storage,
+    query: String,
+    show_all: bool,
+    format: Option<OutputFormat>,
+    fields: Option<Vec<Column>>,
+    config: &Config,
+) -> Result<()> {
+    let items = storage.load_items()?;
+    let hits = search::search(&items, &query)
+        .into_iter()
+        .filter(|hit| show_all || !items[hit.index].done)
+        .collect::<Vec<_>>();
+
+    if let Some(format) = format {
+        let rows = hits
+            .iter()
+            .map(|hit| (hit.index, &items[hit.index]))
+            .collect::<Vec<_>>();
+        print!("{}", output::render(format, fields.as_deref(), &rows));
+    } else if hits.is_empty() {
+        println!("No matching todos.");
+    } else {
+        let highlight = style::enabled(config.display.color)
+            .then(|| format!("{}{}", BOLD, config.colors.highlight.ansi()));
+        for hit in hits {
+            print_hit(&hit, &items[hit.index], highlight.as_deref());
+        }
+    }
+    Ok(())
+}
+
+/// Prints one hit, marking the matched chars with `on` if given.
+fn print_hit(hit: &SearchHit, item: &TodoItem, on: Option<&str>) {
+    let mark = |text: &str, field: Field| match (hit.indices_for(field), on) {
+        (Some(indices), Some(on)) => highlight(text, indices, on, RESET),
+        _ => text.to_string(),
+    };
+
+    let status = if item.done { "[X]" } else { "[ ]" };
+    println!(
+        "{}. {} {}",
+        hit.index + 1,
+        status,
+        mark(&item.description, Field::Description)
+    );
+
+    if let Some(tags) = &item.tags {
+        let tags = tags
+            .iter()
+            .enumerate()
+            .map(|(i, tag)| mark(tag, Field::Tag(i)))
+            .collect::<Vec<_>>();
+        println!("   Tags: {}", tags.join(", "));
+    }
+    if let Some(notes) = &item.notes
+        && hit.indices_for(Field::Notes).is_some()
+    {
+        println!("   Notes: {}", mark(notes, Field::Notes));
+    }
+}
+
+/// Wraps the chars at `indices` (sorted char positions) in `on`/`off`,
+/// merging adjacent positions into a single run.
+pub fn highlight(text: &str, indices: &[usize], on: &str, off: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut wanted = indices.iter().peekable();
+    let mut open = false;
+
+    for (i, c) in text.chars().enumerate() {
+        let hit = wanted.peek() == Some(&&i);
+        if hit {
+            wanted.next();
+        }
+        if hit && !open {
+            out.push_str(on);
+            open = true;
+        } else if !hit && open {
+            out.push_str(off);
+            open = false;
+        }
+        out.push(c);
+    }
+    if open {
+        out.push_str(off);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_merges_adjacent_indices() {
+        assert_eq!(highlight("abcdef", &[1, 2, 4], "<", ">"), "a<bc>d<e>f");
+        assert_eq!(highlight("abc", &[0, 1, 2], "<", ">"), "<abc>");
+        assert_eq!(highlight("abc", &[], "<", ">"), "abc");
+    }
+
+    #[test]
+    fn highlight_counts_chars_not_bytes() {
+        assert_eq!(highlight("🔥ab", &[1], "<", ">"), "🔥<a>b");
+    }
+}
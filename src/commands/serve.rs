@@ -0,0 +1,61 @@
+use crix_todo::api;
+use crix_todo::error::{Result, TodoError};
+use crix_todo::storage::Storage;
+use std::net::SocketAddr;
+use tiny_http::{Header, Server};
+
+/// Serves the REST API on `bind` until stopped.
+pub fn run(storage: impl Storage, bind: SocketAddr, token: Option<String>) -> Result<()> {
+    let access = api::Access::new(bind, token)?;
+    // Fail now, and ask for a passphrase now, rather than on the first request.
+    storage.load_items()?;
+    let server = Server::http(bind)
+        .map_err(|e| TodoError::InvalidInput(format!("cannot listen on {}: {}", bind, e)))?;
+    println!("Serving http://{}/todos; press Ctrl-C to stop", bind);
+
+    for mut request in server.incoming_requests() {
+        let header = |name: &str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+                .map(|h| h.value.to_string())
+        };
+        let mut api_request = api::Request {
+            method: request.method().as_str().to_uppercase(),
+            url: request.url().to_string(),
+            host: header("Host"),
+            content_type: header("Content-Type"),
+            authorization: header("Authorization"),
+            if_match: header("If-Match"),
+            if_none_match: header("If-None-Match"),
+            body: String::new(),
+        };
+        let response = match request.as_reader().read_to_string(&mut api_request.body) {
+            Ok(_) => api::handle(&storage, &access, &api_request),
+            Err(e) => api::Response::from(TodoError::InvalidInput(format!(
+                "could not read request body: {}",
+                e
+            ))),
+        };
+
+        let mut reply = tiny_http::Response::from_string(response.body.unwrap_or_default())
+            .with_status_code(response.status);
+        if response.status != 204 && response.status != 304 {
+            reply.add_header(header_of("Content-Type", "application/json"));
+        }
+        if let Some(etag) = response.etag {
+            reply.add_header(header_of("ETag", &etag));
+        }
+        if let Some(location) = response.location {
+            reply.add_header(header_of("Location", &location));
+        }
+        // The client hanging up early is its own business.
+        let _ = request.respond(reply);
+    }
+    Ok(())
+}
+
+fn header_of(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header names and values are ASCII")
+}
@@ -0,0 +1,1279 @@
+//! Settings, read in layers from TOML files and the environment. Later
+//! layers win:
+//!
+//! 1. built-in defaults
+//! 2. `/etc/crix-todo/config.toml`
+//! 3. `~/.config/crix-todo/config.toml`, or under `$XDG_CONFIG_HOME`
+//! 4. `.crix-todo.toml` in the current directory or the nearest parent
+//! 5. the environment variables listed in [`KEYS`], such as
+//!    `CRIX_TODO_STORAGE_PATH`
+//!
+//! Unknown keys and values of the wrong type are errors, reported with the
+//! file, line and column they are on. Relative paths in a file are relative
+//! to the directory it is in, and the settings in [`USER_ONLY`] may not be
+//! set in a project file.
+//!
+//! The TUI's keys are set in `[keys.normal]` and `[keys.edit]`, one action per
+//! line, written as described in [`crate::keys`].
+
+use crate::error::{Result, TodoError};
+use crate::keys::Bindings;
+use crate::sort::SortSpec;
+use crate::storage::Backend;
+use chrono::format::{Item, StrftimeItems};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, TableLike};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub storage: StorageConfig,
+    pub defaults: Defaults,
+    pub priority: Priorities,
+    pub display: DisplayConfig,
+    pub colors: Colors,
+    pub keys: Keys,
+    pub serve: ServeConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    #[serde(with = "as_str")]
+    pub backend: Backend,
+    /// Where the list is kept; the backend's default when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub history: History,
+    /// The hooks directory; `hooks` next to the user's config file when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<PathBuf>,
+}
+
+impl StorageConfig {
+    pub fn path(&self) -> String {
+        self.path
+            .clone()
+            .unwrap_or_else(|| self.backend.default_path().to_string())
+    }
+
+    pub fn hooks_dir(&self) -> Option<PathBuf> {
+        self.hooks
+            .clone()
+            .or_else(|| user_dir().map(|dir| dir.join("hooks")))
+    }
+}
+
+/// Whether every change is recorded as a git commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum History {
+    #[default]
+    Off,
+    Git,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// Priority for `todo add` without `--priority`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    /// Order for `todo list` without `--sort`.
+    #[serde(with = "as_str_opt", skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SortSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Priorities {
+    /// The lowest priority the TUI demotes to; one step further clears it.
+    pub lowest: u8,
+    /// Priorities from 0 up to this one are shown in bold by `todo list`.
+    pub high: u8,
+}
+
+impl Default for Priorities {
+    fn default() -> Self {
+        Priorities {
+            lowest: 98,
+            high: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// strftime-style format for due dates in `todo list` and the TUI.
+    #[serde(deserialize_with = "date_format")]
+    pub date_format: String,
+    pub color: ColorMode,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            date_format: "%Y-%m-%d".into(),
+            color: ColorMode::Auto,
+        }
+    }
+}
+
+/// When `todo list` and `todo search` use colour.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// On a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub overdue: Color,
+    pub due_today: Color,
+    /// Search matches.
+    pub highlight: Color,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            overdue: Color::Red,
+            due_today: Color::Yellow,
+            highlight: Color::Yellow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Gray,
+}
+
+impl Color {
+    /// The ANSI escape that switches to this foreground colour.
+    pub fn ansi(self) -> &'static str {
+        match self {
+            Color::Black => "\x1b[30m",
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Blue => "\x1b[34m",
+            Color::Magenta => "\x1b[35m",
+            Color::Cyan => "\x1b[36m",
+            Color::White => "\x1b[37m",
+            Color::Gray => "\x1b[90m",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServeConfig {
+    /// The bearer token `todo serve` requires; it only listens beyond this
+    /// machine when one is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    /// Which set of keys the ones below default to.
+    pub preset: Preset,
+    /// Browsing the list.
+    pub normal: NormalKeys,
+    /// The edit modal; keys bound to nothing here type text.
+    pub edit: EditKeys,
+    /// The edit modal's normal mode, entered with `keys.edit.normal_mode`,
+    /// where keys move around instead of typing.
+    pub edit_normal: EditNormalKeys,
+}
+
+impl Keys {
+    /// The keys of `preset`, before any are changed.
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Default => Keys::default(),
+            Preset::Vim => Keys {
+                preset,
+                normal: NormalKeys {
+                    quit: bindings("q"),
+                    down: bindings("j, down"),
+                    up: bindings("k, up"),
+                    toggle_done: bindings("x"),
+                    expand: bindings("space, enter"),
+                    edit: bindings("i"),
+                    delete: bindings("d d"),
+                    promote: bindings("+"),
+                    demote: bindings("-"),
+                    search: bindings("/"),
+                    next_match: bindings("n"),
+                    previous_match: bindings("N"),
+                    sort: bindings("s"),
+                    top: bindings("g g, home"),
+                    bottom: bindings("G, end"),
+                    add: bindings("o"),
+                    undo: bindings("u"),
+                    redo: bindings("ctrl-r"),
+                },
+                edit: EditKeys {
+                    close: bindings(""),
+                    toggle_done: bindings(""),
+                    normal_mode: bindings("esc"),
+                    ..EditKeys::default()
+                },
+                edit_normal: EditNormalKeys::default(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Default,
+    /// `j`/`k`, `gg`/`G`, counts such as `5j`, `dd`, and a normal mode in
+    /// the edit modal.
+    Vim,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NormalKeys {
+    pub quit: Bindings,
+    pub down: Bindings,
+    pub up: Bindings,
+    pub toggle_done: Bindings,
+    pub expand: Bindings,
+    pub edit: Bindings,
+    pub delete: Bindings,
+    pub promote: Bindings,
+    pub demote: Bindings,
+    pub search: Bindings,
+    pub next_match: Bindings,
+    pub previous_match: Bindings,
+    pub sort: Bindings,
+    pub top: Bindings,
+    pub bottom: Bindings,
+    /// Adds an item below the selected one and opens it in the edit modal.
+    pub add: Bindings,
+    pub undo: Bindings,
+    pub redo: Bindings,
+}
+
+impl Default for NormalKeys {
+    fn default() -> Self {
+        NormalKeys {
+            quit: bindings("q"),
+            down: bindings("down"),
+            up: bindings("up"),
+            toggle_done: bindings("enter"),
+            expand: bindings("space"),
+            edit: bindings("e"),
+            delete: bindings("backspace"),
+            promote: bindings("p"),
+            demote: bindings("l"),
+            search: bindings("/"),
+            next_match: bindings("n"),
+            previous_match: bindings("N"),
+            sort: bindings("s"),
+            top: bindings("home"),
+            bottom: bindings("end"),
+            add: bindings("a"),
+            undo: bindings("u"),
+            redo: bindings("ctrl-r"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditKeys {
+    pub next_field: Bindings,
+    pub previous_field: Bindings,
+    pub left: Bindings,
+    pub right: Bindings,
+    pub close: Bindings,
+    pub backspace: Bindings,
+    pub toggle_done: Bindings,
+    pub normal_mode: Bindings,
+}
+
+impl Default for EditKeys {
+    fn default() -> Self {
+        EditKeys {
+            next_field: bindings("down"),
+            previous_field: bindings("up"),
+            left: bindings("left"),
+            right: bindings("right"),
+            close: bindings("esc"),
+            backspace: bindings("backspace"),
+            toggle_done: bindings("enter"),
+            normal_mode: bindings(""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditNormalKeys {
+    pub next_field: Bindings,
+    pub previous_field: Bindings,
+    pub left: Bindings,
+    pub right: Bindings,
+    pub word_forward: Bindings,
+    pub word_backward: Bindings,
+    pub word_end: Bindings,
+    pub line_start: Bindings,
+    pub line_end: Bindings,
+    pub insert: Bindings,
+    pub append: Bindings,
+    pub insert_at_start: Bindings,
+    pub append_at_end: Bindings,
+    pub delete_char: Bindings,
+    pub close: Bindings,
+    pub toggle_done: Bindings,
+}
+
+impl Default for EditNormalKeys {
+    fn default() -> Self {
+        EditNormalKeys {
+            next_field: bindings("j, down"),
+            previous_field: bindings("k, up"),
+            left: bindings("h, left"),
+            right: bindings("l, right"),
+            word_forward: bindings("w"),
+            word_backward: bindings("b"),
+            word_end: bindings("e"),
+            line_start: bindings("0, home"),
+            line_end: bindings("$, end"),
+            insert: bindings("i"),
+            append: bindings("a"),
+            insert_at_start: bindings("I"),
+            append_at_end: bindings("A"),
+            delete_char: bindings("x"),
+            close: bindings("esc"),
+            toggle_done: bindings("enter"),
+        }
+    }
+}
+
+fn bindings(keys: &str) -> Bindings {
+    keys.parse().expect("default bindings parse")
+}
+
+fn date_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<String, D::Error> {
+    let format = String::deserialize(deserializer)?;
+    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        return Err(serde::de::Error::custom(format!(
+            "invalid date format '{}'",
+            format
+        )));
+    }
+    Ok(format)
+}
+
+// For types that already parse from and print as the strings users write.
+mod as_str {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(d: D) -> Result<T, D::Error>
+    where
+        T: FromStr<Err: Display>,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(d)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+mod as_str_opt {
+    use serde::{Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::as_str::serialize(value, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(d: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr<Err: Display>,
+        D: Deserializer<'de>,
+    {
+        super::as_str::deserialize(d).map(Some)
+    }
+}
+
+/// The type of value a key holds, for reading it from the environment or
+/// the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    String,
+    Integer,
+}
+
+/// A setting, the environment variable that overrides it and what it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub name: &'static str,
+    pub env: &'static str,
+    pub kind: Kind,
+    pub help: &'static str,
+}
+
+/// Every setting there is.
+pub const KEYS: &[Key] = &[
+    Key {
+        name: "storage.backend",
+        env: "CRIX_TODO_STORAGE_BACKEND",
+        kind: Kind::String,
+        help: "json, todotxt, markdown or dir",
+    },
+    Key {
+        name: "storage.path",
+        env: "CRIX_TODO_STORAGE_PATH",
+        kind: Kind::String,
+        help: "where the list is kept; defaults to the backend's usual file",
+    },
+    Key {
+        name: "storage.history",
+        env: "CRIX_TODO_HISTORY",
+        kind: Kind::String,
+        help: "git to commit every change, or off",
+    },
+    Key {
+        name: "storage.hooks",
+        env: "CRIX_TODO_HOOKS",
+        kind: Kind::String,
+        help: "directory of lifecycle hooks",
+    },
+    Key {
+        name: "defaults.priority",
+        env: "CRIX_TODO_DEFAULTS_PRIORITY",
+        kind: Kind::Integer,
+        help: "priority for `todo add` without --priority",
+    },
+    Key {
+        name: "defaults.sort",
+        env: "CRIX_TODO_DEFAULTS_SORT",
+        kind: Kind::String,
+        help: "order for `todo list` without --sort, such as due,-priority",
+    },
+    Key {
+        name: "priority.lowest",
+        env: "CRIX_TODO_PRIORITY_LOWEST",
+        kind: Kind::Integer,
+        help: "lowest priority the TUI demotes to; one more step clears it",
+    },
+    Key {
+        name: "priority.high",
+        env: "CRIX_TODO_PRIORITY_HIGH",
+        kind: Kind::Integer,
+        help: "priorities up to this one are shown in bold",
+    },
+    Key {
+        name: "display.date_format",
+        env: "CRIX_TODO_DISPLAY_DATE_FORMAT",
+        kind: Kind::String,
+        help: "strftime format for due dates, such as %d %b %Y",
+    },
+    Key {
+        name: "display.color",
+        env: "CRIX_TODO_DISPLAY_COLOR",
+        kind: Kind::String,
+        help: "auto, always or never",
+    },
+    Key {
+        name: "colors.overdue",
+        env: "CRIX_TODO_COLORS_OVERDUE",
+        kind: Kind::String,
+        help: "colour of overdue items",
+    },
+    Key {
+        name: "colors.due_today",
+        env: "CRIX_TODO_COLORS_DUE_TODAY",
+        kind: Kind::String,
+        help: "colour of items due today",
+    },
+    Key {
+        name: "colors.highlight",
+        env: "CRIX_TODO_COLORS_HIGHLIGHT",
+        kind: Kind::String,
+        help: "colour of search matches",
+    },
+    Key {
+        name: "keys.preset",
+        env: "CRIX_TODO_KEYS_PRESET",
+        kind: Kind::String,
+        help: "default or vim; the keys below start from this preset",
+    },
+    Key {
+        name: "keys.normal.quit",
+        env: "CRIX_TODO_KEYS_NORMAL_QUIT",
+        kind: Kind::String,
+        help: "save and quit",
+    },
+    Key {
+        name: "keys.normal.down",
+        env: "CRIX_TODO_KEYS_NORMAL_DOWN",
+        kind: Kind::String,
+        help: "select the next item",
+    },
+    Key {
+        name: "keys.normal.up",
+        env: "CRIX_TODO_KEYS_NORMAL_UP",
+        kind: Kind::String,
+        help: "select the previous item",
+    },
+    Key {
+        name: "keys.normal.toggle_done",
+        env: "CRIX_TODO_KEYS_NORMAL_TOGGLE_DONE",
+        kind: Kind::String,
+        help: "mark the item done or not done",
+    },
+    Key {
+        name: "keys.normal.expand",
+        env: "CRIX_TODO_KEYS_NORMAL_EXPAND",
+        kind: Kind::String,
+        help: "show or hide the item's details",
+    },
+    Key {
+        name: "keys.normal.edit",
+        env: "CRIX_TODO_KEYS_NORMAL_EDIT",
+        kind: Kind::String,
+        help: "open the edit modal",
+    },
+    Key {
+        name: "keys.normal.delete",
+        env: "CRIX_TODO_KEYS_NORMAL_DELETE",
+        kind: Kind::String,
+        help: "delete the item",
+    },
+    Key {
+        name: "keys.normal.promote",
+        env: "CRIX_TODO_KEYS_NORMAL_PROMOTE",
+        kind: Kind::String,
+        help: "raise the item's priority",
+    },
+    Key {
+        name: "keys.normal.demote",
+        env: "CRIX_TODO_KEYS_NORMAL_DEMOTE",
+        kind: Kind::String,
+        help: "lower the item's priority",
+    },
+    Key {
+        name: "keys.normal.search",
+        env: "CRIX_TODO_KEYS_NORMAL_SEARCH",
+        kind: Kind::String,
+        help: "start a search",
+    },
+    Key {
+        name: "keys.normal.next_match",
+        env: "CRIX_TODO_KEYS_NORMAL_NEXT_MATCH",
+        kind: Kind::String,
+        help: "jump to the next search match",
+    },
+    Key {
+        name: "keys.normal.sort",
+        env: "CRIX_TODO_KEYS_NORMAL_SORT",
+        kind: Kind::String,
+        help: "cycle the sort order",
+    },
+    Key {
+        name: "keys.normal.previous_match",
+        env: "CRIX_TODO_KEYS_NORMAL_PREVIOUS_MATCH",
+        kind: Kind::String,
+        help: "jump to the previous search match",
+    },
+    Key {
+        name: "keys.normal.top",
+        env: "CRIX_TODO_KEYS_NORMAL_TOP",
+        kind: Kind::String,
+        help: "select the first item",
+    },
+    Key {
+        name: "keys.normal.bottom",
+        env: "CRIX_TODO_KEYS_NORMAL_BOTTOM",
+        kind: Kind::String,
+        help: "select the last item",
+    },
+    Key {
+        name: "keys.normal.add",
+        env: "CRIX_TODO_KEYS_NORMAL_ADD",
+        kind: Kind::String,
+        help: "add an item below the selected one",
+    },
+    Key {
+        name: "keys.normal.undo",
+        env: "CRIX_TODO_KEYS_NORMAL_UNDO",
+        kind: Kind::String,
+        help: "undo the last change",
+    },
+    Key {
+        name: "keys.normal.redo",
+        env: "CRIX_TODO_KEYS_NORMAL_REDO",
+        kind: Kind::String,
+        help: "redo the last undone change",
+    },
+    Key {
+        name: "keys.edit.next_field",
+        env: "CRIX_TODO_KEYS_EDIT_NEXT_FIELD",
+        kind: Kind::String,
+        help: "move to the next field",
+    },
+    Key {
+        name: "keys.edit.previous_field",
+        env: "CRIX_TODO_KEYS_EDIT_PREVIOUS_FIELD",
+        kind: Kind::String,
+        help: "move to the previous field",
+    },
+    Key {
+        name: "keys.edit.left",
+        env: "CRIX_TODO_KEYS_EDIT_LEFT",
+        kind: Kind::String,
+        help: "move the cursor left",
+    },
+    Key {
+        name: "keys.edit.right",
+        env: "CRIX_TODO_KEYS_EDIT_RIGHT",
+        kind: Kind::String,
+        help: "move the cursor right",
+    },
+    Key {
+        name: "keys.edit.close",
+        env: "CRIX_TODO_KEYS_EDIT_CLOSE",
+        kind: Kind::String,
+        help: "save and close the modal",
+    },
+    Key {
+        name: "keys.edit.backspace",
+        env: "CRIX_TODO_KEYS_EDIT_BACKSPACE",
+        kind: Kind::String,
+        help: "delete the character before the cursor",
+    },
+    Key {
+        name: "keys.edit.toggle_done",
+        env: "CRIX_TODO_KEYS_EDIT_TOGGLE_DONE",
+        kind: Kind::String,
+        help: "mark the item done or not done",
+    },
+    Key {
+        name: "keys.edit.normal_mode",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_MODE",
+        kind: Kind::String,
+        help: "switch the edit modal to normal mode",
+    },
+    Key {
+        name: "keys.edit_normal.next_field",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_NEXT_FIELD",
+        kind: Kind::String,
+        help: "move to the next field",
+    },
+    Key {
+        name: "keys.edit_normal.previous_field",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_PREVIOUS_FIELD",
+        kind: Kind::String,
+        help: "move to the previous field",
+    },
+    Key {
+        name: "keys.edit_normal.left",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_LEFT",
+        kind: Kind::String,
+        help: "move the cursor left",
+    },
+    Key {
+        name: "keys.edit_normal.right",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_RIGHT",
+        kind: Kind::String,
+        help: "move the cursor right",
+    },
+    Key {
+        name: "keys.edit_normal.word_forward",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_WORD_FORWARD",
+        kind: Kind::String,
+        help: "move to the start of the next word",
+    },
+    Key {
+        name: "keys.edit_normal.word_backward",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_WORD_BACKWARD",
+        kind: Kind::String,
+        help: "move to the start of the word",
+    },
+    Key {
+        name: "keys.edit_normal.word_end",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_WORD_END",
+        kind: Kind::String,
+        help: "move to the end of the word",
+    },
+    Key {
+        name: "keys.edit_normal.line_start",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_LINE_START",
+        kind: Kind::String,
+        help: "move to the start of the field",
+    },
+    Key {
+        name: "keys.edit_normal.line_end",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_LINE_END",
+        kind: Kind::String,
+        help: "move to the end of the field",
+    },
+    Key {
+        name: "keys.edit_normal.insert",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_INSERT",
+        kind: Kind::String,
+        help: "type before the cursor",
+    },
+    Key {
+        name: "keys.edit_normal.append",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_APPEND",
+        kind: Kind::String,
+        help: "type after the cursor",
+    },
+    Key {
+        name: "keys.edit_normal.insert_at_start",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_INSERT_AT_START",
+        kind: Kind::String,
+        help: "type at the start of the field",
+    },
+    Key {
+        name: "keys.edit_normal.append_at_end",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_APPEND_AT_END",
+        kind: Kind::String,
+        help: "type at the end of the field",
+    },
+    Key {
+        name: "keys.edit_normal.delete_char",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_DELETE_CHAR",
+        kind: Kind::String,
+        help: "delete the character under the cursor",
+    },
+    Key {
+        name: "keys.edit_normal.close",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_CLOSE",
+        kind: Kind::String,
+        help: "save and close the modal",
+    },
+    Key {
+        name: "keys.edit_normal.toggle_done",
+        env: "CRIX_TODO_KEYS_EDIT_NORMAL_TOGGLE_DONE",
+        kind: Kind::String,
+        help: "mark the item done or not done",
+    },
+    Key {
+        name: "serve.token",
+        env: "CRIX_TODO_SERVE_TOKEN",
+        kind: Kind::String,
+        help: "bearer token `todo serve` requires; needed to listen beyond localhost",
+    },
+];
+
+/// The setting called `name`.
+pub fn key(name: &str) -> Result<&'static Key> {
+    KEYS.iter().find(|key| key.name == name).ok_or_else(|| {
+        TodoError::InvalidInput(format!(
+            "unknown config key '{}': run `todo config list` to see them all",
+            name
+        ))
+    })
+}
+
+impl Kind {
+    fn parse(self, raw: &str) -> std::result::Result<Value, String> {
+        match self {
+            Kind::String => Ok(Value::String(raw.to_string())),
+            Kind::Integer => raw
+                .trim()
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("expected a number, got '{}'", raw)),
+        }
+    }
+}
+
+/// Where a setting came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// The config files to read, in order; any of them may be missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Places {
+    pub system: Option<PathBuf>,
+    pub user: Option<PathBuf>,
+    pub project: Option<PathBuf>,
+}
+
+impl Places {
+    pub fn standard() -> Self {
+        Places {
+            system: cfg!(unix).then(|| PathBuf::from("/etc/crix-todo/config.toml")),
+            user: user_dir().map(|dir| dir.join("config.toml")),
+            project: std::env::current_dir()
+                .ok()
+                .and_then(|dir| find_project_file(&dir)),
+        }
+    }
+}
+
+/// Settings a project file may not hold: with `storage.hooks` a cloned
+/// repository could run its own programs, and `serve.token` is a secret.
+pub const USER_ONLY: &[&str] = &["storage.hooks", "serve.token"];
+
+/// The name of the project-local config file.
+pub const PROJECT_FILE: &str = ".crix-todo.toml";
+
+/// The user's crix-todo config directory.
+pub fn user_dir() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("crix-todo"))
+}
+
+/// The project file in `dir` or its nearest ancestor that has one.
+pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|file| file.is_file())
+}
+
+/// Every layer of settings, each checked on its own so that errors point at
+/// the file or variable they are in.
+#[derive(Debug, Clone)]
+pub struct Layers {
+    layers: Vec<(Source, Table)>,
+}
+
+impl Layers {
+    pub fn load(places: &Places, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut layers = Vec::new();
+        for file in [&places.system, &places.user, &places.project]
+            .into_iter()
+            .flatten()
+        {
+            let Some(mut table) = read_file(file)? else {
+                continue;
+            };
+            if Some(file) == places.project.as_ref()
+                && let Some(name) = USER_ONLY.iter().find(|name| lookup(&table, name).is_some())
+            {
+                return Err(TodoError::InvalidInput(format!(
+                    "invalid config {}: {} cannot be set in a project file; \
+                     set it in your user config instead",
+                    file.display(),
+                    name
+                )));
+            }
+            if let Some(dir) = file.parent() {
+                resolve_paths(&mut table, dir);
+            }
+            layers.push((Source::File(file.clone()), table));
+        }
+        for key in KEYS {
+            let Some(raw) = env(key.env).filter(|raw| !raw.is_empty()) else {
+                continue;
+            };
+            let invalid = |message: String| {
+                TodoError::InvalidInput(format!("invalid ${}: {}", key.env, message))
+            };
+            let mut table = Table::new();
+            insert(&mut table, key.name, key.kind.parse(&raw).map_err(invalid)?);
+            check(&table).map_err(invalid)?;
+            layers.push((Source::Env(key.env), table));
+        }
+
+        // The default keys depend on the preset, wherever that was set.
+        let preset = layers
+            .iter()
+            .rev()
+            .find_map(|(_, table)| lookup(table, "keys.preset"))
+            .and_then(|preset| preset.clone().try_into().ok())
+            .unwrap_or_default();
+        let defaults = Config {
+            keys: Keys::preset(preset),
+            ..Config::default()
+        };
+        let defaults = Table::try_from(defaults).expect("defaults serialize");
+        layers.insert(0, (Source::Default, defaults));
+        Ok(Layers { layers })
+    }
+
+    /// The settings with every layer applied.
+    pub fn config(&self) -> Config {
+        let mut merged = Table::new();
+        for (_, table) in &self.layers {
+            merge(&mut merged, table);
+        }
+        // Each layer was checked as it was loaded, and the defaults fill
+        // every gap, so the merge is valid too.
+        Value::Table(merged)
+            .try_into()
+            .expect("checked layers merge into a valid config")
+    }
+
+    /// The value of `key` and the layer it came from, or `None` if unset.
+    pub fn get(&self, key: &Key) -> Option<(&Value, &Source)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(source, table)| lookup(table, key.name).map(|value| (value, source)))
+    }
+}
+
+/// The settings from the standard places and the environment.
+pub fn load() -> Result<Config> {
+    Ok(Layers::load(&Places::standard(), |name| std::env::var(name).ok())?.config())
+}
+
+/// Sets `key` to `raw` in the config file at `file`, creating it if needed.
+pub fn set(file: &Path, key: &Key, raw: &str) -> Result<()> {
+    let mut table = read_file(file)?.unwrap_or_default();
+    let invalid = |message: String| {
+        TodoError::InvalidInput(format!("invalid value for {}: {}", key.name, message))
+    };
+    let value = key.kind.parse(raw).map_err(invalid)?;
+    insert(&mut table, key.name, value.clone());
+    check(&table).map_err(invalid)?;
+
+    // Edit the file as written, keeping its comments, order and layout.
+    let text = fs::read_to_string(file).unwrap_or_default();
+    let mut document = text
+        .parse::<DocumentMut>()
+        .expect("read_file has parsed it");
+    let (path, field) = key.name.rsplit_once('.').expect("keys are section.field");
+    let mut section: &mut dyn TableLike = document.as_table_mut();
+    for name in path.split('.') {
+        section = section
+            .entry(name)
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                toml_edit::Item::Table(table)
+            })
+            .as_table_like_mut()
+            .expect("check has refused anything else");
+    }
+    let value = match value {
+        Value::Integer(n) => toml_edit::value(n),
+        value => toml_edit::value(value.as_str().expect("keys are strings or integers")),
+    };
+    match section.get_mut(field) {
+        // Keep any comment beside the old value.
+        Some(toml_edit::Item::Value(old)) => {
+            let decor = old.decor().clone();
+            *old = value.into_value().expect("a value");
+            *old.decor_mut() = decor;
+        }
+        _ => {
+            section.insert(field, value);
+        }
+    }
+
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| TodoError::io(dir, e))?;
+    }
+    fs::write(file, document.to_string()).map_err(|e| TodoError::io(file, e))
+}
+
+/// The table in `file`, checked against [`Config`], or `None` if there is no
+/// such file.
+/// Makes relative `storage.path` and `storage.hooks` values relative to
+/// `dir`, the directory of the file they were read from.
+fn resolve_paths(table: &mut Table, dir: &Path) {
+    let Some(Value::Table(storage)) = table.get_mut("storage") else {
+        return;
+    };
+    for name in ["path", "hooks"] {
+        if let Some(Value::String(path)) = storage.get_mut(name)
+            && Path::new(path.as_str()).is_relative()
+        {
+            *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+        }
+    }
+}
+
+fn read_file(file: &Path) -> Result<Option<Table>> {
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(TodoError::io(file, e)),
+    };
+    let invalid = |e: toml::de::Error| {
+        let position = e
+            .span()
+            .map(|span| {
+                let before = &text[..span.start];
+                let line = before.matches('\n').count() + 1;
+                let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+                format!(":{}:{}", line, column)
+            })
+            .unwrap_or_default();
+        TodoError::InvalidInput(format!(
+            "invalid config {}{}: {}",
+            file.display(),
+            position,
+            e.message()
+        ))
+    };
+    toml::from_str::<Config>(&text).map_err(invalid)?;
+    toml::from_str::<Table>(&text).map(Some).map_err(invalid)
+}
+
+fn check(table: &Table) -> std::result::Result<(), String> {
+    Value::Table(table.clone())
+        .try_into::<Config>()
+        .map(|_| ())
+        .map_err(|e| e.message().to_string())
+}
+
+fn lookup<'a>(table: &'a Table, name: &str) -> Option<&'a Value> {
+    let (path, field) = name.rsplit_once('.')?;
+    let mut table = table;
+    for section in path.split('.') {
+        table = table.get(section)?.as_table()?;
+    }
+    table.get(field)
+}
+
+fn insert(table: &mut Table, name: &str, value: Value) {
+    let (path, field) = name.rsplit_once('.').expect("keys are section.field");
+    let mut table = table;
+    for section in path.split('.') {
+        let entry = table
+            .entry(section)
+            .or_insert_with(|| Value::Table(Table::new()));
+        let Value::Table(section) = entry else {
+            return;
+        };
+        table = section;
+    }
+    table.insert(field.to_string(), value);
+}
+
+fn merge(into: &mut Table, from: &Table) {
+    for (name, value) in from {
+        match (into.get_mut(name), value) {
+            (Some(Value::Table(into)), Value::Table(from)) => merge(into, from),
+            _ => {
+                into.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn places(dir: &TempDir, user: &str, project: &str) -> Places {
+        let write = |name: &str, text: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, text).unwrap();
+            Some(path)
+        };
+        Places {
+            system: Some(dir.path().join("missing.toml")),
+            user: write("user.toml", user),
+            project: write("project.toml", project),
+        }
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let dir = TempDir::new().unwrap();
+        let places = places(
+            &dir,
+            "[storage]\npath = \"mine.json\"\n[priority]\nlowest = 9\n",
+            "[storage]\nbackend = \"todotxt\"\n",
+        );
+        let env = HashMap::from([("CRIX_TODO_STORAGE_PATH", "env.txt".to_string())]);
+        let layers = Layers::load(&places, |name| env.get(name).cloned()).unwrap();
+        let config = layers.config();
+
+        assert_eq!(config.storage.backend, Backend::TodoTxt);
+        assert_eq!(config.storage.path(), "env.txt");
+        assert_eq!(config.priority.lowest, 9);
+        assert_eq!(config.priority.high, 1);
+        let (value, source) = layers.get(key("priority.lowest").unwrap()).unwrap();
+        assert_eq!(value, &Value::Integer(9));
+        assert_eq!(source, &Source::File(dir.path().join("user.toml")));
+        assert_eq!(layers.get(key("defaults.priority").unwrap()), None);
+    }
+
+    #[test]
+    fn relative_paths_are_relative_to_their_file() {
+        let dir = TempDir::new().unwrap();
+        let places = places(
+            &dir,
+            "[storage]\nhooks = \"my-hooks\"\n",
+            "[storage]\npath = \"todo.json\"\n",
+        );
+        let config = Layers::load(&places, |_| None).unwrap().config();
+        let in_dir = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        assert_eq!(config.storage.path(), in_dir("todo.json"));
+        assert_eq!(
+            config.storage.hooks_dir(),
+            Some(PathBuf::from(in_dir("my-hooks")))
+        );
+
+        // the environment is read from wherever the command runs
+        let env = |name: &str| (name == "CRIX_TODO_STORAGE_PATH").then(|| "here.json".into());
+        let config = Layers::load(&places, env).unwrap().config();
+        assert_eq!(config.storage.path(), "here.json");
+    }
+
+    #[test]
+    fn project_files_cannot_set_hooks_or_the_token() {
+        let dir = TempDir::new().unwrap();
+        for project in ["[storage]\nhooks = \"h\"\n", "[serve]\ntoken = \"t\"\n"] {
+            let places = places(&dir, "", project);
+            let err = Layers::load(&places, |_| None).unwrap_err();
+            assert!(
+                err.to_string().contains("cannot be set in a project file"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn the_preset_picks_the_keys_that_others_override() {
+        let dir = TempDir::new().unwrap();
+        let places = places(&dir, "[keys.normal]\ndelete = \"D\"\n", "");
+        let env = |name: &str| (name == "CRIX_TODO_KEYS_PRESET").then(|| "vim".to_string());
+        let layers = Layers::load(&places, env).unwrap();
+        let keys = layers.config().keys;
+
+        assert_eq!(keys.preset, Preset::Vim);
+        assert_eq!(keys.normal.down, "j, down".parse().unwrap());
+        assert_eq!(keys.normal.delete, "D".parse().unwrap());
+        let (_, source) = layers.get(key("keys.normal.top").unwrap()).unwrap();
+        assert_eq!(source, &Source::Default);
+    }
+
+    #[test]
+    fn unknown_keys_and_bad_values_are_reported_where_they_are() {
+        let dir = TempDir::new().unwrap();
+        let bad_file = places(&dir, "[display]\n\ncolour = \"never\"\n", "");
+        let err = Layers::load(&bad_file, |_| None).unwrap_err().to_string();
+        assert!(
+            err.contains("user.toml:3:1: unknown field `colour`"),
+            "{}",
+            err
+        );
+
+        let bad_format = places(&dir, "", "[display]\ndate_format = \"%Q\"\n");
+        let err = Layers::load(&bad_format, |_| None).unwrap_err().to_string();
+        assert!(err.contains("invalid date format '%Q'"), "{}", err);
+
+        let bad_key = places(&dir, "[keys.normal]\ndelete = \"d d, hyper-x\"\n", "");
+        let err = Layers::load(&bad_key, |_| None).unwrap_err().to_string();
+        assert!(
+            err.contains("user.toml:2:10: unknown modifier 'hyper'"),
+            "{}",
+            err
+        );
+
+        let env = |name: &str| (name == "CRIX_TODO_DEFAULTS_PRIORITY").then(|| "high".to_string());
+        let err = Layers::load(&Places::default(), env)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "invalid $CRIX_TODO_DEFAULTS_PRIORITY: expected a number, got 'high'"
+        );
+
+        assert!(key("display.colour").is_err());
+    }
+
+    #[test]
+    fn set_writes_a_valid_value_and_refuses_a_bad_one() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("nested").join("config.toml");
+        set(&file, key("defaults.sort").unwrap(), "due,-priority").unwrap();
+        set(&file, key("priority.high").unwrap(), "2").unwrap();
+        assert!(set(&file, key("display.color").unwrap(), "sometimes").is_err());
+        assert!(set(&file, key("priority.high").unwrap(), "300").is_err());
+
+        let places = Places {
+            user: Some(file),
+            ..Places::default()
+        };
+        let config = Layers::load(&places, |_| None).unwrap().config();
+        assert_eq!(config.defaults.sort, Some("due,-priority".parse().unwrap()));
+        assert_eq!(config.priority.high, 2);
+        assert_eq!(config.display.color, ColorMode::Auto);
+    }
+
+    #[test]
+    fn set_keeps_comments_and_layout() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("config.toml");
+        fs::write(
+            &file,
+            "# My settings\n\n[priority]\nhigh = 1   # bold up to here\nlowest = 50\n",
+        )
+        .unwrap();
+
+        set(&file, key("priority.high").unwrap(), "3").unwrap();
+        set(&file, key("keys.edit_normal.close").unwrap(), "Z Z").unwrap();
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "# My settings\n\n[priority]\nhigh = 3   # bold up to here\nlowest = 50\n\n\
+             [keys.edit_normal]\nclose = \"Z Z\"\n"
+        );
+    }
+}
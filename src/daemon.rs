@@ -0,0 +1,369 @@
+//! `todo daemon`: one process owns the list and serves it over a Unix socket
+//! to CLI commands and TUIs, telling subscribers whenever it changes.
+//!
+//! The protocol is one JSON object per line. Each request gets one response,
+//! except `watch`, which is answered `ok` once the subscriber is registered
+//! and then keeps the connection open, sending a `changed` message after
+//! every change, whoever made it.
+
+use crate::error::{Result, TodoError};
+use crate::storage::{Storage, TodoItem};
+use crate::watcher::Watcher;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    Load,
+    Save { items: Vec<TodoItem> },
+    Add { item: TodoItem },
+    Watch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Items { items: Vec<TodoItem> },
+    Ok,
+    Error { message: String },
+    Changed,
+}
+
+/// How long a client waits for the daemon before giving up on it. Longer
+/// than a hook may run, since the daemon runs them while the client waits.
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The socket a daemon for the list at `path` listens on.
+pub fn socket_path(path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.sock", path))
+}
+
+enum Message {
+    Request(Request, Sender<Response>),
+    Watch(UnixStream),
+}
+
+/// Serves `storage` to clients of `listener` until the process is stopped.
+/// Changes made to the file at `path` by anything else are passed on to
+/// subscribers too.
+pub fn serve(storage: impl Storage, listener: UnixListener, path: &Path) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let tx = tx.clone();
+            thread::spawn(move || connection(stream, tx));
+        }
+    });
+
+    let mut watcher = Watcher::new(path);
+    let mut subscribers = Vec::new();
+    loop {
+        match rx.recv_timeout(Duration::from_millis(500)) {
+            Ok(Message::Request(request, reply)) => {
+                let (response, changed) = handle(&storage, request);
+                let _ = reply.send(response);
+                if changed {
+                    watcher.sync();
+                    notify(&mut subscribers);
+                }
+            }
+            Ok(Message::Watch(mut stream)) => {
+                if send(&mut stream, &Response::Ok).is_ok() {
+                    subscribers.push(stream);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if watcher.changed() {
+                    notify(&mut subscribers);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// Listens on `socket`, replacing a socket left behind by a daemon that is
+/// no longer running.
+pub fn bind(socket: &Path) -> Result<UnixListener> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(TodoError::InvalidInput(format!(
+                "a daemon is already running on {}",
+                socket.display()
+            )));
+        }
+        fs::remove_file(socket).map_err(|e| TodoError::io(socket, e))?;
+    }
+    UnixListener::bind(socket).map_err(|e| TodoError::io(socket, e))
+}
+
+/// Reads requests from one client and passes them to the main loop, which
+/// owns the storage.
+fn connection(stream: UnixStream, tx: Sender<Message>) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut writer = stream;
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Watch) => {
+                let _ = tx.send(Message::Watch(writer));
+                return;
+            }
+            Ok(request) => {
+                let (reply, response) = mpsc::channel();
+                if tx.send(Message::Request(request, reply)).is_err() {
+                    return;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => return,
+                }
+            }
+            Err(e) => Response::Error {
+                message: format!("invalid request: {}", e),
+            },
+        };
+        if send(&mut writer, &response).is_err() {
+            return;
+        }
+    }
+}
+
+/// The response to `request`, and whether the list changed.
+fn handle(storage: &impl Storage, request: Request) -> (Response, bool) {
+    let result = match request {
+        Request::Load => storage
+            .load_items()
+            .map(|items| (Response::Items { items }, false)),
+        Request::Save { items } => storage.save_items(&items).map(|_| (Response::Ok, true)),
+        Request::Add { item } => storage.add_item(item).map(|_| (Response::Ok, true)),
+        Request::Watch => Ok((Response::Ok, false)),
+    };
+    result.unwrap_or_else(|e| {
+        let message = e.to_string();
+        (Response::Error { message }, false)
+    })
+}
+
+fn notify(subscribers: &mut Vec<UnixStream>) {
+    subscribers.retain_mut(|stream| send(stream, &Response::Changed).is_ok());
+}
+
+fn send<T: Serialize>(stream: &mut UnixStream, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+/// Storage served by a running daemon. Whenever the daemon cannot be
+/// reached, `direct` is used instead, so commands work the same without one.
+/// A daemon that stops answering is given up on after a timeout: loads fall
+/// back to `direct`, while changes fail, since the daemon may still make them.
+pub struct DaemonStorage<S> {
+    socket: PathBuf,
+    direct: S,
+    timeout: Duration,
+}
+
+impl<S: Storage> DaemonStorage<S> {
+    pub fn new(socket: PathBuf, direct: S) -> Self {
+        Self {
+            socket,
+            direct,
+            timeout: TIMEOUT,
+        }
+    }
+
+    /// The daemon's response, or `None` if no daemon is listening, or if
+    /// it does not answer a load in time.
+    fn request(&self, request: &Request) -> Option<Result<Response>> {
+        let mut stream = UnixStream::connect(&self.socket).ok()?;
+        let mut exchange = || {
+            stream.set_read_timeout(Some(self.timeout))?;
+            stream.set_write_timeout(Some(self.timeout))?;
+            send(&mut stream, request)?;
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line)?;
+            Ok(line)
+        };
+        match exchange() {
+            Ok(line) => {
+                Some(serde_json::from_str(&line).map_err(|e| TodoError::json(&self.socket, e)))
+            }
+            Err(e) if is_timeout(&e) && *request == Request::Load => None,
+            Err(e) if is_timeout(&e) => Some(Err(TodoError::InvalidInput(format!(
+                "the daemon on {} did not answer within {} seconds; \
+                 the change may or may not have been made",
+                self.socket.display(),
+                self.timeout.as_secs()
+            )))),
+            Err(e) => Some(Err(TodoError::io(&self.socket, e))),
+        }
+    }
+
+    fn expect_ok(&self, request: Request) -> Option<Result<()>> {
+        Some(match self.request(&request)? {
+            Ok(Response::Ok) => Ok(()),
+            Ok(other) => Err(unexpected(other)),
+            Err(e) => Err(e),
+        })
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+fn unexpected(response: Response) -> TodoError {
+    match response {
+        Response::Error { message } => TodoError::InvalidInput(message),
+        other => TodoError::InvalidInput(format!("unexpected reply from daemon: {:?}", other)),
+    }
+}
+
+impl<S: Storage> Storage for DaemonStorage<S> {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        match self.request(&Request::Load) {
+            None => self.direct.load_items(),
+            Some(Ok(Response::Items { items })) => Ok(items),
+            Some(Ok(other)) => Err(unexpected(other)),
+            Some(Err(e)) => Err(e),
+        }
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let request = Request::Save {
+            items: items.to_vec(),
+        };
+        self.expect_ok(request)
+            .unwrap_or_else(|| self.direct.save_items(items))
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        match self.expect_ok(Request::Add { item: item.clone() }) {
+            Some(result) => result,
+            None => self.direct.add_item(item),
+        }
+    }
+}
+
+/// Subscribes to a running daemon's change notifications. The receiver
+/// disconnects when the daemon goes away; `None` if there is no daemon.
+pub fn subscribe(socket: &Path) -> Option<Receiver<()>> {
+    let mut stream = UnixStream::connect(socket).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    send(&mut stream, &Request::Watch).ok()?;
+    // Wait until the daemon has registered us, so no change is missed.
+    let mut reader = BufReader::new(stream);
+    let mut ack = String::new();
+    reader.read_line(&mut ack).ok()?;
+    if serde_json::from_str::<Response>(&ack).ok()? != Response::Ok {
+        return None;
+    }
+    reader.get_ref().set_read_timeout(None).ok()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            if line.is_err() || tx.send(()).is_err() {
+                return;
+            }
+        }
+    });
+    Some(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use tempfile::TempDir;
+
+    fn item(id: &str) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: format!("item {}", id),
+            ..TodoItem::default()
+        }
+    }
+
+    fn start(dir: &TempDir) -> (PathBuf, PathBuf) {
+        let path = dir.path().join("todo.json");
+        let socket = socket_path(path.to_str().unwrap());
+        let listener = bind(&socket).unwrap();
+        let served = path.clone();
+        thread::spawn(move || {
+            let storage = FileStorage::new(served.to_str().unwrap());
+            serve(storage, listener, &served)
+        });
+        (path, socket)
+    }
+
+    #[test]
+    fn serves_the_list_and_notifies_subscribers() {
+        let dir = TempDir::new().unwrap();
+        let (path, socket) = start(&dir);
+        let direct = || FileStorage::new(path.to_str().unwrap());
+        let client = DaemonStorage::new(socket.clone(), direct());
+        let changes = subscribe(&socket).unwrap();
+
+        client.add_item(item("a")).unwrap();
+        changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        client.save_items(&[item("a"), item("b")]).unwrap();
+        changes.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(client.load_items().unwrap(), vec![item("a"), item("b")]);
+        assert_eq!(direct().load_items().unwrap(), vec![item("a"), item("b")]);
+        assert!(matches!(bind(&socket), Err(TodoError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn falls_back_to_direct_access_without_a_daemon() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        // A socket left behind by a daemon that was killed.
+        drop(UnixListener::bind(socket_path(path.to_str().unwrap())).unwrap());
+
+        let client = DaemonStorage::new(
+            socket_path(path.to_str().unwrap()),
+            FileStorage::new(path.to_str().unwrap()),
+        );
+        client.add_item(item("a")).unwrap();
+        assert_eq!(client.load_items().unwrap(), vec![item("a")]);
+        assert!(subscribe(&socket_path(path.to_str().unwrap())).is_none());
+    }
+
+    #[test]
+    fn gives_up_on_a_daemon_that_does_not_answer() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        let socket = socket_path(path.to_str().unwrap());
+        // Accepts connections but never reads or answers them.
+        let _listener = UnixListener::bind(&socket).unwrap();
+        let direct = FileStorage::new(path.to_str().unwrap());
+        direct.save_items(&[item("a")]).unwrap();
+
+        let client = DaemonStorage {
+            timeout: Duration::from_millis(100),
+            ..DaemonStorage::new(socket, direct)
+        };
+        assert_eq!(client.load_items().unwrap(), vec![item("a")]);
+        assert!(matches!(
+            client.add_item(item("b")),
+            Err(TodoError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,526 @@
+//! Finds what is wrong with a todo list and works out a repaired version.
+//!
+//! A JSON file that no longer parses is split into its top-level objects by
+//! tracking strings and brackets, so one broken item does not take the rest
+//! with it. Each object is then read field by field, keeping what is valid,
+//! converting what can be converted and clearing the rest.
+
+use crate::storage::{TodoItem, assign_missing_ids, new_id};
+use chrono::NaiveDate;
+use serde_json::Value;
+use std::fmt;
+
+/// Something wrong with the list and what the repair does about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// The file it is in, for lists kept in more than one.
+    pub file: Option<String>,
+    /// 1-based line in the file, when known.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            file: None,
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnosis {
+    /// The list as it would be after the repair.
+    pub items: Vec<TodoItem>,
+    pub problems: Vec<Problem>,
+}
+
+/// Diagnoses the text of a `todo.json` file.
+pub fn diagnose_json(text: &str) -> Diagnosis {
+    let mut problems = Vec::new();
+    if text.trim().is_empty() {
+        return Diagnosis::default();
+    }
+
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(_)) => {}
+        Ok(_) => problems.push(Problem::new(Some(1), "the file is not a list of items")),
+        Err(e) => problems.push(Problem::new(
+            Some(e.line()),
+            format!("column {}: {}", e.column(), syntax_message(&e)),
+        )),
+    }
+
+    let mut located = Vec::new();
+    for (start, end) in objects(text) {
+        let line = Some(line_of(text, start));
+        let object = &text[start..end];
+        let value = serde_json::from_str::<Value>(object)
+            .or_else(|_| serde_json::from_str::<Value>(&drop_trailing_commas(object)));
+        match value {
+            Ok(Value::Object(fields)) => {
+                if let Some(item) = read_item(&fields, line, &mut problems) {
+                    located.push((line, item));
+                }
+            }
+            _ => problems.push(Problem::new(
+                line,
+                "this item cannot be read and will be dropped",
+            )),
+        }
+    }
+
+    let mut diagnosis = check_items(located);
+    problems.append(&mut diagnosis.problems);
+    problems.sort_by_key(|p| p.line);
+    diagnosis.problems = problems;
+    diagnosis
+}
+
+/// Diagnoses the text of a single item, such as one of the files of a `dir`
+/// list. The item is `None` when nothing of it can be read.
+pub fn diagnose_item(text: &str) -> (Option<TodoItem>, Vec<Problem>) {
+    let mut problems = Vec::new();
+    let value = serde_json::from_str::<Value>(text).or_else(|e| {
+        problems.push(Problem::new(
+            Some(e.line()),
+            format!("column {}: {}", e.column(), syntax_message(&e)),
+        ));
+        serde_json::from_str::<Value>(&drop_trailing_commas(text))
+    });
+
+    let item = match value {
+        Ok(Value::Object(fields)) => read_item(&fields, None, &mut problems),
+        _ => {
+            problems.push(Problem::new(
+                None,
+                "this item cannot be read and will be dropped",
+            ));
+            None
+        }
+    };
+    (item, problems)
+}
+
+/// Checks items that did load, from any backend, for bad dates and
+/// duplicate IDs.
+pub fn check_items(items: Vec<(Option<usize>, TodoItem)>) -> Diagnosis {
+    let mut problems = Vec::new();
+    let mut lines: Vec<Option<usize>> = items.iter().map(|(line, _)| *line).collect();
+    let mut items: Vec<TodoItem> = items.into_iter().map(|(_, item)| item).collect();
+    assign_missing_ids(&mut items);
+
+    for (item, line) in items.iter_mut().zip(&lines) {
+        if let Some(due) = item.due.clone()
+            && NaiveDate::parse_from_str(&due, "%Y-%m-%d").is_err()
+        {
+            item.due = fix_date(&due);
+            let message = match &item.due {
+                Some(fixed) => format!(
+                    "due date '{}' on \"{}\" will be written as {}",
+                    due, item.description, fixed
+                ),
+                None => format!(
+                    "invalid due date '{}' on \"{}\" will be removed",
+                    due, item.description
+                ),
+            };
+            problems.push(Problem::new(*line, message));
+        }
+    }
+
+    let mut index = 0;
+    while index < items.len() {
+        let earlier = items[..index].iter().find(|i| i.id == items[index].id);
+        match earlier {
+            Some(first) if *first == items[index] => {
+                problems.push(Problem::new(
+                    lines[index],
+                    format!(
+                        "\"{}\" is listed twice; the copy will be dropped",
+                        items[index].description
+                    ),
+                ));
+                items.remove(index);
+                lines.remove(index);
+                continue;
+            }
+            Some(_) => {
+                let id = new_id();
+                problems.push(Problem::new(
+                    lines[index],
+                    format!(
+                        "duplicate ID {}; \"{}\" will get ID {}",
+                        items[index].id, items[index].description, id
+                    ),
+                ));
+                items[index].id = id;
+            }
+            None => {}
+        }
+        index += 1;
+    }
+
+    Diagnosis { items, problems }
+}
+
+fn syntax_message(e: &serde_json::Error) -> String {
+    let message = e.to_string();
+    match message.rfind(" at line ") {
+        Some(idx) => message[..idx].to_string(),
+        None => message,
+    }
+}
+
+/// Dates written in a few other common ways, as YYYY-MM-DD.
+fn fix_date(due: &str) -> Option<String> {
+    let due = due.trim();
+    // An ISO timestamp starts with the date we want.
+    let candidates = [due, due.get(..10).unwrap_or(due)];
+    for candidate in candidates {
+        for format in ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d", "%Y%m%d"] {
+            if let Ok(date) = NaiveDate::parse_from_str(candidate, format) {
+                return Some(date.format("%Y-%m-%d").to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Reads an item field by field, reporting and repairing what is wrong.
+/// Items without a description are dropped.
+fn read_item(
+    fields: &serde_json::Map<String, Value>,
+    line: Option<usize>,
+    problems: &mut Vec<Problem>,
+) -> Option<TodoItem> {
+    let field = |name: &str| fields.get(name).filter(|v| !v.is_null());
+
+    let Some(description) = field("description").and_then(Value::as_str) else {
+        problems.push(Problem::new(
+            line,
+            "item has no description and will be dropped",
+        ));
+        return None;
+    };
+    let mut report = |message: String| {
+        problems.push(Problem::new(
+            line,
+            format!("{} on \"{}\"", message, description),
+        ))
+    };
+
+    let id = match field("id") {
+        None => String::new(),
+        Some(Value::String(id)) => id.clone(),
+        Some(other) => {
+            let id = other.to_string();
+            report(format!("ID {} will be written as a string", id));
+            id
+        }
+    };
+
+    let priority = match field("priority") {
+        None => None,
+        Some(value) => match value.as_u64().and_then(|p| u8::try_from(p).ok()) {
+            Some(p) => Some(p),
+            None => match value.as_str().and_then(|s| s.trim().parse::<u8>().ok()) {
+                Some(p) => {
+                    report(format!("priority {} will be written as a number", value));
+                    Some(p)
+                }
+                None => {
+                    report(format!(
+                        "invalid priority {} (expected 0 to 255) will be cleared",
+                        value
+                    ));
+                    None
+                }
+            },
+        },
+    };
+
+    let due = match field("due") {
+        None => None,
+        Some(Value::String(due)) => Some(due.clone()),
+        Some(other) => {
+            report(format!("invalid due date {} will be removed", other));
+            None
+        }
+    };
+
+    let tags = match field("tags") {
+        None => None,
+        Some(Value::Array(values)) if values.iter().all(Value::is_string) => Some(
+            values
+                .iter()
+                .filter_map(|t| t.as_str().map(String::from))
+                .collect(),
+        ),
+        Some(Value::String(list)) => {
+            report(format!("tags \"{}\" will be written as a list", list));
+            Some(list.split(',').map(|t| t.trim().to_string()).collect())
+        }
+        Some(other) => {
+            report(format!("invalid tags {} will be removed", other));
+            None
+        }
+    };
+
+    let done = match fields.get("done") {
+        Some(Value::Bool(done)) => *done,
+        None | Some(Value::Null) => {
+            report("missing completion state; it will be marked not done".into());
+            false
+        }
+        Some(other) => {
+            let done =
+                matches!(other.as_str(), Some("true" | "x" | "done")) || other.as_u64() == Some(1);
+            report(format!(
+                "completion state {} will be written as {}",
+                other, done
+            ));
+            done
+        }
+    };
+
+    let notes = match field("notes") {
+        None => None,
+        Some(Value::String(notes)) => Some(notes.clone()),
+        Some(other) => {
+            report(format!("notes {} will be written as text", other));
+            Some(other.to_string())
+        }
+    };
+
+    let created = match field("created") {
+        None => None,
+        Some(value) if value.as_u64().is_some() => value.as_u64(),
+        Some(other) => {
+            report(format!("invalid creation time {} will be removed", other));
+            None
+        }
+    };
+
+    Some(TodoItem {
+        id,
+        description: description.to_string(),
+        priority,
+        due,
+        tags,
+        done,
+        notes,
+        created,
+    })
+}
+
+/// Byte ranges of the objects directly inside the top-level array, or of
+/// the top-level object itself. An object cut off by the end of the file
+/// runs to the end.
+fn objects(text: &str) -> Vec<(usize, usize)> {
+    let base = if text.trim_start().starts_with('[') {
+        1
+    } else {
+        0
+    };
+    let mut ranges = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                if c == '{' && depth == base {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            '}' | ']' => {
+                depth -= 1;
+                if c == '}'
+                    && depth == base
+                    && let Some(s) = start.take()
+                {
+                    ranges.push((s, i + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push((s, text.len()));
+    }
+    ranges
+}
+
+/// `{"a": 1,}` -> `{"a": 1}`
+fn drop_trailing_commas(object: &str) -> String {
+    let mut out = String::with_capacity(object.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    // A comma not yet written, and the whitespace seen after it.
+    let mut pending: Option<String> = None;
+
+    for c in object.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        if let Some(mut held) = pending.take() {
+            if c.is_whitespace() {
+                held.push(c);
+                pending = Some(held);
+                continue;
+            }
+            if c != '}' && c != ']' {
+                out.push(',');
+            }
+            out.push_str(&held);
+        }
+        match c {
+            ',' => pending = Some(String::new()),
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn healthy_files_have_no_problems() {
+        let text = r#"[
+  {"id": "a", "description": "One", "priority": 1, "due": "2025-07-10",
+   "tags": ["x"], "done": false, "notes": null, "created": 5}
+]"#;
+        let diagnosis = diagnose_json(text);
+        assert!(diagnosis.problems.is_empty());
+        assert_eq!(diagnosis.items.len(), 1);
+        assert!(diagnose_json("").problems.is_empty());
+    }
+
+    #[test]
+    fn salvages_items_around_syntax_errors() {
+        let text = "[\n  {\"id\": \"a\", \"description\": \"One\", \"done\": false,},\n  {\"id\": \"b\", \"description\": \"Two\" \"done\": true},\n  {\"id\": \"c\", \"description\": \"Th}ree\", \"done\": true}\n]";
+        let diagnosis = diagnose_json(text);
+
+        let descriptions: Vec<&str> = diagnosis
+            .items
+            .iter()
+            .map(|i| i.description.as_str())
+            .collect();
+        assert_eq!(descriptions, vec!["One", "Th}ree"]);
+        let messages: Vec<String> = diagnosis.problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 2: column 51: trailing comma",
+                "line 3: this item cannot be read and will be dropped",
+            ]
+        );
+    }
+
+    #[test]
+    fn repairs_fields_and_duplicate_ids() {
+        let text = r#"[
+  {"id": "a", "description": "One", "priority": 300, "due": "2025/07/10", "done": false},
+  {"id": "a", "description": "Two", "priority": "2", "due": "soon", "tags": "x, y"},
+  {"id": "a", "description": "One", "due": "2025-07-10", "done": false}
+]"#;
+        let diagnosis = diagnose_json(text);
+        let items = &diagnosis.items;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].priority, None);
+        assert_eq!(items[0].due.as_deref(), Some("2025-07-10"));
+        assert_eq!(items[1].priority, Some(2));
+        assert_eq!(items[1].due, None);
+        assert_eq!(items[1].tags, Some(vec!["x".into(), "y".into()]));
+        assert_ne!(items[1].id, "a");
+
+        let messages: Vec<String> = diagnosis.problems.iter().map(|p| p.to_string()).collect();
+        assert!(messages.contains(
+            &"line 2: invalid priority 300 (expected 0 to 255) will be cleared on \"One\"".into()
+        ));
+        assert!(messages.contains(
+            &"line 2: due date '2025/07/10' on \"One\" will be written as 2025-07-10".into()
+        ));
+        assert!(messages.contains(
+            &"line 3: missing completion state; it will be marked not done on \"Two\"".into()
+        ));
+        assert!(
+            messages.contains(&"line 4: \"One\" is listed twice; the copy will be dropped".into())
+        );
+    }
+
+    #[test]
+    fn truncated_file_keeps_complete_items() {
+        let text = "[\n  {\"description\": \"One\", \"done\": false},\n  {\"description\": \"Tw";
+        let diagnosis = diagnose_json(text);
+        assert_eq!(diagnosis.items.len(), 1);
+        assert_eq!(diagnosis.problems.len(), 2);
+    }
+
+    #[test]
+    fn single_items_are_read_around_stray_commas() {
+        let (item, problems) = diagnose_item("{\"id\": \"a\", \"description\": \"One\",}");
+        assert_eq!(item.unwrap().description, "One");
+        let messages: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 1: column 34: trailing comma",
+                "missing completion state; it will be marked not done on \"One\"",
+            ]
+        );
+
+        let (item, problems) = diagnose_item("{\"description\": ");
+        assert!(item.is_none());
+        assert_eq!(
+            problems.last().unwrap().message,
+            "this item cannot be read and will be dropped"
+        );
+    }
+}
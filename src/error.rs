@@ -0,0 +1,136 @@
+//! The error type every fallible operation returns.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub type Result<T> = std::result::Result<T, TodoError>;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TodoError {
+    /// Reading or writing the todo file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// The todo file exists but is not valid.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The user asked for something that cannot be done.
+    InvalidInput(String),
+    /// Setting up or drawing the terminal UI failed.
+    Terminal(io::Error),
+    /// A merge finished but left conflicts for the user to resolve.
+    Conflict(String),
+}
+
+impl TodoError {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        TodoError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Maps a `serde_json` failure on `path` to an I/O or parse error, keeping
+    /// the line and column so the user can find the problem.
+    pub fn json(path: &Path, err: serde_json::Error) -> Self {
+        if err.is_io() {
+            return TodoError::io(path, err.into());
+        }
+        TodoError::Parse {
+            path: path.to_path_buf(),
+            line: err.line(),
+            column: err.column(),
+            message: strip_position(&err.to_string()),
+        }
+    }
+
+    /// Process exit status for this error. 2 is left to clap's usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TodoError::Io { .. } => 3,
+            TodoError::Parse { .. } => 4,
+            TodoError::InvalidInput(_) => 5,
+            TodoError::Terminal(_) => 6,
+            // git treats any non-zero status from a merge driver as a conflict.
+            TodoError::Conflict(_) => 1,
+        }
+    }
+}
+
+// serde_json appends " at line X column Y", which we already report.
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(idx) => message[..idx].to_string(),
+        None => message.to_string(),
+    }
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoError::Io { path, source } => {
+                write!(f, "could not access {}: {}", path.display(), source)
+            }
+            TodoError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "{}:{}:{}: invalid todo file: {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            TodoError::InvalidInput(message) | TodoError::Conflict(message) => {
+                write!(f, "{}", message)
+            }
+            TodoError::Terminal(source) => write!(f, "terminal error: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for TodoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TodoError::Io { source, .. } | TodoError::Terminal(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_syntax_errors_keep_position() {
+        let err = serde_json::from_str::<serde_json::Value>("[\n  1,\n]").unwrap_err();
+        let err = TodoError::json(Path::new("todo.json"), err);
+
+        assert_eq!(err.exit_code(), 4);
+        assert_eq!(
+            err.to_string(),
+            "todo.json:3:1: invalid todo file: trailing comma"
+        );
+    }
+
+    #[test]
+    fn io_errors_mention_path() {
+        let err = TodoError::io(
+            Path::new("/nope/todo.json"),
+            io::Error::new(io::ErrorKind::PermissionDenied, "permission denied"),
+        );
+        assert_eq!(err.exit_code(), 3);
+        assert_eq!(
+            err.to_string(),
+            "could not access /nope/todo.json: permission denied"
+        );
+    }
+}
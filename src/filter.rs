@@ -0,0 +1,74 @@
+//! Choosing which items to show, as `todo list --tag work` does.
+
+use crate::storage::TodoItem;
+
+/// Which items `todo list` shows. The default is every pending item.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListFilter {
+    /// Include completed items.
+    pub show_all: bool,
+    /// Only items with exactly this priority.
+    pub priority: Option<u8>,
+    /// Only items with this tag.
+    pub tag: Option<String>,
+    /// Only items due on this date (`YYYY-MM-DD`).
+    pub due: Option<String>,
+}
+
+impl ListFilter {
+    /// Whether `item` passes every part of the filter.
+    pub fn matches(&self, item: &TodoItem) -> bool {
+        (self.show_all || !item.done)
+            && self.priority.is_none_or(|p| item.priority == Some(p))
+            && self
+                .tag
+                .as_ref()
+                .is_none_or(|tag| item.tags.as_ref().is_some_and(|tags| tags.contains(tag)))
+            && self
+                .due
+                .as_ref()
+                .is_none_or(|due| item.due.as_deref() == Some(due.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(priority: Option<u8>, tags: Option<Vec<&str>>, done: bool) -> TodoItem {
+        TodoItem {
+            description: "x".into(),
+            priority,
+            due: Some("2025-07-10".into()),
+            tags: tags.map(|t| t.into_iter().map(String::from).collect()),
+            done,
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn default_filter_hides_only_completed() {
+        let filter = ListFilter::default();
+        assert!(filter.matches(&item(None, None, false)));
+        assert!(!filter.matches(&item(None, None, true)));
+
+        let all = ListFilter {
+            show_all: true,
+            ..ListFilter::default()
+        };
+        assert!(all.matches(&item(None, None, true)));
+    }
+
+    #[test]
+    fn filter_fields_must_all_match() {
+        let filter = ListFilter {
+            priority: Some(1),
+            tag: Some("work".into()),
+            due: Some("2025-07-10".into()),
+            ..ListFilter::default()
+        };
+        assert!(filter.matches(&item(Some(1), Some(vec!["home", "work"]), false)));
+        assert!(!filter.matches(&item(Some(2), Some(vec!["work"]), false)));
+        assert!(!filter.matches(&item(Some(1), None, false)));
+    }
+}
@@ -0,0 +1,253 @@
+//! CSV with a header row, as written by spreadsheets or `todo list --format csv`.
+//!
+//! Columns are matched by name, case-insensitively; common alternatives such
+//! as `title` or `due_date` are accepted. Columns we do not know are reported
+//! once and ignored.
+
+use super::{Imported, Notice, empty_item, parse_date, push_tag};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Id,
+    Description,
+    Done,
+    Priority,
+    Due,
+    Tags,
+    Notes,
+    Created,
+    /// Our own export writes a row number; it carries no information.
+    Index,
+}
+
+fn field_for(header: &str) -> Option<Field> {
+    let name = header.trim().to_lowercase();
+    let field = match name.as_str() {
+        "id" => Field::Id,
+        "description" | "title" | "task" | "summary" | "name" => Field::Description,
+        "done" | "completed" | "status" => Field::Done,
+        "priority" => Field::Priority,
+        "due" | "due_date" | "due date" => Field::Due,
+        "tags" | "tag" => Field::Tags,
+        "notes" | "note" => Field::Notes,
+        "created" => Field::Created,
+        "index" => Field::Index,
+        _ => return None,
+    };
+    Some(field)
+}
+
+/// Fails when the input has no header row or no description column.
+pub fn parse(input: &str) -> Result<Imported, String> {
+    let records = parse_records(input)?;
+    let mut records = records.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Imported::default());
+    };
+
+    let fields: Vec<Option<Field>> = header.iter().map(|h| field_for(h)).collect();
+    if !fields.contains(&Some(Field::Description)) {
+        return Err("no description column in the header row".into());
+    }
+
+    let mut imported = Imported::default();
+    let unknown: Vec<&str> = header
+        .iter()
+        .zip(&fields)
+        .filter(|(_, f)| f.is_none())
+        .map(|(h, _)| h.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        imported.notices.push(Notice::new(
+            "line 1",
+            format!("columns ignored: {}", unknown.join(", ")),
+        ));
+    }
+
+    for (line, record) in records {
+        if record.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let location = format!("line {}", line);
+        let mut item = empty_item(String::new());
+        let mut notices = Vec::new();
+
+        for (field, value) in fields.iter().zip(&record) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match field {
+                Some(Field::Id) => item.id = value.to_string(),
+                Some(Field::Description) => item.description = value.to_string(),
+                Some(Field::Done) => item.done = is_truthy(value),
+                Some(Field::Priority) => match parse_priority(value) {
+                    Some(p) => item.priority = Some(p),
+                    None => notices.push(format!("priority '{}' dropped", value)),
+                },
+                Some(Field::Due) => match parse_date(value) {
+                    Some(_) => item.due = Some(value.to_string()),
+                    None => notices.push(format!("due date '{}' dropped", value)),
+                },
+                Some(Field::Tags) => {
+                    for tag in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                        push_tag(&mut item, tag);
+                    }
+                }
+                Some(Field::Notes) => item.notes = Some(value.to_string()),
+                Some(Field::Created) => match value.parse() {
+                    Ok(created) => item.created = Some(created),
+                    Err(_) => notices.push(format!("created '{}' dropped", value)),
+                },
+                Some(Field::Index) | None => {}
+            }
+        }
+        if record.len() > fields.len() {
+            notices.push(format!(
+                "{} extra cells dropped",
+                record.len() - fields.len()
+            ));
+        }
+
+        if item.description.is_empty() {
+            imported.skip(location, "no description");
+            continue;
+        }
+        imported.notices.extend(
+            notices
+                .into_iter()
+                .map(|m| Notice::new(location.clone(), m)),
+        );
+        imported.items.push(item);
+    }
+
+    Ok(imported)
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.to_lowercase().as_str(),
+        "true" | "yes" | "y" | "1" | "x" | "done" | "completed"
+    )
+}
+
+/// A number, or a todo.txt-style letter where A is 0.
+fn parse_priority(value: &str) -> Option<u8> {
+    if let Ok(n) = value.parse() {
+        return Some(n);
+    }
+    match value.to_ascii_uppercase().as_bytes() {
+        [c @ b'A'..=b'Z'] => Some(c - b'A'),
+        _ => None,
+    }
+}
+
+/// Splits RFC 4180 CSV into records, each tagged with the line it starts on.
+/// Quoted cells may contain commas, doubled quotes and newlines.
+fn parse_records(input: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    cell.push(c);
+                }
+                _ => cell.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut cell));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            _ => cell.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("line {}: unterminated quoted cell", record_line));
+    }
+    if !cell.is_empty() || !record.is_empty() {
+        record.push(cell);
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_quoted_records() {
+        let records = parse_records("a,b\r\n\"x, \"\"y\"\"\",\"multi\nline\"\nlast,\n").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                (1, vec!["a".to_string(), "b".to_string()]),
+                (2, vec!["x, \"y\"".to_string(), "multi\nline".to_string()]),
+                (4, vec!["last".to_string(), String::new()]),
+            ]
+        );
+        assert!(parse_records("\"open").is_err());
+    }
+
+    #[test]
+    fn maps_columns_by_name() {
+        let input = "Title,Due Date,Priority,Status,Tags,Owner\n\
+                     Pay rent,2025-02-01,B,done,\"home, money\",me\n\
+                     Call bank,someday,3,,,\n";
+        let imported = parse(input).unwrap();
+
+        assert_eq!(imported.items.len(), 2);
+        let rent = &imported.items[0];
+        assert_eq!(rent.description, "Pay rent");
+        assert_eq!(rent.due.as_deref(), Some("2025-02-01"));
+        assert_eq!(rent.priority, Some(1));
+        assert!(rent.done);
+        assert_eq!(rent.tags, Some(vec!["home".into(), "money".into()]));
+        assert_eq!(imported.items[1].priority, Some(3));
+        assert_eq!(
+            imported.notices,
+            vec![
+                Notice::new("line 1", "columns ignored: Owner"),
+                Notice::new("line 3", "due date 'someday' dropped"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_own_export() {
+        let input = "id,index,done,priority,due,description,tags,notes,created\n\
+                     ab12cd34,1,false,2,,Write docs,docs,,1735776000\n";
+        let imported = parse(input).unwrap();
+
+        assert_eq!(imported.items[0].id, "ab12cd34");
+        assert_eq!(imported.items[0].created, Some(1_735_776_000));
+        assert!(imported.notices.is_empty());
+    }
+
+    #[test]
+    fn requires_description_column() {
+        assert!(parse("due,priority\n2025-01-01,1\n").is_err());
+    }
+}
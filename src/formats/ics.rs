@@ -0,0 +1,446 @@
+//! iCalendar (RFC 5545) VTODO components.
+//!
+//! Our priority 0 is the most urgent; iCalendar's is 1, and it stops at 9, so
+//! priorities 9 and up are written as 9 with the real value in
+//! `X-CRIX-PRIORITY`. The item ID goes into `UID` as `<id>@crix-todo`.
+
+use super::{Imported, Notice, empty_item, push_tag};
+use crate::storage::TodoItem;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+const UID_SUFFIX: &str = "@crix-todo";
+const X_PRIORITY: &str = "X-CRIX-PRIORITY";
+
+/// Properties that carry no task data of their own.
+const IGNORED: &[&str] = &[
+    "DTSTAMP",
+    "SEQUENCE",
+    "LAST-MODIFIED",
+    "COMPLETED",
+    "PERCENT-COMPLETE",
+];
+
+/// Writes `items` as one VCALENDAR. `stamp` is the `DTSTAMP` for every item.
+pub fn write(items: &[TodoItem], stamp: u64) -> String {
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(
+        &mut out,
+        &format!(
+            "PRODID:-//crix-todo//crix-todo {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+    );
+
+    for item in items {
+        line(&mut out, "BEGIN:VTODO");
+        line(&mut out, &format!("UID:{}{}", item.id, UID_SUFFIX));
+        line(&mut out, &format!("DTSTAMP:{}", datetime(stamp)));
+        if let Some(created) = item.created {
+            line(&mut out, &format!("CREATED:{}", datetime(created)));
+        }
+        line(&mut out, &format!("SUMMARY:{}", escape(&item.description)));
+        if let Some(priority) = item.priority {
+            line(
+                &mut out,
+                &format!("PRIORITY:{}", priority.saturating_add(1).min(9)),
+            );
+            if priority >= 9 {
+                line(&mut out, &format!("{}:{}", X_PRIORITY, priority));
+            }
+        }
+        if let Some(due) = item.due.as_deref().and_then(super::parse_date) {
+            line(
+                &mut out,
+                &format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")),
+            );
+        }
+        if let Some(tags) = item.tags.as_ref().filter(|t| !t.is_empty()) {
+            let tags: Vec<String> = tags.iter().map(|t| escape(t)).collect();
+            line(&mut out, &format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(notes) = &item.notes {
+            line(&mut out, &format!("DESCRIPTION:{}", escape(notes)));
+        }
+        let status = if item.done {
+            "COMPLETED"
+        } else {
+            "NEEDS-ACTION"
+        };
+        line(&mut out, &format!("STATUS:{}", status));
+        line(&mut out, "END:VTODO");
+    }
+
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Appends a content line, folded at 75 octets and ended with CRLF.
+fn line(out: &mut String, content: &str) {
+    let mut width = 0;
+    for c in content.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn datetime(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undoes `escape`, splitting on unescaped commas when `list` is set.
+fn unescape(text: &str, list: bool) -> Vec<String> {
+    let mut values = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let current = values.last_mut().expect("never empty");
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => current.push('\n'),
+                Some(other) => current.push(other),
+                None => {}
+            },
+            ',' if list => values.push(String::new()),
+            _ => current.push(c),
+        }
+    }
+    values
+}
+
+/// A content line split into its parts; parameter values are left raw.
+struct Property<'a> {
+    name: String,
+    params: Vec<&'a str>,
+    value: &'a str,
+}
+
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    let colon = find_value_colon(line)?;
+    let mut head = line[..colon].split(';');
+    let name = head.next()?.trim().to_ascii_uppercase();
+    Some(Property {
+        name,
+        params: head.collect(),
+        value: &line[colon + 1..],
+    })
+}
+
+// Parameter values may be quoted and contain colons.
+fn find_value_colon(line: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Joins folded lines, keeping the line number each logical line starts on.
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (n, raw) in input.lines().enumerate() {
+        let raw = raw.trim_end_matches('\r');
+        if let Some(rest) = raw.strip_prefix([' ', '\t'])
+            && let Some((_, last)) = lines.last_mut()
+        {
+            last.push_str(rest);
+        } else if !raw.is_empty() {
+            lines.push((n + 1, raw.to_string()));
+        }
+    }
+    lines
+}
+
+/// Fails when the input contains no VCALENDAR.
+pub fn parse(input: &str) -> Result<Imported, String> {
+    let lines = unfold(input);
+    if !lines
+        .iter()
+        .any(|(_, l)| l.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err("not an iCalendar file (no BEGIN:VCALENDAR)".into());
+    }
+
+    let mut imported = Imported::default();
+    let mut current: Option<(usize, Vec<Property>)> = None;
+    // Nesting inside a VTODO (such as VALARM) or inside a component we skip.
+    let mut depth = 0;
+
+    for (n, text) in &lines {
+        let Some(property) = parse_property(text) else {
+            imported.notices.push(Notice::new(
+                format!("line {}", n),
+                "unreadable line ignored",
+            ));
+            continue;
+        };
+        let value = property.value.trim().to_ascii_uppercase();
+        match property.name.as_str() {
+            "BEGIN" if value == "VCALENDAR" => {}
+            "BEGIN" if depth == 0 && current.is_none() && value == "VTODO" => {
+                current = Some((*n, Vec::new()));
+            }
+            "BEGIN" => {
+                if depth == 0
+                    && current.is_none()
+                    && matches!(value.as_str(), "VEVENT" | "VJOURNAL")
+                {
+                    imported.skip(format!("line {}", n), format!("{} is not a task", value));
+                }
+                depth += 1;
+            }
+            "END" if depth > 0 => depth -= 1,
+            "END" if value == "VTODO" => {
+                if let Some((start, properties)) = current.take() {
+                    convert(&properties, format!("line {}", start), &mut imported);
+                }
+            }
+            _ if depth == 0 => {
+                if let Some((_, properties)) = current.as_mut() {
+                    properties.push(property);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(imported)
+}
+
+fn convert(properties: &[Property], location: String, imported: &mut Imported) {
+    let mut item = empty_item(String::new());
+    let mut notices = Vec::new();
+    let mut dropped = Vec::new();
+    let mut x_priority = None;
+
+    for property in properties {
+        let text = || unescape(property.value, false).remove(0);
+        match property.name.as_str() {
+            "SUMMARY" => item.description = text().trim().to_string(),
+            "DESCRIPTION" => item.notes = Some(text()).filter(|n| !n.is_empty()),
+            "UID" => {
+                if let Some(id) = property.value.strip_suffix(UID_SUFFIX) {
+                    item.id = id.to_string();
+                }
+            }
+            "CREATED" => item.created = parse_datetime(property.value).map(|(ts, _)| ts),
+            "PRIORITY" => match property.value.trim().parse::<u8>() {
+                Ok(0) => {}
+                Ok(p @ 1..=9) => item.priority = Some(p - 1),
+                _ => notices.push(format!("priority '{}' dropped", property.value)),
+            },
+            X_PRIORITY => x_priority = property.value.trim().parse::<u8>().ok(),
+            "DUE" => match parse_due(property) {
+                Some((date, had_time)) => {
+                    if had_time {
+                        notices.push(format!("due time dropped from '{}'", property.value));
+                    }
+                    item.due = Some(date.format("%Y-%m-%d").to_string());
+                }
+                None => notices.push(format!("due '{}' dropped", property.value)),
+            },
+            "CATEGORIES" => {
+                for tag in unescape(property.value, true) {
+                    let tag = tag.trim();
+                    if !tag.is_empty() {
+                        push_tag(&mut item, tag);
+                    }
+                }
+            }
+            "STATUS" => match property.value.trim().to_ascii_uppercase().as_str() {
+                "COMPLETED" => item.done = true,
+                "NEEDS-ACTION" | "IN-PROCESS" => {}
+                "CANCELLED" => {
+                    item.done = true;
+                    notices.push("cancelled task imported as done".to_string());
+                }
+                other => notices.push(format!("status '{}' dropped", other)),
+            },
+            name if IGNORED.contains(&name) => {}
+            name => {
+                if !dropped.contains(&name) {
+                    dropped.push(name);
+                }
+            }
+        }
+    }
+    if x_priority.is_some() {
+        item.priority = x_priority;
+    }
+    if properties.iter().any(|p| p.name == "COMPLETED") {
+        item.done = true;
+    }
+    if !dropped.is_empty() {
+        notices.push(format!("properties dropped: {}", dropped.join(", ")));
+    }
+
+    if item.description.is_empty() {
+        imported.skip(location, "no SUMMARY");
+        return;
+    }
+    imported.notices.extend(
+        notices
+            .into_iter()
+            .map(|m| Notice::new(location.clone(), m)),
+    );
+    imported.items.push(item);
+}
+
+/// A DATE or DATE-TIME value, and whether it carried a time of day. Times
+/// in a named zone are taken as written.
+fn parse_due(property: &Property) -> Option<(NaiveDate, bool)> {
+    let is_date = property
+        .params
+        .iter()
+        .any(|p| p.eq_ignore_ascii_case("VALUE=DATE"));
+    let value = property.value.trim();
+    if is_date || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(|d| (d, false));
+    }
+    parse_datetime(value).map(|(_, dt)| (dt.date(), dt.time() != chrono::NaiveTime::MIN))
+}
+
+fn parse_datetime(value: &str) -> Option<(u64, NaiveDateTime)> {
+    let value = value.trim();
+    let dt = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+    Some((dt.and_utc().timestamp().max(0) as u64, dt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> TodoItem {
+        TodoItem {
+            id: "ab12cd34".into(),
+            description: "Plan trip; book hotel, flights".into(),
+            priority: Some(12),
+            due: Some("2025-07-10".into()),
+            tags: Some(vec!["travel".into(), "a,b".into()]),
+            done: true,
+            notes: Some("line one\nline two".into()),
+            created: Some(1_735_776_000),
+        }
+    }
+
+    #[test]
+    fn writes_vtodo() {
+        let out = write(&[item()], 1_735_862_400);
+        let lines: Vec<&str> = out.split("\r\n").collect();
+
+        assert_eq!(
+            lines[3..lines.len() - 2],
+            [
+                "BEGIN:VTODO",
+                "UID:ab12cd34@crix-todo",
+                "DTSTAMP:20250103T000000Z",
+                "CREATED:20250102T000000Z",
+                "SUMMARY:Plan trip\\; book hotel\\, flights",
+                "PRIORITY:9",
+                "X-CRIX-PRIORITY:12",
+                "DUE;VALUE=DATE:20250710",
+                "CATEGORIES:travel,a\\,b",
+                "DESCRIPTION:line one\\nline two",
+                "STATUS:COMPLETED",
+                "END:VTODO",
+            ]
+        );
+        assert_eq!(lines[lines.len() - 2], "END:VCALENDAR");
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let mut long = item();
+        long.description = "é".repeat(60);
+        let out = write(&[long], 0);
+
+        assert!(out.split("\r\n").all(|l| l.len() <= 75));
+        let imported = parse(&out).unwrap();
+        assert_eq!(imported.items[0].description, "é".repeat(60));
+    }
+
+    #[test]
+    fn round_trips_our_fields() {
+        let original = item();
+        let imported = parse(&write(std::slice::from_ref(&original), 0)).unwrap();
+
+        assert_eq!(imported.items, vec![original]);
+        assert!(imported.notices.is_empty());
+    }
+
+    #[test]
+    fn round_trips_the_lowest_priority() {
+        let mut lowest = item();
+        lowest.priority = Some(u8::MAX);
+        let out = write(std::slice::from_ref(&lowest), 0);
+
+        assert!(out.contains("PRIORITY:9\r\nX-CRIX-PRIORITY:255\r\n"));
+        assert_eq!(parse(&out).unwrap().items, vec![lowest]);
+    }
+
+    #[test]
+    fn reads_foreign_calendar() {
+        let input = "BEGIN:VCALENDAR\n\
+                     BEGIN:VEVENT\nSUMMARY:Meeting\nEND:VEVENT\n\
+                     BEGIN:VTODO\nUID:123-abc\nSUMMARY:Call\n  plumber\n\
+                     PRIORITY:5\nDUE;TZID=Europe/Paris:20250110T093000\n\
+                     GEO:1;2\nBEGIN:VALARM\nACTION:DISPLAY\nEND:VALARM\n\
+                     END:VTODO\nEND:VCALENDAR\n";
+        let imported = parse(input).unwrap();
+
+        assert_eq!(imported.skipped, 1);
+        let call = &imported.items[0];
+        assert_eq!(call.id, "");
+        assert_eq!(call.description, "Call plumber");
+        assert_eq!(call.priority, Some(4));
+        assert_eq!(call.due.as_deref(), Some("2025-01-10"));
+        let messages: Vec<&str> = imported
+            .notices
+            .iter()
+            .map(|n| n.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "VEVENT is not a task",
+                "due time dropped from '20250110T093000'",
+                "properties dropped: GEO",
+            ]
+        );
+        assert_eq!(imported.notices[1].location, "line 5");
+    }
+
+    #[test]
+    fn rejects_non_calendar() {
+        assert!(parse("SUMMARY:x").is_err());
+    }
+}
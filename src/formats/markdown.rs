@@ -0,0 +1,424 @@
+//! GitHub-style Markdown checklists.
+//!
+//! Each item is a `- [ ]` or `- [x]` line with inline markers: `#tag`,
+//! `due:YYYY-MM-DD` and `!priority`. Notes are nested bullets under the item.
+//! Words in the description that would read as a marker are escaped with a
+//! backslash.
+
+use super::{GroupBy, Imported, Notice, empty_item, parse_date, push_tag};
+use crate::storage::TodoItem;
+use std::collections::BTreeMap;
+
+pub fn write(items: &[TodoItem], group_by: GroupBy) -> String {
+    let mut out = String::new();
+    for (heading, group) in groups(items, group_by) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n\n", heading));
+        for item in group {
+            write_item(&mut out, item);
+        }
+    }
+    out
+}
+
+/// Headings with their items, in file order within each group. Items are
+/// listed under their first tag only, so each appears once.
+fn groups(items: &[TodoItem], group_by: GroupBy) -> Vec<(String, Vec<&TodoItem>)> {
+    match group_by {
+        GroupBy::Priority => {
+            // Keyed so that items without a priority come last.
+            let mut groups: BTreeMap<(bool, Option<u8>), Vec<&TodoItem>> = BTreeMap::new();
+            for item in items {
+                let key = (item.priority.is_none(), item.priority);
+                groups.entry(key).or_default().push(item);
+            }
+            groups
+                .into_iter()
+                .map(|((_, priority), group)| match priority {
+                    Some(p) => (format!("Priority {}", p), group),
+                    None => ("No priority".to_string(), group),
+                })
+                .collect()
+        }
+        GroupBy::Tag => {
+            let mut groups: Vec<(Option<&String>, Vec<&TodoItem>)> = Vec::new();
+            for item in items {
+                let key = item.tags.as_ref().and_then(|t| t.first());
+                match groups.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, group)) => group.push(item),
+                    None => groups.push((key, vec![item])),
+                }
+            }
+            groups.sort_by_key(|(tag, _)| tag.is_none());
+            groups
+                .into_iter()
+                .map(|(tag, group)| match tag {
+                    Some(tag) => (format!("#{}", tag), group),
+                    None => ("Untagged".to_string(), group),
+                })
+                .collect()
+        }
+    }
+}
+
+fn write_item(out: &mut String, item: &TodoItem) {
+    for line in item_lines(item) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+/// The checkbox line for `item`, followed by one nested bullet per line of
+/// its notes.
+pub fn item_lines(item: &TodoItem) -> Vec<String> {
+    let mut line = format!("- [{}]", if item.done { "x" } else { " " });
+    for word in item.description.split_whitespace() {
+        line.push(' ');
+        if is_marker(word) {
+            line.push('\\');
+        }
+        line.push_str(word);
+    }
+    for tag in item.tags.iter().flatten() {
+        line.push_str(&format!(" #{}", tag));
+    }
+    if let Some(due) = &item.due {
+        line.push_str(&format!(" due:{}", due));
+    }
+    if let Some(priority) = item.priority {
+        line.push_str(&format!(" !{}", priority));
+    }
+
+    let mut lines = vec![line];
+    for note in item.notes.iter().flat_map(|n| n.lines()) {
+        lines.push(format!("  - {}", note).trim_end().to_string());
+    }
+    lines
+}
+
+/// An HTML comment carrying what the checkbox line cannot show; it is
+/// invisible once the Markdown is rendered.
+pub fn metadata_comment(item: &TodoItem) -> String {
+    let mut comment = format!("<!-- id:{}", item.id);
+    if let Some(created) = item.created {
+        comment.push_str(&format!(" created:{}", created));
+    }
+    comment.push_str(" -->");
+    comment
+}
+
+fn is_marker(word: &str) -> bool {
+    tag_marker(word).is_some()
+        || priority_marker(word).is_some()
+        || word.starts_with("due:")
+        || word.starts_with('\\')
+}
+
+/// `#tag`, but not `#123`, which is usually an issue reference.
+fn tag_marker(word: &str) -> Option<&str> {
+    word.strip_prefix('#')
+        .filter(|t| !t.is_empty() && !t.starts_with('#'))
+        .filter(|t| !t.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn priority_marker(word: &str) -> Option<u8> {
+    word.strip_prefix('!').and_then(|p| p.parse().ok())
+}
+
+/// A `- [ ]`, `* [x]` or `+ [X]` line: its indent, done flag and text.
+pub fn checkbox(line: &str) -> Option<(usize, bool, &str)> {
+    let indent = indent_of(line);
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let (done, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, text)
+    } else {
+        return None;
+    };
+    Some((indent, done, text.trim()))
+}
+
+pub fn indent_of(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// A checklist item and the lines it spans.
+#[derive(Debug)]
+pub struct Block {
+    /// Index of the checkbox line.
+    pub start: usize,
+    /// Index one past the last line of the item's notes.
+    pub end: usize,
+    /// Indices of note lines that follow a nested item, outside `start..end`.
+    pub later_notes: Vec<usize>,
+    pub item: TodoItem,
+    /// Markers that could not be read and were kept in the description.
+    pub lossy: Vec<String>,
+}
+
+/// Finds every checklist item in `lines`. Text indented under a checkbox
+/// becomes that item's notes, up to the next blank or less indented line;
+/// nested checkboxes are items of their own, and text after them that is
+/// indented only under their parent goes back to the parent. Fenced code is
+/// skipped.
+pub fn blocks(lines: &[&str]) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    // The indent and block index of each item a note could still belong to,
+    // innermost last.
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            open.clear();
+            continue;
+        }
+        if trimmed.is_empty() {
+            open.clear();
+            continue;
+        }
+
+        let indent = indent_of(line);
+        while open.last().is_some_and(|&(outer, _)| outer >= indent) {
+            open.pop();
+        }
+
+        if let Some((_, done, text)) = checkbox(line) {
+            let (item, lossy) = parse_text(text, done);
+            open.push((indent, blocks.len()));
+            blocks.push(Block {
+                start: index,
+                end: index + 1,
+                later_notes: Vec::new(),
+                item,
+                lossy,
+            });
+            continue;
+        }
+
+        let Some(&(_, owner)) = open.last() else {
+            continue;
+        };
+        let block = &mut blocks[owner];
+        let text = note_text(trimmed);
+        match &mut block.item.notes {
+            Some(notes) => {
+                notes.push('\n');
+                notes.push_str(text);
+            }
+            None => block.item.notes = Some(text.to_string()),
+        }
+        if block.end == index && block.later_notes.is_empty() {
+            block.end = index + 1;
+        } else {
+            block.later_notes.push(index);
+        }
+    }
+
+    blocks
+}
+
+// Notes are usually nested bullets; the bullet itself is not part of them.
+fn note_text(line: &str) -> &str {
+    match line.strip_prefix(['-', '*', '+']) {
+        Some("") => "",
+        Some(rest) if rest.starts_with(' ') => &rest[1..],
+        _ => line,
+    }
+}
+
+pub fn parse(input: &str) -> Imported {
+    let mut imported = Imported::default();
+    let lines: Vec<&str> = input.lines().collect();
+
+    for block in blocks(&lines) {
+        let location = format!("line {}", block.start + 1);
+        if block.item.description.is_empty() {
+            imported.skip(location, "no description");
+            continue;
+        }
+        imported.notices.extend(
+            block
+                .lossy
+                .into_iter()
+                .map(|m| Notice::new(location.clone(), m)),
+        );
+        imported.items.push(block.item);
+    }
+
+    imported
+}
+
+/// Splits a trailing `metadata_comment` off checkbox text.
+fn split_metadata(text: &str) -> (&str, Option<&str>) {
+    if let Some(body) = text.strip_suffix("-->")
+        && let Some(start) = body.rfind("<!--")
+    {
+        return (body[..start].trim_end(), Some(body[start + 4..].trim()));
+    }
+    (text, None)
+}
+
+fn parse_text(text: &str, done: bool) -> (TodoItem, Vec<String>) {
+    let mut item = empty_item(String::new());
+    item.done = done;
+    let mut lossy = Vec::new();
+    let mut words = Vec::new();
+
+    let (text, metadata) = split_metadata(text);
+    for field in metadata.iter().flat_map(|m| m.split_whitespace()) {
+        match field.split_once(':') {
+            Some(("id", id)) => item.id = id.to_string(),
+            Some(("created", created)) => item.created = created.parse().ok(),
+            _ => {}
+        }
+    }
+
+    for word in text.split_whitespace() {
+        if let Some(escaped) = word.strip_prefix('\\') {
+            words.push(escaped);
+        } else if let Some(tag) = tag_marker(word) {
+            push_tag(&mut item, tag);
+        } else if let Some(priority) = priority_marker(word) {
+            item.priority = Some(priority);
+        } else if let Some(due) = word.strip_prefix("due:") {
+            if parse_date(due).is_some() {
+                item.due = Some(due.to_string());
+            } else {
+                lossy.push(format!("'{}' is not a date; kept in the description", word));
+                words.push(word);
+            }
+        } else {
+            words.push(word);
+        }
+    }
+
+    item.description = words.join(" ");
+    (item, lossy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(description: &str, priority: Option<u8>, tags: &[&str], done: bool) -> TodoItem {
+        let mut item = empty_item(description.into());
+        item.priority = priority;
+        if !tags.is_empty() {
+            item.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+        }
+        item.done = done;
+        item
+    }
+
+    #[test]
+    fn writes_groups_by_priority() {
+        let mut report = item("Write report", Some(1), &["work"], false);
+        report.due = Some("2025-01-10".into());
+        report.notes = Some("draft\nreview".into());
+        let items = vec![
+            item("Water plants", None, &[], true),
+            report,
+            item("Fix #12 and #urgent", Some(10), &[], false),
+        ];
+
+        assert_eq!(
+            write(&items, GroupBy::Priority),
+            "## Priority 1\n\n\
+             - [ ] Write report #work due:2025-01-10 !1\n  - draft\n  - review\n\n\
+             ## Priority 10\n\n\
+             - [ ] Fix #12 and \\#urgent !10\n\n\
+             ## No priority\n\n\
+             - [x] Water plants\n"
+        );
+    }
+
+    #[test]
+    fn writes_groups_by_first_tag() {
+        let items = vec![
+            item("a", None, &["home", "work"], false),
+            item("b", None, &[], false),
+            item("c", None, &["work"], false),
+        ];
+        let out = write(&items, GroupBy::Tag);
+        let headings: Vec<&str> = out.lines().filter(|l| l.starts_with("##")).collect();
+        assert_eq!(headings, vec!["## #home", "## #work", "## Untagged"]);
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut report = item("Write #1 report !", Some(2), &["work", "q1"], true);
+        report.due = Some("2025-01-10".into());
+        report.notes = Some("first\nsecond".into());
+        let items = vec![report, item("Say \\hi due:later", None, &[], false)];
+
+        let imported = parse(&write(&items, GroupBy::Priority));
+        assert_eq!(imported.items, items);
+    }
+
+    #[test]
+    fn parses_nested_bullets_and_prose() {
+        let input = "# Release\n\nSome prose.\n\n\
+                     - [ ] Ship it #release !0\n\
+                     \x20 - check changelog\n\
+                     \x20   continued\n\
+                     \x20 - [X] Tag the commit due:2025-02-01\n\
+                     * [ ] due:tomorrow Tweet\n\
+                     - [ ]\n\
+                     - plain bullet\n";
+        let imported = parse(input);
+
+        let descriptions: Vec<_> = imported
+            .items
+            .iter()
+            .map(|i| i.description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec!["Ship it", "Tag the commit", "due:tomorrow Tweet"]
+        );
+        let ship = &imported.items[0];
+        assert_eq!(ship.tags, Some(vec!["release".into()]));
+        assert_eq!(ship.priority, Some(0));
+        assert_eq!(ship.notes.as_deref(), Some("check changelog\ncontinued"));
+        assert!(imported.items[1].done);
+        assert_eq!(imported.items[1].due.as_deref(), Some("2025-02-01"));
+        assert_eq!(imported.skipped, 1);
+        assert_eq!(imported.notices[0].location, "line 9");
+    }
+
+    #[test]
+    fn notes_after_a_nested_item_belong_to_its_parent() {
+        let lines = [
+            "- [ ] Top",
+            "  - [ ] Nested child",
+            "    - child note",
+            "  - plain note",
+        ];
+        let blocks = blocks(&lines);
+
+        assert_eq!(blocks[0].item.notes.as_deref(), Some("plain note"));
+        assert_eq!((blocks[0].start, blocks[0].end), (0, 1));
+        assert_eq!(blocks[0].later_notes, vec![3]);
+        assert_eq!(blocks[1].item.notes.as_deref(), Some("child note"));
+        assert_eq!((blocks[1].start, blocks[1].end), (1, 3));
+    }
+}
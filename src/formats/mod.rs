@@ -0,0 +1,120 @@
+//! Reading and writing other tools' formats for `todo import` and `todo export`.
+
+pub mod csv;
+pub mod ics;
+pub mod markdown;
+pub mod org;
+pub mod taskwarrior;
+pub mod todotxt;
+
+use crate::storage::{TodoItem, now_timestamp};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+
+/// Foreign formats `todo import` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    Todotxt,
+    Taskwarrior,
+    Csv,
+    Ics,
+    Markdown,
+    Org,
+}
+
+/// Formats `todo export` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Ics,
+    Markdown,
+    Org,
+}
+
+/// How grouped exports such as Markdown split items under headings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    #[default]
+    Priority,
+    Tag,
+}
+
+/// Parses `input` as `format`. Errors mean the input as a whole is unusable;
+/// problems with single entries are reported as notices instead.
+pub fn parse(format: ImportFormat, input: &str) -> Result<Imported, String> {
+    match format {
+        ImportFormat::Todotxt => Ok(todotxt::parse(input)),
+        ImportFormat::Taskwarrior => taskwarrior::parse(input),
+        ImportFormat::Csv => csv::parse(input),
+        ImportFormat::Ics => ics::parse(input),
+        ImportFormat::Markdown => Ok(markdown::parse(input)),
+        ImportFormat::Org => Ok(org::parse(input)),
+    }
+}
+
+pub fn render(format: ExportFormat, items: &[TodoItem], group_by: GroupBy) -> String {
+    match format {
+        ExportFormat::Ics => ics::write(items, now_timestamp()),
+        ExportFormat::Markdown => markdown::write(items, group_by),
+        ExportFormat::Org => org::write(items),
+    }
+}
+
+/// Something the user should know about an entry: a field we could not carry
+/// over, or an entry skipped entirely.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    /// Where the entry came from, e.g. "line 4" or "task 2".
+    pub location: String,
+    pub message: String,
+}
+
+impl Notice {
+    pub fn new(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// The result of parsing a foreign file. Items have no `id` yet and only a
+/// `created` timestamp when the source recorded one.
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub items: Vec<TodoItem>,
+    pub notices: Vec<Notice>,
+    pub skipped: usize,
+}
+
+impl Imported {
+    fn skip(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.skipped += 1;
+        self.notices.push(Notice::new(location, message));
+    }
+}
+
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Midnight UTC of `date`, in the form `TodoItem::created` uses.
+pub fn date_to_timestamp(date: NaiveDate) -> u64 {
+    date.and_hms_opt(0, 0, 0)
+        .map(|dt| dt.and_utc().timestamp().max(0) as u64)
+        .unwrap_or_default()
+}
+
+/// A blank item to fill in; `done` and every optional field start empty.
+fn empty_item(description: String) -> TodoItem {
+    TodoItem {
+        description,
+        ..TodoItem::default()
+    }
+}
+
+fn push_tag(item: &mut TodoItem, tag: &str) {
+    let tags = item.tags.get_or_insert_with(Vec::new);
+    if !tags.iter().any(|t| t == tag) {
+        tags.push(tag.to_string());
+    }
+}
@@ -0,0 +1,515 @@
+//! Org-mode outlines, for org-agenda.
+//!
+//! Items become `* TODO`/`* DONE` headlines with `[#A]`–`[#Z]` priorities
+//! (0–25) and `:tag:` lists, `DEADLINE:` carries the due date and the notes
+//! are the body. The ID and creation time (in UTC) go into the property
+//! drawer, along with any priority or tags Org cannot express.
+
+use super::{Imported, Notice, empty_item, push_tag};
+use crate::storage::TodoItem;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+const PRIORITY_PROPERTY: &str = "CRIX_PRIORITY";
+const TAGS_PROPERTY: &str = "CRIX_TAGS";
+
+pub fn write(items: &[TodoItem]) -> String {
+    let mut out = String::new();
+    for item in items {
+        write_item(&mut out, item);
+    }
+    out
+}
+
+fn write_item(out: &mut String, item: &TodoItem) {
+    let mut headline = format!("* {}", if item.done { "DONE" } else { "TODO" });
+    let letter = item.priority.and_then(priority_letter);
+    if let Some(letter) = letter {
+        headline.push_str(&format!(" [#{}]", letter));
+    }
+    // A description line break would start a new paragraph in Org.
+    headline.push(' ');
+    headline.push_str(
+        &item
+            .description
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+
+    let tags = item.tags.as_deref().unwrap_or_default();
+    let org_tags: Vec<&str> = tags
+        .iter()
+        .map(String::as_str)
+        .filter(|t| is_org_tag(t))
+        .collect();
+    if !org_tags.is_empty() {
+        headline.push_str(&format!(" :{}:", org_tags.join(":")));
+    }
+    out.push_str(&headline);
+    out.push('\n');
+
+    if let Some(due) = item.due.as_deref().and_then(super::parse_date) {
+        out.push_str(&format!("  DEADLINE: <{}>\n", due.format("%Y-%m-%d %a")));
+    }
+
+    let mut properties = Vec::new();
+    if !item.id.is_empty() {
+        properties.push(("ID", item.id.clone()));
+    }
+    if let Some(created) = item
+        .created
+        .and_then(|c| DateTime::from_timestamp(c as i64, 0))
+    {
+        properties.push(("CREATED", created.format("[%Y-%m-%d %a %H:%M]").to_string()));
+    }
+    if let (Some(priority), None) = (item.priority, letter) {
+        properties.push((PRIORITY_PROPERTY, priority.to_string()));
+    }
+    if org_tags.len() < tags.len() {
+        properties.push((TAGS_PROPERTY, tags.join(",")));
+    }
+    if !properties.is_empty() {
+        out.push_str("  :PROPERTIES:\n");
+        for (name, value) in properties {
+            out.push_str(&format!("  :{}: {}\n", name, value));
+        }
+        out.push_str("  :END:\n");
+    }
+
+    for line in item.notes.iter().flat_map(|n| n.lines()) {
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            out.push_str(&format!("  {}\n", line));
+        }
+    }
+}
+
+fn priority_letter(priority: u8) -> Option<char> {
+    (priority < 26).then(|| (b'A' + priority) as char)
+}
+
+/// Org tags may only hold letters, digits, `_`, `@`, `#` and `%`.
+fn is_org_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '%'))
+}
+
+/// Which keywords mark a headline as a task, from `#+TODO:` lines.
+struct Keywords {
+    pending: Vec<String>,
+    done: Vec<String>,
+}
+
+impl Keywords {
+    fn from_input(input: &str) -> Self {
+        let mut keywords = Keywords {
+            pending: Vec::new(),
+            done: Vec::new(),
+        };
+        for line in input.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            if !matches!(
+                key.to_ascii_uppercase().as_str(),
+                "#+TODO" | "#+SEQ_TODO" | "#+TYP_TODO"
+            ) {
+                continue;
+            }
+            let words: Vec<&str> = value.split_whitespace().collect();
+            let (pending, done) = match words.iter().position(|w| *w == "|") {
+                Some(bar) => (&words[..bar], &words[bar + 1..]),
+                // Without a bar, only the last keyword means done.
+                None => words.split_at(words.len().saturating_sub(1)),
+            };
+            keywords
+                .pending
+                .extend(pending.iter().map(|w| keyword_name(w)));
+            keywords.done.extend(done.iter().map(|w| keyword_name(w)));
+        }
+        if keywords.pending.is_empty() && keywords.done.is_empty() {
+            keywords.pending.push("TODO".into());
+            keywords.done.push("DONE".into());
+        }
+        keywords
+    }
+
+    fn is_done(&self, word: &str) -> Option<bool> {
+        if self.pending.iter().any(|k| k == word) {
+            Some(false)
+        } else if self.done.iter().any(|k| k == word) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+// `WAIT(w@/!)` declares fast-access keys and logging after the name.
+fn keyword_name(word: &str) -> String {
+    word.split('(').next().unwrap_or(word).to_string()
+}
+
+/// The item being read and what is left to do before the body starts.
+struct Entry {
+    item: TodoItem,
+    location: String,
+    notices: Vec<String>,
+    body: Vec<String>,
+    in_drawer: Option<String>,
+    x_priority: Option<u8>,
+    x_tags: Option<Vec<String>>,
+}
+
+/// Headlines with a TODO keyword become items, text under them becomes
+/// their notes, and everything else is ignored.
+pub fn parse(input: &str) -> Imported {
+    let keywords = Keywords::from_input(input);
+    let mut imported = Imported::default();
+    let mut current: Option<Entry> = None;
+
+    for (n, line) in input.lines().enumerate() {
+        let location = format!("line {}", n + 1);
+        if let Some(title) = headline(line) {
+            if let Some(entry) = current.take() {
+                finish(&mut imported, entry);
+            }
+            current = parse_headline(title, &keywords).map(|(item, notices)| Entry {
+                item,
+                location,
+                notices,
+                body: Vec::new(),
+                in_drawer: None,
+                x_priority: None,
+                x_tags: None,
+            });
+            continue;
+        }
+        if let Some(entry) = current.as_mut() {
+            read_line(entry, line);
+        }
+    }
+    if let Some(entry) = current {
+        finish(&mut imported, entry);
+    }
+
+    imported
+}
+
+fn headline(line: &str) -> Option<&str> {
+    let stars = line.len() - line.trim_start_matches('*').len();
+    if stars == 0 {
+        return None;
+    }
+    line[stars..].strip_prefix(' ')
+}
+
+fn parse_headline(title: &str, keywords: &Keywords) -> Option<(TodoItem, Vec<String>)> {
+    let (keyword, mut rest) = title.trim().split_once(' ').unwrap_or((title.trim(), ""));
+    let done = keywords.is_done(keyword)?;
+    let mut item = empty_item(String::new());
+    let mut notices = Vec::new();
+    item.done = done;
+    if !matches!(keyword, "TODO" | "DONE") {
+        let state = if done { "done" } else { "pending" };
+        notices.push(format!("keyword {} imported as {}", keyword, state));
+    }
+
+    rest = rest.trim();
+    if let Some(cookie) = rest.strip_prefix("[#")
+        && let Some((value, after)) = cookie.split_once(']')
+    {
+        match value.as_bytes() {
+            [c @ b'A'..=b'Z'] => item.priority = Some(c - b'A'),
+            _ => match value.parse() {
+                Ok(p) => item.priority = Some(p),
+                Err(_) => notices.push(format!("priority [#{}] dropped", value)),
+            },
+        }
+        rest = after.trim_start();
+    }
+
+    if let Some((before, last)) = rest.rsplit_once(char::is_whitespace)
+        && last.len() > 2
+        && last.starts_with(':')
+        && last.ends_with(':')
+    {
+        let tags: Vec<&str> = last[1..last.len() - 1].split(':').collect();
+        if tags.iter().all(|t| is_org_tag(t)) {
+            for tag in tags {
+                push_tag(&mut item, tag);
+            }
+            rest = before.trim_end();
+        }
+    }
+
+    item.description = rest.to_string();
+    Some((item, notices))
+}
+
+fn read_line(entry: &mut Entry, line: &str) {
+    let trimmed = line.trim();
+
+    if let Some(drawer) = &entry.in_drawer {
+        if trimmed.eq_ignore_ascii_case(":END:") {
+            entry.in_drawer = None;
+        } else if drawer == "PROPERTIES" {
+            read_property(entry, trimmed);
+        }
+        return;
+    }
+
+    // Planning and drawers only come before the body.
+    if entry.body.is_empty() {
+        if let Some(name) = trimmed
+            .strip_prefix(':')
+            .and_then(|t| t.strip_suffix(':'))
+            .filter(|t| !t.is_empty() && !t.contains(char::is_whitespace))
+        {
+            let name = name.to_ascii_uppercase();
+            if name != "PROPERTIES" {
+                entry.notices.push(format!("{} drawer dropped", name));
+            }
+            entry.in_drawer = Some(name);
+            return;
+        }
+        if read_planning(entry, trimmed) {
+            return;
+        }
+        if trimmed.is_empty() {
+            return;
+        }
+    }
+
+    entry.body.push(line.to_string());
+}
+
+fn read_property(entry: &mut Entry, line: &str) {
+    let Some((name, value)) = line
+        .strip_prefix(':')
+        .and_then(|l| l.split_once(':'))
+        .map(|(n, v)| (n.to_ascii_uppercase(), v.trim()))
+    else {
+        return;
+    };
+    match name.as_str() {
+        "ID" => entry.item.id = value.to_string(),
+        "CREATED" => match parse_timestamp(value) {
+            Some(dt) => entry.item.created = Some(dt.and_utc().timestamp().max(0) as u64),
+            None => entry.notices.push(format!("CREATED '{}' dropped", value)),
+        },
+        PRIORITY_PROPERTY => entry.x_priority = value.parse().ok(),
+        TAGS_PROPERTY => {
+            entry.x_tags = Some(
+                value
+                    .split(',')
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect(),
+            );
+        }
+        _ => entry.notices.push(format!("property {} dropped", name)),
+    }
+}
+
+/// Reads a `DEADLINE:`/`SCHEDULED:`/`CLOSED:` line; false if it is not one.
+fn read_planning(entry: &mut Entry, line: &str) -> bool {
+    let keywords = ["DEADLINE:", "SCHEDULED:", "CLOSED:"];
+    if !keywords.iter().any(|k| line.starts_with(k)) {
+        return false;
+    }
+
+    let mut rest = line;
+    while let Some(start) = keywords
+        .iter()
+        .filter_map(|k| rest.find(k).map(|i| (i, *k)))
+        .min()
+    {
+        let (index, keyword) = start;
+        let after = rest[index + keyword.len()..].trim_start();
+        let end = after.find(['>', ']']).map(|i| i + 1).unwrap_or(after.len());
+        let stamp = &after[..end];
+        rest = &after[end..];
+
+        match keyword {
+            "DEADLINE:" => match parse_date_stamp(stamp) {
+                Some((date, extra)) => {
+                    entry.item.due = Some(date.format("%Y-%m-%d").to_string());
+                    if extra {
+                        entry
+                            .notices
+                            .push(format!("time or repeater dropped from DEADLINE {}", stamp));
+                    }
+                }
+                None => entry.notices.push(format!("DEADLINE {} dropped", stamp)),
+            },
+            "SCHEDULED:" => entry.notices.push(format!("SCHEDULED {} dropped", stamp)),
+            _ => entry.notices.push(format!("CLOSED {} dropped", stamp)),
+        }
+    }
+    true
+}
+
+/// `<2025-01-10 Fri>` and whether anything beyond the day name followed.
+fn parse_date_stamp(stamp: &str) -> Option<(NaiveDate, bool)> {
+    let inner = stamp
+        .trim_start_matches(['<', '['])
+        .trim_end_matches(['>', ']']);
+    let mut parts = inner.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let extra = parts
+        .filter(|p| !p.chars().all(char::is_alphabetic))
+        .count()
+        > 0;
+    Some((date, extra))
+}
+
+/// `[2025-01-02 Thu 09:30]`, with the time optional.
+fn parse_timestamp(stamp: &str) -> Option<NaiveDateTime> {
+    let inner = stamp
+        .trim_start_matches(['<', '['])
+        .trim_end_matches(['>', ']']);
+    let mut parts = inner.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    let time = parts
+        .find_map(|p| chrono::NaiveTime::parse_from_str(p, "%H:%M").ok())
+        .unwrap_or_default();
+    Some(date.and_time(time))
+}
+
+fn finish(imported: &mut Imported, mut entry: Entry) {
+    if entry.item.description.is_empty() {
+        imported.skip(entry.location, "no title");
+        return;
+    }
+    if entry.x_priority.is_some() {
+        entry.item.priority = entry.x_priority;
+    }
+    if let Some(tags) = entry.x_tags {
+        entry.item.tags = Some(tags).filter(|t| !t.is_empty());
+    }
+
+    while entry.body.last().is_some_and(|l| l.trim().is_empty()) {
+        entry.body.pop();
+    }
+    let indent = entry
+        .body
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    if !entry.body.is_empty() {
+        let notes: Vec<&str> = entry
+            .body
+            .iter()
+            .map(|l| l.get(indent..).unwrap_or("").trim_end())
+            .collect();
+        entry.item.notes = Some(notes.join("\n"));
+    }
+
+    let location = entry.location;
+    imported.notices.extend(
+        entry
+            .notices
+            .into_iter()
+            .map(|m| Notice::new(location.clone(), m)),
+    );
+    imported.items.push(entry.item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> TodoItem {
+        TodoItem {
+            id: "ab12cd34".into(),
+            description: "Write report".into(),
+            priority: Some(1),
+            due: Some("2025-01-10".into()),
+            tags: Some(vec!["work".into(), "q1".into()]),
+            notes: Some("draft first\n\n  * then review".into()),
+            created: Some(1_735_776_000),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn writes_headline_planning_and_body() {
+        assert_eq!(
+            write(&[item()]),
+            "* TODO [#B] Write report :work:q1:\n\
+             \x20 DEADLINE: <2025-01-10 Fri>\n\
+             \x20 :PROPERTIES:\n\
+             \x20 :ID: ab12cd34\n\
+             \x20 :CREATED: [2025-01-02 Thu 00:00]\n\
+             \x20 :END:\n\
+             \x20 draft first\n\
+             \n\
+             \x20   * then review\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_including_properties_for_the_unexpressible() {
+        let mut odd = item();
+        odd.priority = Some(40);
+        odd.tags = Some(vec!["two words".into(), "ok".into()]);
+        odd.done = true;
+        odd.notes = None;
+        let items = vec![item(), odd];
+
+        let imported = parse(&write(&items));
+        assert_eq!(imported.items, items);
+        assert!(imported.notices.is_empty());
+    }
+
+    #[test]
+    fn reads_hand_written_outline() {
+        let input = "#+TODO: TODO NEXT | DONE CANCELLED\n\
+                     * Projects\n\
+                     ** NEXT [#A] Call plumber :home:\n\
+                     \x20  SCHEDULED: <2025-01-09 Thu> DEADLINE: <2025-01-10 Fri 09:00>\n\
+                     \x20  :LOGBOOK:\n\
+                     \x20  - State \"NEXT\" from \"TODO\"\n\
+                     \x20  :END:\n\
+                     \x20  Ask about the boiler.\n\
+                     ** CANCELLED Paint fence\n\
+                     ** Notes without a keyword\n\
+                     \x20  ignored text\n\
+                     * DONE\n";
+        let imported = parse(input);
+
+        assert_eq!(imported.items.len(), 2);
+        let call = &imported.items[0];
+        assert_eq!(call.description, "Call plumber");
+        assert_eq!(call.priority, Some(0));
+        assert_eq!(call.tags, Some(vec!["home".into()]));
+        assert_eq!(call.due.as_deref(), Some("2025-01-10"));
+        assert_eq!(call.notes.as_deref(), Some("Ask about the boiler."));
+        assert!(imported.items[1].done);
+        assert_eq!(imported.skipped, 1);
+
+        let messages: Vec<&str> = imported
+            .notices
+            .iter()
+            .map(|n| n.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "keyword NEXT imported as pending",
+                "SCHEDULED <2025-01-09 Thu> dropped",
+                "time or repeater dropped from DEADLINE <2025-01-10 Fri 09:00>",
+                "LOGBOOK drawer dropped",
+                "keyword CANCELLED imported as done",
+                "no title",
+            ]
+        );
+        assert_eq!(imported.notices[0].location, "line 3");
+    }
+}
@@ -0,0 +1,269 @@
+//! Taskwarrior's `task export` JSON, either as one array or one task per line.
+//!
+//! Priorities H/M/L map to 0/1/2, as todo.txt's (A)/(B)/(C) do, due dates
+//! are taken in the local time zone, `project` becomes a tag and annotations are
+//! joined into the notes. Deleted tasks and recurrence templates are skipped.
+
+use super::{Imported, Notice, empty_item, push_tag};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde_json::{Map, Value};
+
+/// Fields we either map or that only matter inside Taskwarrior.
+const KNOWN_FIELDS: &[&str] = &[
+    "description",
+    "status",
+    "priority",
+    "due",
+    "tags",
+    "project",
+    "annotations",
+    "entry",
+    "id",
+    "uuid",
+    "urgency",
+    "modified",
+    "mask",
+    "imask",
+    "parent",
+];
+
+/// Fails only when the input is not Taskwarrior JSON at all; problems with
+/// individual tasks become notices.
+pub fn parse(input: &str) -> Result<Imported, String> {
+    let tasks = read_tasks(input)?;
+    let mut imported = Imported::default();
+
+    for (n, task) in tasks.iter().enumerate() {
+        let location = format!("task {}", n + 1);
+        let Some(task) = task.as_object() else {
+            imported.skip(location, "not a JSON object");
+            continue;
+        };
+        convert(task, location, &mut imported);
+    }
+
+    Ok(imported)
+}
+
+fn read_tasks(input: &str) -> Result<Vec<Value>, String> {
+    let trimmed = input.trim_start();
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+    if trimmed.starts_with('[') {
+        return match serde_json::from_str(trimmed) {
+            Ok(Value::Array(tasks)) => Ok(tasks),
+            Ok(_) => Err("expected a JSON array of tasks".into()),
+            Err(e) => Err(e.to_string()),
+        };
+    }
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            serde_json::from_str(line.trim().trim_end_matches(','))
+                .map_err(|e| format!("line {}: {}", n + 1, e))
+        })
+        .collect()
+}
+
+fn convert(task: &Map<String, Value>, location: String, imported: &mut Imported) {
+    let description = task
+        .get("description")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .unwrap_or_default();
+    if description.is_empty() {
+        imported.skip(location, "no description");
+        return;
+    }
+
+    let status = task
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("pending");
+    match status {
+        "deleted" => return imported.skip(location, "deleted task skipped"),
+        "recurring" => return imported.skip(location, "recurrence template skipped"),
+        _ => {}
+    }
+
+    let mut notices = Vec::new();
+    let mut item = empty_item(description.to_string());
+    match status {
+        "completed" => item.done = true,
+        "pending" => {}
+        other => notices.push(format!("status '{}' imported as pending", other)),
+    }
+
+    match task.get("priority").and_then(Value::as_str) {
+        Some("H") => item.priority = Some(0),
+        Some("M") => item.priority = Some(1),
+        Some("L") => item.priority = Some(2),
+        Some(other) => notices.push(format!("unknown priority '{}' dropped", other)),
+        None => {}
+    }
+
+    if let Some(due) = task.get("due").and_then(Value::as_str) {
+        match parse_datetime(due) {
+            Some(dt) => {
+                let (date, time) = local_date(dt, &Local);
+                if let Some(time) = time {
+                    notices.push(format!("due time {} dropped", time));
+                }
+                item.due = Some(date.format("%Y-%m-%d").to_string());
+            }
+            None => notices.push(format!("unreadable due date '{}' dropped", due)),
+        }
+    }
+
+    if let Some(entry) = task
+        .get("entry")
+        .and_then(Value::as_str)
+        .and_then(parse_datetime)
+    {
+        item.created = Some(entry.and_utc().timestamp().max(0) as u64);
+    }
+
+    if let Some(project) = task.get("project").and_then(Value::as_str) {
+        push_tag(&mut item, project);
+    }
+    for tag in task
+        .get("tags")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let Some(tag) = tag.as_str() {
+            push_tag(&mut item, tag);
+        }
+    }
+
+    let annotations: Vec<&str> = task
+        .get("annotations")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|a| a.get("description").and_then(Value::as_str))
+        .collect();
+    if !annotations.is_empty() {
+        item.notes = Some(annotations.join("\n"));
+    }
+
+    let mut dropped: Vec<&str> = task
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !KNOWN_FIELDS.contains(key))
+        .collect();
+    dropped.sort_unstable();
+    if !dropped.is_empty() {
+        notices.push(format!("fields dropped: {}", dropped.join(", ")));
+    }
+
+    imported.notices.extend(
+        notices
+            .into_iter()
+            .map(|m| Notice::new(location.clone(), m)),
+    );
+    imported.items.push(item);
+}
+
+/// Taskwarrior's compact UTC form, e.g. `20250110T143000Z`.
+fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok()
+}
+
+/// The date of a UTC time in `zone`, and the time of day unless it is
+/// midnight there.
+fn local_date<Tz: TimeZone>(utc: NaiveDateTime, zone: &Tz) -> (NaiveDate, Option<NaiveTime>) {
+    let local = utc.and_utc().with_timezone(zone).naive_local();
+    let time = (local.time() != NaiveTime::MIN).then(|| local.time());
+    (local.date(), time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_export_array() {
+        let input = r#"[
+            {"id":1,"uuid":"a","description":"Write report","status":"pending",
+             "priority":"H","entry":"20250102T000000Z",
+             "project":"work","tags":["urgent","work"],
+             "annotations":[{"entry":"20250103T000000Z","description":"draft in docs"}],
+             "urgency":9.1},
+            {"id":0,"uuid":"b","description":"Old thing","status":"completed"}
+        ]"#;
+        let imported = parse(input).unwrap();
+
+        assert_eq!(imported.items.len(), 2);
+        let report = &imported.items[0];
+        assert_eq!(report.priority, Some(0));
+        assert_eq!(report.created, Some(1_735_776_000));
+        assert_eq!(report.tags, Some(vec!["work".into(), "urgent".into()]));
+        assert_eq!(report.notes.as_deref(), Some("draft in docs"));
+        assert!(imported.items[1].done);
+        assert!(imported.notices.is_empty());
+    }
+
+    #[test]
+    fn parses_one_task_per_line() {
+        let input = "{\"description\":\"a\"}\n\n{\"description\":\"b\"}\n";
+        let imported = parse(input).unwrap();
+        assert_eq!(imported.items.len(), 2);
+    }
+
+    #[test]
+    fn reports_lossy_and_skipped_tasks() {
+        let input = r#"[
+            {"description":"Gone","status":"deleted"},
+            {"description":"Weekly","status":"recurring","recur":"weekly"},
+            {"description":"Later","status":"waiting","wait":"20250101T000000Z",
+             "due":"20250110T141700Z","scheduled":"20250105T000000Z"}
+        ]"#;
+        let imported = parse(input).unwrap();
+
+        assert_eq!(imported.items.len(), 1);
+        assert_eq!(imported.skipped, 2);
+        // 14:17 UTC is not midnight in any time zone
+        let due = parse_datetime("20250110T141700Z").unwrap();
+        let dropped = format!("due time {} dropped", local_date(due, &Local).1.unwrap());
+        let messages: Vec<&str> = imported
+            .notices
+            .iter()
+            .map(|n| n.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "deleted task skipped",
+                "recurrence template skipped",
+                "status 'waiting' imported as pending",
+                &dropped,
+                "fields dropped: scheduled, wait",
+            ]
+        );
+        assert_eq!(imported.notices[2].location, "task 3");
+    }
+
+    #[test]
+    fn due_dates_are_local() {
+        let utc = parse_datetime("20250109T230000Z").unwrap();
+        let berlin = chrono::FixedOffset::east_opt(3600).unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        assert_eq!(local_date(utc, &berlin), (date, None));
+
+        let new_york = chrono::FixedOffset::west_opt(5 * 3600).unwrap();
+        let (date, time) = local_date(utc, &new_york);
+        assert_eq!(date.to_string(), "2025-01-09");
+        assert_eq!(time.unwrap().to_string(), "18:00:00");
+    }
+
+    #[test]
+    fn rejects_non_json() {
+        assert!(parse("not json").is_err());
+        assert!(parse("{\"a\":1}").unwrap().items.is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+//! The todo.txt format: <https://github.com/todotxt/todo.txt>.
+//!
+//! Priorities `(A)`–`(Z)` map to 0–25, `+project` and `@context` become tags,
+//! `due:YYYY-MM-DD` becomes the due date and the creation date becomes
+//! `created`. Our own extensions are `id:`, `note:` (percent-encoded) and
+//! `pri:`, which holds the priority of completed tasks and priorities above 25.
+
+use super::{Imported, Notice, date_to_timestamp, empty_item, parse_date, push_tag};
+use crate::storage::TodoItem;
+use chrono::{DateTime, NaiveDate};
+
+/// One task line, with what `TodoItem` has no slot for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub item: TodoItem,
+    pub completed: Option<NaiveDate>,
+    /// Tags written as `@context` rather than `+project`.
+    pub contexts: Vec<String>,
+    /// Tokens that looked like ours but could not be read; they are kept in
+    /// the description.
+    pub invalid: Vec<String>,
+}
+
+pub fn parse(input: &str) -> Imported {
+    let mut imported = Imported::default();
+
+    for (n, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let location = format!("line {}", n + 1);
+        let task = parse_line(line);
+
+        if task.item.description.is_empty() {
+            imported.skip(location, "no description");
+            continue;
+        }
+        if let Some(date) = task.completed {
+            let message = format!("completion date {} dropped", date);
+            imported
+                .notices
+                .push(Notice::new(location.clone(), message));
+        }
+        for token in task.invalid {
+            let message = format!("'{}' is not valid; kept in the description", token);
+            imported
+                .notices
+                .push(Notice::new(location.clone(), message));
+        }
+        imported.items.push(task.item);
+    }
+
+    imported
+}
+
+pub fn parse_line(line: &str) -> Task {
+    let mut item = empty_item(String::new());
+    let mut completed = None;
+    let mut contexts = Vec::new();
+    let mut invalid = Vec::new();
+    let mut tokens = line.split_whitespace().peekable();
+
+    if tokens.peek() == Some(&"x") {
+        tokens.next();
+        item.done = true;
+        if let Some(date) = tokens.peek().and_then(|t| parse_date(t)) {
+            tokens.next();
+            completed = Some(date);
+        }
+    }
+    if let Some(priority) = tokens.peek().and_then(|t| parse_priority(t)) {
+        tokens.next();
+        item.priority = Some(priority);
+    }
+    if let Some(date) = tokens.peek().and_then(|t| parse_date(t)) {
+        tokens.next();
+        item.created = Some(date_to_timestamp(date));
+    }
+
+    let mut words = Vec::new();
+    for token in tokens {
+        if let Some(tag) = token.strip_prefix('+').filter(|t| !t.is_empty()) {
+            push_tag(&mut item, tag);
+        } else if let Some(tag) = token.strip_prefix('@').filter(|t| !t.is_empty()) {
+            push_tag(&mut item, tag);
+            contexts.push(tag.to_string());
+        } else if let Some((key, value)) = token.split_once(':')
+            && let Some(ok) = read_extension(&mut item, key, value)
+        {
+            if !ok {
+                invalid.push(token.to_string());
+                words.push(token);
+            }
+        } else {
+            words.push(token);
+        }
+    }
+
+    item.description = words.join(" ");
+    Task {
+        item,
+        completed,
+        contexts,
+        invalid,
+    }
+}
+
+/// Applies a `key:value` token we understand. `None` means the key is not
+/// ours; `Some(false)` that the value was unusable.
+fn read_extension(item: &mut TodoItem, key: &str, value: &str) -> Option<bool> {
+    let ok = match key {
+        "due" => {
+            let valid = parse_date(value).is_some();
+            if valid {
+                item.due = Some(value.to_string());
+            }
+            valid
+        }
+        "pri" => {
+            let priority = priority_letter(value).or_else(|| value.parse().ok());
+            item.priority = priority.or(item.priority);
+            priority.is_some()
+        }
+        "id" => {
+            item.id = value.to_string();
+            !value.is_empty()
+        }
+        "note" => {
+            let note = decode(value);
+            match &mut item.notes {
+                Some(notes) => {
+                    notes.push('\n');
+                    notes.push_str(&note);
+                }
+                None => item.notes = Some(note),
+            }
+            true
+        }
+        _ => return None,
+    };
+    Some(ok)
+}
+
+/// Writes `item` as one line. Tags in `contexts` are written with `@`.
+pub fn write_line(item: &TodoItem, completed: Option<NaiveDate>, contexts: &[String]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let letter = item
+        .priority
+        .filter(|p| *p < 26)
+        .map(|p| ((b'A' + p) as char).to_string());
+
+    if item.done {
+        parts.push("x".into());
+        parts.extend(completed.map(|d| d.to_string()));
+    } else if let Some(letter) = &letter {
+        parts.push(format!("({})", letter));
+    }
+    if let Some(created) = item
+        .created
+        .and_then(|c| DateTime::from_timestamp(c as i64, 0))
+    {
+        parts.push(created.date_naive().to_string());
+    }
+    parts.extend(item.description.split_whitespace().map(String::from));
+
+    for tag in item.tags.iter().flatten() {
+        let sigil = if contexts.contains(tag) { '@' } else { '+' };
+        parts.push(format!(
+            "{}{}",
+            sigil,
+            tag.replace(char::is_whitespace, "_")
+        ));
+    }
+    if let Some(due) = &item.due {
+        parts.push(format!("due:{}", due));
+    }
+    match (item.priority, letter) {
+        (Some(_), Some(letter)) if item.done => parts.push(format!("pri:{}", letter)),
+        (Some(priority), None) => parts.push(format!("pri:{}", priority)),
+        _ => {}
+    }
+    if let Some(notes) = &item.notes {
+        parts.push(format!("note:{}", encode(notes)));
+    }
+    if !item.id.is_empty() {
+        parts.push(format!("id:{}", item.id));
+    }
+
+    parts.join(" ")
+}
+
+/// `(A)` → 0 … `(Z)` → 25.
+fn parse_priority(token: &str) -> Option<u8> {
+    token
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .and_then(priority_letter)
+}
+
+fn priority_letter(letter: &str) -> Option<u8> {
+    match letter.as_bytes() {
+        [c @ b'A'..=b'Z'] => Some(c - b'A'),
+        _ => None,
+    }
+}
+
+// Tokens end at whitespace, so notes escape it along with `%` itself.
+fn encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            ' ' => out.push_str("%20"),
+            '\n' => out.push_str("%0A"),
+            '\t' => out.push_str("%09"),
+            '\r' => out.push_str("%0D"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub(crate) fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_line() {
+        let task = parse_line("(B) 2025-01-02 Call Mom +Family @phone due:2025-01-10 about:trip");
+        let item = &task.item;
+
+        assert_eq!(item.description, "Call Mom about:trip");
+        assert_eq!(item.priority, Some(1));
+        assert_eq!(item.created, Some(1_735_776_000));
+        assert_eq!(item.due.as_deref(), Some("2025-01-10"));
+        assert_eq!(item.tags, Some(vec!["Family".into(), "phone".into()]));
+        assert_eq!(task.contexts, vec!["phone"]);
+        assert!(!item.done);
+        assert!(task.invalid.is_empty());
+    }
+
+    #[test]
+    fn parses_completed_line() {
+        let task = parse_line("x 2025-03-01 2025-02-01 File taxes pri:A");
+
+        assert!(task.item.done);
+        assert_eq!(task.item.description, "File taxes");
+        assert_eq!(task.item.priority, Some(0));
+        assert_eq!(task.item.created, Some(1_738_368_000));
+        assert_eq!(task.completed, NaiveDate::from_ymd_opt(2025, 3, 1));
+    }
+
+    #[test]
+    fn keeps_malformed_due_in_description() {
+        let task = parse_line("Renew passport due:soon");
+        assert_eq!(task.item.description, "Renew passport due:soon");
+        assert_eq!(task.item.due, None);
+        assert_eq!(task.invalid, vec!["due:soon"]);
+    }
+
+    #[test]
+    fn priority_must_lead_the_line() {
+        let task = parse_line("Read (A) book");
+        assert_eq!(task.item.priority, None);
+        assert_eq!(task.item.description, "Read (A) book");
+    }
+
+    #[test]
+    fn writes_and_reads_back_extensions() {
+        let item = TodoItem {
+            id: "ab12cd34".into(),
+            description: "Plan trip".into(),
+            priority: Some(30),
+            due: Some("2025-07-10".into()),
+            tags: Some(vec!["travel".into(), "phone".into()]),
+            done: true,
+            notes: Some("100% sure\nbook early".into()),
+            created: Some(1_735_776_000),
+        };
+        let completed = NaiveDate::from_ymd_opt(2025, 3, 1);
+        let line = write_line(&item, completed, &["phone".to_string()]);
+
+        assert_eq!(
+            line,
+            "x 2025-03-01 2025-01-02 Plan trip +travel @phone due:2025-07-10 pri:30 \
+             note:100%25%20sure%0Abook%20early id:ab12cd34"
+        );
+        let task = parse_line(&line);
+        assert_eq!(task.item, item);
+        assert_eq!(task.completed, completed);
+    }
+
+    #[test]
+    fn parse_reports_skipped_and_lossy_lines() {
+        let imported = parse("Buy milk\n\nx 2025-01-01\nx 2025-01-02 Walk dog due:soon\n");
+
+        assert_eq!(imported.items.len(), 2);
+        assert_eq!(imported.skipped, 1);
+        assert_eq!(
+            imported.notices,
+            vec![
+                Notice::new("line 3", "no description"),
+                Notice::new("line 4", "completion date 2025-01-02 dropped"),
+                Notice::new("line 4", "'due:soon' is not valid; kept in the description"),
+            ]
+        );
+    }
+}
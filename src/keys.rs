@@ -0,0 +1,250 @@
+//! Keys as written in the config: a chord such as `q`, `enter` or `ctrl-r`,
+//! a sequence of chords separated by spaces such as `g g`, and a list of
+//! alternative sequences separated by commas such as `backspace, d d`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Esc,
+    Backspace,
+    Delete,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+const NAMES: &[(&str, Key)] = &[
+    ("enter", Key::Enter),
+    ("esc", Key::Esc),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("tab", Key::Tab),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    // These would otherwise be read as separators.
+    ("space", Key::Char(' ')),
+    ("comma", Key::Char(',')),
+];
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        if let Some((_, key)) = NAMES.iter().find(|(name, _)| *name == lower) {
+            return Ok(*key);
+        }
+        if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok())
+            && (1..=12).contains(&n)
+        {
+            return Ok(Key::F(n));
+        }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Key::Char(c)),
+            _ => Err(format!("unknown key '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((name, _)) = NAMES.iter().find(|(_, key)| key == self) {
+            return f.write_str(name);
+        }
+        match self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(n) => write!(f, "f{}", n),
+            _ => unreachable!("every other key has a name"),
+        }
+    }
+}
+
+/// A key with the modifiers held down while pressing it. Shift is folded
+/// into letters, so `shift-g` and `G` are the same chord.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Chord {
+    pub fn new(key: Key, ctrl: bool, alt: bool, shift: bool) -> Self {
+        match key {
+            Key::Char(c) => Chord {
+                key: Key::Char(if shift { c.to_ascii_uppercase() } else { c }),
+                ctrl,
+                alt,
+                shift: false,
+            },
+            key => Chord {
+                key,
+                ctrl,
+                alt,
+                shift,
+            },
+        }
+    }
+
+    pub fn plain(key: Key) -> Self {
+        Chord::new(key, false, false, false)
+    }
+}
+
+impl FromStr for Chord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+        let mut rest = s;
+        // The last part is the key itself, which may be `-`.
+        while let Some((modifier, tail)) = rest.split_once('-')
+            && !tail.is_empty()
+        {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => ctrl = true,
+                "alt" => alt = true,
+                "shift" => shift = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, s)),
+            }
+            rest = tail;
+        }
+        Ok(Chord::new(rest.parse()?, ctrl, alt, shift))
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "ctrl-"),
+            (self.alt, "alt-"),
+            (self.shift, "shift-"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Chords pressed one after another.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sequence(pub Vec<Chord>);
+
+impl FromStr for Sequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chords = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Chord>, _>>()?;
+        if chords.is_empty() {
+            return Err("empty key sequence".into());
+        }
+        Ok(Sequence(chords))
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chords = self.0.iter().map(Chord::to_string).collect::<Vec<_>>();
+        f.write_str(&chords.join(" "))
+    }
+}
+
+/// The sequences that trigger one action; empty leaves it unbound.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bindings(pub Vec<Sequence>);
+
+impl FromStr for Bindings {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|part| !part.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Bindings)
+    }
+}
+
+impl fmt::Display for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sequences = self.0.iter().map(Sequence::to_string).collect::<Vec<_>>();
+        f.write_str(&sequences.join(", "))
+    }
+}
+
+impl Serialize for Bindings {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_round_trip_through_their_written_form() {
+        let bindings: Bindings = "backspace, d d,ctrl-alt-x, shift-G, ctrl--, comma"
+            .parse()
+            .unwrap();
+        assert_eq!(bindings.0.len(), 6);
+        assert_eq!(bindings.0[1].0, vec![Chord::plain(Key::Char('d')); 2]);
+        assert_eq!(
+            bindings.0[2].0,
+            vec![Chord::new(Key::Char('x'), true, true, false)]
+        );
+        assert_eq!(bindings.0[3].0, vec![Chord::plain(Key::Char('G'))]);
+        assert_eq!(
+            bindings.0[4].0,
+            vec![Chord::new(Key::Char('-'), true, false, false)]
+        );
+        assert_eq!(
+            bindings.to_string(),
+            "backspace, d d, ctrl-alt-x, G, ctrl--, comma"
+        );
+        assert_eq!("".parse::<Bindings>().unwrap(), Bindings::default());
+    }
+
+    #[test]
+    fn bad_keys_are_rejected() {
+        assert_eq!(
+            "hyper-x".parse::<Chord>().unwrap_err(),
+            "unknown modifier 'hyper' in 'hyper-x'"
+        );
+        assert_eq!("enterr".parse::<Key>().unwrap_err(), "unknown key 'enterr'");
+        assert!("f13".parse::<Key>().is_err());
+    }
+}
@@ -0,0 +1,51 @@
+//! The todo list behind the `todo` command, as a library.
+//!
+//! [`storage::TodoItem`] is the data model and [`storage::Storage`] the trait
+//! every backend implements: JSON, todo.txt, Markdown and one file per item,
+//! optionally wrapped with git history or encryption. [`filter`] and
+//! [`sort`] select and order items the way `todo list` does, and [`ops`]
+//! holds the changes the CLI and the APIs make to single items.
+//!
+//! ```
+//! use crix_todo::filter::ListFilter;
+//! use crix_todo::ops::{self, NewItem};
+//! use crix_todo::storage::{FileStorage, Storage};
+//!
+//! let dir = tempfile::tempdir()?;
+//! let storage = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+//! let item = NewItem {
+//!     description: "Walk dog".into(),
+//!     priority: Some(1),
+//!     ..NewItem::default()
+//! }
+//! .into_item()?;
+//! storage.add_item(item)?;
+//!
+//! let pending = ops::select(storage.load_items()?, &ListFilter::default(), None);
+//! assert_eq!(pending[0].description, "Walk dog");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The types in [`storage`], [`filter`], [`sort`], [`ops`] and [`error`]
+//! follow semantic versioning: a field or variant is only removed or changed
+//! in a new major version. Minor versions may add fields to structs, so build
+//! them with `..Default::default()` as above or through a constructor, and
+//! variants to [`error::TodoError`] and [`storage::Backend`], which are
+//! `#[non_exhaustive]`, so a match on them needs a wildcard arm.
+
+pub mod api;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod doctor;
+pub mod error;
+pub mod filter;
+pub mod formats;
+pub mod keys;
+pub mod merge;
+pub mod ops;
+pub mod rpc;
+pub mod search;
+pub mod sort;
+pub mod storage;
+pub mod watcher;
@@ -1,15 +1,54 @@
 mod cli;
 mod commands;
-mod storage;
+mod output;
+mod style;
 mod tui;
 
-use crate::storage::FileStorage;
 use clap::Parser;
 use cli::{Cli, Commands};
+use crix_todo::filter::ListFilter;
+use crix_todo::{config, error, storage};
+use std::path::Path;
 
 fn main() {
     let cli = Cli::parse();
-    let storage = FileStorage::new("todo.json");
+    if let Err(e) = run(cli) {
+        eprintln!("error: {}", e);
+        if let error::TodoError::Parse { .. } = e {
+            eprintln!("hint: run `todo doctor` to find and repair the problem");
+        }
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(cli: Cli) -> error::Result<()> {
+    // Works on the files git hands it, not on the configured storage.
+    if let Commands::MergeDriver { base, ours, theirs } = &cli.command {
+        return commands::merge_driver::run(base, ours, theirs);
+    }
+    // Works even when the config files are broken, so that they can be fixed.
+    if let Commands::Config { action } = &cli.command {
+        return commands::config::run(action.clone());
+    }
+    let config = config::load()?;
+    let path = config.storage.path();
+    match &cli.command {
+        Commands::Log { id } => return commands::log::run(Path::new(&path), id.clone()),
+        Commands::Restore { at } => return commands::restore::run(Path::new(&path), at.clone()),
+        Commands::Daemon => {
+            #[cfg(unix)]
+            return commands::daemon::run(storage::open_direct(&config)?, Path::new(&path));
+            #[cfg(not(unix))]
+            return Err(error::TodoError::InvalidInput(format!(
+                "cannot serve {}: the daemon needs Unix domain sockets",
+                path
+            )));
+        }
+        Commands::Encrypt => return commands::encrypt::run(storage::open_encrypted(&config)?),
+        Commands::Decrypt => return commands::decrypt::run(storage::open_encrypted(&config)?),
+        _ => {}
+    }
+    let storage = storage::open(&config)?;
 
     match cli.command {
         Commands::Add {
@@ -18,13 +57,68 @@ fn main() {
             due,
             tags,
             notes,
-        } => commands::add::run(storage, description, priority, due, tags, notes),
+        } => {
+            let priority = priority.or(config.defaults.priority);
+            commands::add::run(storage, description, priority, due, tags, notes)
+        }
         Commands::List {
             all,
             priority,
             tag,
             due,
-        } => commands::list::run(storage, all, priority, tag, due),
-        Commands::Edit => commands::edit::run(storage),
+            sort,
+            format,
+            fields,
+        } => {
+            let filter = ListFilter {
+                show_all: all,
+                priority,
+                tag,
+                due,
+            };
+            commands::list::run(storage, filter, sort, format, fields, &config)
+        }
+        Commands::Search {
+            query,
+            all,
+            format,
+            fields,
+        } => commands::search::run(storage, query, all, format, fields, &config),
+        Commands::Export {
+            format,
+            pending,
+            priority,
+            tag,
+            due,
+            group_by,
+        } => {
+            let filter = ListFilter {
+                show_all: !pending,
+                priority,
+                tag,
+                due,
+            };
+            commands::export::run(storage, format, filter, group_by)
+        }
+        Commands::Import {
+            from,
+            file,
+            dry_run,
+        } => commands::import::run(storage, from, &file, dry_run),
+        Commands::Edit => commands::edit::run(storage, Path::new(&path), config),
+        Commands::Serve { bind } => commands::serve::run(storage, bind, config.serve.token),
+        Commands::Rpc => commands::rpc::run(storage, Path::new(&path)),
+        Commands::Doctor { fix } => {
+            commands::doctor::run(storage, config.storage.backend, Path::new(&path), fix)
+        }
+        Commands::MergeDriver { .. }
+        | Commands::Log { .. }
+        | Commands::Restore { .. }
+        | Commands::Daemon
+        | Commands::Config { .. }
+        | Commands::Encrypt
+        | Commands::Decrypt => {
+            unreachable!("handled before opening storage")
+        }
     }
 }
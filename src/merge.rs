@@ -0,0 +1,386 @@
+//! Three-way merge of two versions of a todo list against their common base.
+//!
+//! Items are matched by ID and merged field by field: a field changed on only
+//! one side takes that side's value, and tags merge as sets. Only a field
+//! changed differently on both sides, or an item changed on one side and
+//! deleted on the other, is a conflict.
+
+use crate::storage::TodoItem;
+use serde_json::{Map, Value};
+
+/// Field names in the order `TodoItem` serializes them.
+const FIELDS: &[&str] = &[
+    "id",
+    "description",
+    "priority",
+    "due",
+    "tags",
+    "done",
+    "notes",
+    "created",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Clean(Value),
+    Conflict { ours: Value, theirs: Value },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Item(Vec<(&'static str, Field)>),
+    /// Changed on one side and deleted on the other.
+    Deleted {
+        kept: TodoItem,
+        deleted_by: Side,
+    },
+}
+
+impl Entry {
+    fn is_conflict(&self) -> bool {
+        match self {
+            Entry::Item(fields) => fields
+                .iter()
+                .any(|(_, f)| matches!(f, Field::Conflict { .. })),
+            Entry::Deleted { .. } => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Merge {
+    pub entries: Vec<Entry>,
+}
+
+impl Merge {
+    /// How many items still need a human.
+    pub fn conflicts(&self) -> usize {
+        self.entries.iter().filter(|e| e.is_conflict()).count()
+    }
+
+    /// The merged list, or `None` while conflicts remain.
+    pub fn items(&self) -> Option<Vec<TodoItem>> {
+        self.entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Item(fields) => item_of(fields, None),
+                Entry::Deleted { .. } => None,
+            })
+            .collect()
+    }
+
+    /// The merged list with each conflicting field settled in favour of
+    /// `side`. An item changed on one side and deleted on the other is kept.
+    pub fn resolve(&self, side: Side) -> Vec<TodoItem> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Item(fields) => item_of(fields, Some(side)),
+                Entry::Deleted { kept, .. } => Some(kept.clone()),
+            })
+            .collect()
+    }
+
+    /// The merged list as JSON with git-style conflict markers around each
+    /// conflicting field, or around the whole item for a change/delete
+    /// conflict. Without conflicts this is what `FileStorage` would write.
+    pub fn render(&self) -> String {
+        if let Some(items) = self.items() {
+            return serde_json::to_string_pretty(&items).unwrap_or_default();
+        }
+
+        let mut out = String::from("[\n");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let comma = if i + 1 < self.entries.len() { "," } else { "" };
+            match entry {
+                Entry::Item(fields) => {
+                    out.push_str("  {\n");
+                    for (j, (name, field)) in fields.iter().enumerate() {
+                        let comma = if j + 1 < fields.len() { "," } else { "" };
+                        match field {
+                            Field::Clean(value) => out.push_str(&field_line(name, value, comma)),
+                            Field::Conflict { ours, theirs } => {
+                                out.push_str("<<<<<<< ours\n");
+                                out.push_str(&field_line(name, ours, comma));
+                                out.push_str("=======\n");
+                                out.push_str(&field_line(name, theirs, comma));
+                                out.push_str(">>>>>>> theirs\n");
+                            }
+                        }
+                    }
+                    out.push_str(&format!("  }}{}\n", comma));
+                }
+                Entry::Deleted { kept, deleted_by } => {
+                    let json = serde_json::to_string_pretty(kept).unwrap_or_default();
+                    let body = format!("  {}{}\n", json.replace('\n', "\n  "), comma);
+                    let (ours, theirs) = match deleted_by {
+                        Side::Ours => ("", body.as_str()),
+                        Side::Theirs => (body.as_str(), ""),
+                    };
+                    out.push_str(&format!(
+                        "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs\n",
+                        ours, theirs
+                    ));
+                }
+            }
+        }
+        out.push_str("]\n");
+        out
+    }
+}
+
+/// Builds an item from merged fields, taking `side`'s value for conflicts,
+/// or giving up on them without one.
+fn item_of(fields: &[(&'static str, Field)], side: Option<Side>) -> Option<TodoItem> {
+    let mut map = Map::new();
+    for (name, field) in fields {
+        let value = match (field, side) {
+            (Field::Clean(value), _) => value,
+            (Field::Conflict { ours, .. }, Some(Side::Ours)) => ours,
+            (Field::Conflict { theirs, .. }, Some(Side::Theirs)) => theirs,
+            (Field::Conflict { .. }, None) => return None,
+        };
+        map.insert(name.to_string(), value.clone());
+    }
+    serde_json::from_value(Value::Object(map)).ok()
+}
+
+fn field_line(name: &str, value: &Value, comma: &str) -> String {
+    let json = serde_json::to_string_pretty(value).unwrap_or_default();
+    format!(
+        "    \"{}\": {}{}\n",
+        name,
+        json.replace('\n', "\n    "),
+        comma
+    )
+}
+
+/// Merges `ours` and `theirs` against `base`. The result follows our order,
+/// with items only they added appended in their order.
+pub fn merge(base: &[TodoItem], ours: &[TodoItem], theirs: &[TodoItem]) -> Merge {
+    let find = |items: &'_ [TodoItem], id: &str| items.iter().find(|i| i.id == id).cloned();
+    let mut entries = Vec::new();
+
+    for mine in ours {
+        let original = find(base, &mine.id);
+        match (original, find(theirs, &mine.id)) {
+            (original, Some(other)) => {
+                entries.push(merge_item(original.as_ref(), mine, &other));
+            }
+            (Some(original), None) if original == *mine => {}
+            (Some(_), None) => entries.push(Entry::Deleted {
+                kept: mine.clone(),
+                deleted_by: Side::Theirs,
+            }),
+            (None, None) => entries.push(merge_item(None, mine, mine)),
+        }
+    }
+
+    for other in theirs.iter().filter(|t| find(ours, &t.id).is_none()) {
+        match find(base, &other.id) {
+            Some(original) if original == *other => {}
+            Some(_) => entries.push(Entry::Deleted {
+                kept: other.clone(),
+                deleted_by: Side::Ours,
+            }),
+            None => entries.push(merge_item(None, other, other)),
+        }
+    }
+
+    Merge { entries }
+}
+
+fn merge_item(base: Option<&TodoItem>, ours: &TodoItem, theirs: &TodoItem) -> Entry {
+    let base = base.map(fields_of);
+    let ours = fields_of(ours);
+    let theirs = fields_of(theirs);
+
+    let fields = FIELDS
+        .iter()
+        .map(|&name| {
+            let o = ours.get(name).cloned().unwrap_or(Value::Null);
+            let t = theirs.get(name).cloned().unwrap_or(Value::Null);
+            let b = base
+                .as_ref()
+                .map(|b| b.get(name).cloned().unwrap_or(Value::Null));
+
+            let field = if o == t || b.as_ref() == Some(&t) {
+                Field::Clean(o)
+            } else if b.as_ref() == Some(&o) {
+                Field::Clean(t)
+            } else if name == "tags" {
+                Field::Clean(merge_tags(b.as_ref(), &o, &t))
+            } else {
+                Field::Conflict { ours: o, theirs: t }
+            };
+            (name, field)
+        })
+        .collect();
+    Entry::Item(fields)
+}
+
+fn fields_of(item: &TodoItem) -> Map<String, Value> {
+    match serde_json::to_value(item) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Keeps our tags, drops those they removed and adds those they added.
+fn merge_tags(base: Option<&Value>, ours: &Value, theirs: &Value) -> Value {
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        value
+            .and_then(Value::as_array)
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|t| t.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let base = strings(base);
+    let theirs = strings(Some(theirs));
+
+    let mut merged: Vec<String> = strings(Some(ours))
+        .into_iter()
+        .filter(|tag| !base.contains(tag) || theirs.contains(tag))
+        .collect();
+    for tag in theirs {
+        if !base.contains(&tag) && !merged.contains(&tag) {
+            merged.push(tag);
+        }
+    }
+
+    if merged.is_empty() {
+        Value::Null
+    } else {
+        Value::from(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, description: &str) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: description.into(),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn merges_fields_changed_on_different_sides() {
+        let base = vec![item("a", "Write report"), item("b", "Walk dog")];
+        let mut ours = base.clone();
+        ours[0].done = true;
+        ours.push(item("c", "Ours added"));
+        let mut theirs = base.clone();
+        theirs[0].priority = Some(1);
+        theirs.remove(1);
+        theirs.push(item("d", "Theirs added"));
+
+        let merge = merge(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts(), 0);
+
+        let items = merge.items().unwrap();
+        let ids: Vec<&str> = items.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c", "d"]);
+        assert!(items[0].done);
+        assert_eq!(items[0].priority, Some(1));
+        assert_eq!(
+            merge.render(),
+            serde_json::to_string_pretty(&items).unwrap()
+        );
+    }
+
+    #[test]
+    fn tags_merge_as_sets() {
+        let mut base = item("a", "x");
+        base.tags = Some(vec!["home".into(), "old".into()]);
+        let mut ours = base.clone();
+        ours.tags = Some(vec!["home".into(), "old".into(), "urgent".into()]);
+        let mut theirs = base.clone();
+        theirs.tags = Some(vec!["home".into(), "garden".into()]);
+
+        let items = merge(&[base], &[ours], &[theirs]).items().unwrap();
+        assert_eq!(
+            items[0].tags,
+            Some(vec!["home".into(), "urgent".into(), "garden".into()])
+        );
+    }
+
+    #[test]
+    fn same_field_changed_differently_is_marked() {
+        let base = vec![item("a", "Write report")];
+        let mut ours = base.clone();
+        ours[0].description = "Write the report".into();
+        let mut theirs = base.clone();
+        theirs[0].description = "Write report today".into();
+        theirs[0].done = true;
+
+        let merge = merge(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts(), 1);
+        assert!(merge.items().is_none());
+        assert_eq!(
+            merge.render(),
+            "[\n  {\n    \"id\": \"a\",\n\
+             <<<<<<< ours\n    \"description\": \"Write the report\",\n\
+             =======\n    \"description\": \"Write report today\",\n\
+             >>>>>>> theirs\n\
+             \x20   \"priority\": null,\n    \"due\": null,\n    \"tags\": null,\n\
+             \x20   \"done\": true,\n    \"notes\": null,\n    \"created\": null\n  }\n]\n"
+        );
+    }
+
+    #[test]
+    fn change_against_delete_is_a_conflict() {
+        let base = vec![item("a", "Write report"), item("b", "Walk dog")];
+        let mut ours = base.clone();
+        ours[0].done = true;
+        ours.remove(1);
+        let theirs = vec![item("b", "Walk the dog")];
+
+        let merge = merge(&base, &ours, &theirs);
+        assert_eq!(merge.conflicts(), 2);
+        assert_eq!(
+            merge.entries[0],
+            Entry::Deleted {
+                kept: ours[0].clone(),
+                deleted_by: Side::Theirs,
+            }
+        );
+        let rendered = merge.render();
+        assert!(rendered.contains("=======\n  {\n    \"id\": \"b\""));
+    }
+
+    #[test]
+    fn resolve_settles_conflicts_for_one_side() {
+        let base = vec![item("a", "Write report"), item("b", "Walk dog")];
+        let mut ours = base.clone();
+        ours[0].description = "Write the report".into();
+        ours[1].done = true;
+        let mut theirs = vec![base[0].clone()];
+        theirs[0].description = "Write report today".into();
+
+        let resolved = merge(&base, &ours, &theirs).resolve(Side::Ours);
+        assert_eq!(resolved, ours);
+    }
+
+    #[test]
+    fn unchanged_items_deleted_on_one_side_stay_deleted() {
+        let base = vec![item("a", "x"), item("b", "y")];
+        let ours = vec![item("a", "x")];
+        let theirs = vec![item("b", "y")];
+
+        let merge = merge(&base, &ours, &theirs);
+        assert_eq!(merge.items(), Some(vec![]));
+    }
+}
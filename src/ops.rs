@@ -0,0 +1,222 @@
+//! Queries and changes to single items, shared by the commands that edit the
+//! list from outside the TUI.
+
+use crate::error::{Result, TodoError};
+use crate::filter::ListFilter;
+use crate::sort::SortSpec;
+use crate::storage::{Storage, TodoItem, new_id, now_timestamp};
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
+
+/// The fields a caller chooses for a new item.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewItem {
+    pub description: String,
+    #[serde(default)]
+    pub priority: Option<u8>,
+    #[serde(default)]
+    pub due: Option<String>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl NewItem {
+    /// A pending item with a fresh ID, or an error if a field is invalid.
+    pub fn into_item(self) -> Result<TodoItem> {
+        if let Some(due) = &self.due {
+            check_due(due)?;
+        }
+        Ok(TodoItem {
+            id: new_id(),
+            description: self.description,
+            priority: self.priority,
+            due: self.due,
+            tags: self.tags,
+            done: false,
+            notes: self.notes,
+            created: Some(now_timestamp()),
+        })
+    }
+}
+
+/// Fields to change on an item. A missing field is left alone; an explicit
+/// `null` clears an optional one.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Patch {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub priority: Option<Option<u8>>,
+    #[serde(default, deserialize_with = "present")]
+    pub due: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub tags: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "present")]
+    pub notes: Option<Option<String>>,
+    #[serde(default)]
+    pub done: Option<bool>,
+}
+
+// Tells `"due": null` (clear it) apart from no `due` at all (keep it).
+fn present<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl Patch {
+    /// Applies the patch to `item`, leaving it untouched if a field is invalid.
+    pub fn apply(self, item: &mut TodoItem) -> Result<()> {
+        if let Some(Some(due)) = &self.due {
+            check_due(due)?;
+        }
+        if let Some(description) = self.description {
+            item.description = description;
+        }
+        if let Some(priority) = self.priority {
+            item.priority = priority;
+        }
+        if let Some(due) = self.due {
+            item.due = due;
+        }
+        if let Some(tags) = self.tags {
+            item.tags = tags;
+        }
+        if let Some(notes) = self.notes {
+            item.notes = notes;
+        }
+        if let Some(done) = self.done {
+            item.done = done;
+        }
+        Ok(())
+    }
+}
+
+pub fn check_due(due: &str) -> Result<()> {
+    match NaiveDate::parse_from_str(due, "%Y-%m-%d") {
+        Ok(_) => Ok(()),
+        Err(_) => Err(TodoError::InvalidInput(format!(
+            "invalid due date '{}': expected YYYY-MM-DD",
+            due
+        ))),
+    }
+}
+
+/// Where the item with `id` is in `items`.
+pub fn position(items: &[TodoItem], id: &str) -> Option<usize> {
+    items.iter().position(|item| item.id == id)
+}
+
+/// The items `todo list` would show for `filter` and `sort`, in that order.
+pub fn select(items: Vec<TodoItem>, filter: &ListFilter, sort: Option<&SortSpec>) -> Vec<TodoItem> {
+    let mut selected = items
+        .into_iter()
+        .filter(|item| filter.matches(item))
+        .collect::<Vec<_>>();
+    if let Some(spec) = sort {
+        selected.sort_by(|a, b| spec.compare(a, b));
+    }
+    selected
+}
+
+/// Adds `item` and returns it as stored, which a hook may have changed.
+pub fn add(storage: &impl Storage, item: TodoItem) -> Result<TodoItem> {
+    storage.add_item(item.clone())?;
+    Ok(stored(storage, &item.id)?.unwrap_or(item))
+}
+
+/// The item with `id` as it is now stored.
+fn stored(storage: &impl Storage, id: &str) -> Result<Option<TodoItem>> {
+    let mut items = storage.load_items()?;
+    Ok(position(&items, id).map(|index| items.swap_remove(index)))
+}
+
+/// Applies `change` to the item with `id` and saves the list, returning the
+/// changed item as stored, or `None` if there is no such item. Nothing is
+/// saved if `change` fails.
+pub fn update<E: From<TodoError>>(
+    storage: &impl Storage,
+    id: &str,
+    change: impl FnOnce(&mut TodoItem) -> std::result::Result<(), E>,
+) -> std::result::Result<Option<TodoItem>, E> {
+    let mut items = storage.load_items()?;
+    let Some(index) = position(&items, id) else {
+        return Ok(None);
+    };
+    change(&mut items[index])?;
+    storage.save_items(&items)?;
+    Ok(stored(storage, id)?)
+}
+
+/// Deletes the item with `id` once `check` allows it, returning the deleted
+/// item, or `None` if there is no such item.
+pub fn delete<E: From<TodoError>>(
+    storage: &impl Storage,
+    id: &str,
+    check: impl FnOnce(&TodoItem) -> std::result::Result<(), E>,
+) -> std::result::Result<Option<TodoItem>, E> {
+    let mut items = storage.load_items()?;
+    let Some(index) = position(&items, id) else {
+        return Ok(None);
+    };
+    check(&items[index])?;
+    let deleted = items.remove(index);
+    storage.save_items(&items)?;
+    Ok(Some(deleted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> TodoItem {
+        TodoItem {
+            id: "ab12cd34".into(),
+            description: "Walk dog".into(),
+            priority: Some(2),
+            due: Some("2025-07-10".into()),
+            tags: Some(vec!["home".into()]),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn patch_keeps_missing_fields_and_clears_null_ones() {
+        let patch: Patch =
+            serde_json::from_str(r#"{"priority":null,"notes":"leash","done":true}"#).unwrap();
+        let mut patched = item();
+        patch.apply(&mut patched).unwrap();
+
+        assert_eq!(
+            patched,
+            TodoItem {
+                priority: None,
+                notes: Some("leash".into()),
+                done: true,
+                ..item()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        let patch: Patch = serde_json::from_str(r#"{"description":"x","due":"soon"}"#).unwrap();
+        let mut patched = item();
+        assert!(matches!(
+            patch.apply(&mut patched),
+            Err(TodoError::InvalidInput(_))
+        ));
+        assert_eq!(patched, item());
+
+        assert!(serde_json::from_str::<Patch>(r#"{"colour":"red"}"#).is_err());
+        let new: NewItem =
+            serde_json::from_str(r#"{"description":"x","due":"2025-13-01"}"#).unwrap();
+        assert!(new.into_item().is_err());
+    }
+}
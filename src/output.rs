@@ -0,0 +1,687 @@
+use crate::style::{self, BOLD, DIM};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use crix_todo::config::Config;
+use crix_todo::storage::TodoItem;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON array
+    Json,
+    /// One compact JSON object per line
+    Jsonl,
+    Csv,
+    Tsv,
+    /// Aligned plain-text columns
+    Table,
+    /// GitHub-flavoured Markdown table
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Column {
+    Id,
+    Index,
+    Done,
+    Priority,
+    Due,
+    Description,
+    Tags,
+    Notes,
+    Created,
+}
+
+pub const DEFAULT_COLUMNS: &[Column] = &[
+    Column::Id,
+    Column::Done,
+    Column::Priority,
+    Column::Due,
+    Column::Description,
+    Column::Tags,
+];
+
+impl Column {
+    fn name(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Index => "index",
+            Column::Done => "done",
+            Column::Priority => "priority",
+            Column::Due => "due",
+            Column::Description => "description",
+            Column::Tags => "tags",
+            Column::Notes => "notes",
+            Column::Created => "created",
+        }
+    }
+}
+
+/// The JSON/JSONL shape. This is a public interface: add fields, never
+/// rename or remove them. `index` is the 1-based position shown by the text
+/// output, `tags` is always an array and absent values are `null`.
+#[derive(Serialize)]
+struct ItemRecord<'a> {
+    id: &'a str,
+    index: usize,
+    description: &'a str,
+    done: bool,
+    priority: Option<u8>,
+    due: Option<&'a str>,
+    tags: &'a [String],
+    notes: Option<&'a str>,
+    created: Option<u64>,
+}
+
+impl<'a> ItemRecord<'a> {
+    fn new(index: usize, item: &'a TodoItem) -> Self {
+        Self {
+            id: &item.id,
+            index: index + 1,
+            description: &item.description,
+            done: item.done,
+            priority: item.priority,
+            due: item.due.as_deref(),
+            tags: item.tags.as_deref().unwrap_or_default(),
+            notes: item.notes.as_deref(),
+            created: item.created,
+        }
+    }
+}
+
+/// Renders `rows` (file index, item) in `format`. Without `fields`, the
+/// tabular formats show [`DEFAULT_COLUMNS`] and JSON carries every field.
+pub fn render(
+    format: OutputFormat,
+    fields: Option<&[Column]>,
+    rows: &[(usize, &TodoItem)],
+) -> String {
+    let columns = fields.unwrap_or(DEFAULT_COLUMNS);
+    match format {
+        OutputFormat::Json => {
+            let records: Vec<serde_json::Value> = rows
+                .iter()
+                .map(|(i, item)| record(*i, item, fields))
+                .collect();
+            let mut out = serde_json::to_string_pretty(&records).expect("records serialize");
+            out.push('\n');
+            out
+        }
+        OutputFormat::Jsonl => rows
+            .iter()
+            .map(|(i, item)| record(*i, item, fields).to_string() + "\n")
+            .collect(),
+        OutputFormat::Csv => delimited(columns, rows, ",", csv_escape),
+        OutputFormat::Tsv => delimited(columns, rows, "\t", tsv_escape),
+        OutputFormat::Table => table(columns, rows),
+        OutputFormat::Markdown => markdown(columns, rows),
+    }
+}
+
+/// The JSON record for an item, keeping only `fields` if given.
+fn record(index: usize, item: &TodoItem, fields: Option<&[Column]>) -> serde_json::Value {
+    let mut value = serde_json::to_value(ItemRecord::new(index, item)).expect("record serializes");
+    if let (Some(fields), Some(object)) = (fields, value.as_object_mut()) {
+        object.retain(|key, _| fields.iter().any(|column| column.name() == key));
+    }
+    value
+}
+
+fn cell(column: Column, index: usize, item: &TodoItem, human: bool) -> String {
+    match column {
+        Column::Id => item.id.clone(),
+        Column::Index => (index + 1).to_string(),
+        Column::Done if human => if item.done { "[x]" } else { "[ ]" }.to_string(),
+        Column::Done => item.done.to_string(),
+        Column::Priority => item.priority.map(|p| p.to_string()).unwrap_or_default(),
+        Column::Due => item.due.clone().unwrap_or_default(),
+        Column::Description => item.description.clone(),
+        Column::Tags => item
+            .tags
+            .as_ref()
+            .map(|tags| tags.join(if human { ", " } else { "," }))
+            .unwrap_or_default(),
+        Column::Notes => item.notes.clone().unwrap_or_default(),
+        Column::Created => item.created.map(|c| c.to_string()).unwrap_or_default(),
+    }
+}
+
+fn delimited(
+    columns: &[Column],
+    rows: &[(usize, &TodoItem)],
+    separator: &str,
+    escape: fn(&str) -> String,
+) -> String {
+    let mut out = columns
+        .iter()
+        .map(|c| c.name())
+        .collect::<Vec<_>>()
+        .join(separator);
+    out.push('\n');
+    for (i, item) in rows {
+        let cells = columns
+            .iter()
+            .map(|c| escape(&cell(*c, *i, item, false)))
+            .collect::<Vec<_>>();
+        out.push_str(&cells.join(separator));
+        out.push('\n');
+    }
+    out
+}
+
+// RFC 4180: quote fields containing separators, quotes or line breaks.
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn tsv_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn table(columns: &[Column], rows: &[(usize, &TodoItem)]) -> String {
+    let header: Vec<String> = columns.iter().map(|c| c.name().to_uppercase()).collect();
+    let body: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(i, item)| {
+            columns
+                .iter()
+                .map(|c| cell(*c, *i, item, true).replace(['\n', '\r'], " "))
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|col| {
+            std::iter::once(&header)
+                .chain(&body)
+                .map(|row| row[col].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    std::iter::once(&header)
+        .chain(&body)
+        .map(|row| {
+            let padded = row
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{:<width$}", value, width = width))
+                .collect::<Vec<_>>();
+            padded.join("  ").trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+fn markdown(columns: &[Column], rows: &[(usize, &TodoItem)]) -> String {
+    let escape = |value: String| value.replace('|', "\\|").replace('\n', "<br>");
+
+    let mut out = format!(
+        "| {} |\n|{}|\n",
+        columns
+            .iter()
+            .map(|c| c.name())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        columns
+            .iter()
+            .map(|_| " --- ")
+            .collect::<Vec<_>>()
+            .join("|")
+    );
+    for (i, item) in rows {
+        let cells = columns
+            .iter()
+            .map(|c| escape(cell(*c, *i, item, true)))
+            .collect::<Vec<_>>();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
+}
+
+const MAX_TAGS_WIDTH: usize = 24;
+const MIN_DESCRIPTION_WIDTH: usize = 16;
+const GAP: &str = "  ";
+
+pub struct PrettyOptions {
+    pub today: NaiveDate,
+    /// Terminal width to fit into; `None` never wraps.
+    pub width: Option<usize>,
+    pub styled: bool,
+    /// Supplies the date format, colours and high-priority threshold.
+    pub config: Config,
+}
+
+/// The default human-readable `list` output: one aligned row per item with
+/// notes underneath, long text wrapped to fit `width`, overdue rows and rows
+/// due today in their configured colours, high priorities in bold and
+/// completed rows dimmed.
+pub fn pretty(rows: &[(usize, &TodoItem)], options: &PrettyOptions) -> String {
+    let header = ["#", "", "PRI", "DUE", "DESCRIPTION", "TAGS"];
+    let cells: Vec<[String; 4]> = rows
+        .iter()
+        .map(|(i, item)| {
+            [
+                (i + 1).to_string(),
+                if item.done { "[x]" } else { "[ ]" }.to_string(),
+                item.priority.map(|p| p.to_string()).unwrap_or_default(),
+                item.due
+                    .as_deref()
+                    .map(|due| format_date(due, &options.config.display.date_format))
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let tags: Vec<String> = rows
+        .iter()
+        .map(|(_, item)| item.tags.as_ref().map(|t| t.join(", ")).unwrap_or_default())
+        .collect();
+
+    let fixed: Vec<usize> = (0..4)
+        .map(|col| {
+            cells
+                .iter()
+                .map(|row| row[col].chars().count())
+                .chain([header[col].chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let tags_width = tags
+        .iter()
+        .map(|t| t.chars().count())
+        .chain([header[5].len()])
+        .max()
+        .unwrap_or(0)
+        .min(MAX_TAGS_WIDTH);
+
+    let natural_description = rows
+        .iter()
+        .flat_map(|(_, item)| {
+            std::iter::once(item.description.chars().count()).chain(
+                item.notes
+                    .iter()
+                    .flat_map(|n| n.lines().map(|l| l.chars().count() + 2)),
+            )
+        })
+        .chain([header[4].len()])
+        .max()
+        .unwrap_or(0);
+    let description_width = match options.width {
+        Some(width) => {
+            let used = fixed.iter().sum::<usize>() + tags_width + GAP.len() * 5;
+            width.saturating_sub(used).clamp(
+                MIN_DESCRIPTION_WIDTH,
+                natural_description.max(MIN_DESCRIPTION_WIDTH),
+            )
+        }
+        None => natural_description,
+    };
+
+    let layout = |columns: [&str; 6]| -> String {
+        let widths = [
+            fixed[0],
+            fixed[1],
+            fixed[2],
+            fixed[3],
+            description_width,
+            tags_width,
+        ];
+        let padded = columns
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(col, (value, width))| {
+                // right-align the index so the numbers line up
+                if col == 0 {
+                    format!("{:>width$}", value, width = width)
+                } else {
+                    format!("{:<width$}", value, width = width)
+                }
+            })
+            .collect::<Vec<_>>();
+        padded.join(GAP).trim_end().to_string()
+    };
+
+    let mut out = style::paint(&layout(header), BOLD, options.styled);
+    out.push('\n');
+
+    for (((_, item), cell), tags) in rows.iter().zip(&cells).zip(&tags) {
+        let row_style = row_style(item, options);
+        let description = wrap(&item.description, description_width);
+        let tags = truncate(tags, tags_width);
+
+        for (n, line) in description.iter().enumerate() {
+            let columns: [&str; 6] = if n == 0 {
+                [&cell[0], &cell[1], &cell[2], &cell[3], line, &tags]
+            } else {
+                ["", "", "", "", line, ""]
+            };
+            out.push_str(&style::paint(&layout(columns), &row_style, options.styled));
+            out.push('\n');
+        }
+
+        if let Some(notes) = &item.notes {
+            for line in notes
+                .lines()
+                .flat_map(|l| wrap(l, description_width.saturating_sub(2).max(1)))
+            {
+                let indented = format!("  {}", line);
+                out.push_str(&style::paint(
+                    &layout(["", "", "", "", &indented, ""]),
+                    DIM,
+                    options.styled,
+                ));
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// `due` in `format`, or as it is if it is not a YYYY-MM-DD date.
+pub fn format_date(due: &str, format: &str) -> String {
+    match NaiveDate::parse_from_str(due, "%Y-%m-%d") {
+        Ok(date) => date.format(format).to_string(),
+        Err(_) => due.to_string(),
+    }
+}
+
+fn row_style(item: &TodoItem, options: &PrettyOptions) -> String {
+    if item.done {
+        return DIM.to_string();
+    }
+
+    let config = &options.config;
+    let mut style = String::new();
+    if item.priority.is_some_and(|p| p <= config.priority.high) {
+        style.push_str(BOLD);
+    }
+    let due = item
+        .due
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    match due {
+        Some(due) if due < options.today => style.push_str(config.colors.overdue.ansi()),
+        Some(due) if due == options.today => style.push_str(config.colors.due_today.ansi()),
+        _ => {}
+    }
+    style
+}
+
+/// Greedy word wrap by char count; words longer than `width` are split.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        loop {
+            let used = current.chars().count();
+            let needed = if used == 0 {
+                word.len()
+            } else {
+                used + 1 + word.len()
+            };
+            if needed <= width {
+                if used > 0 {
+                    current.push(' ');
+                }
+                current.extend(word.iter());
+                break;
+            }
+            if used > 0 {
+                lines.push(std::mem::take(&mut current));
+                continue;
+            }
+            // a single word wider than the column
+            let rest = word.split_off(width.max(1));
+            lines.push(word.into_iter().collect());
+            word = rest;
+            if word.is_empty() {
+                break;
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> TodoItem {
+        TodoItem {
+            id: "0000abcd".into(),
+            description: "Write \"report\", v2".into(),
+            priority: Some(1),
+            due: Some("2025-07-10".into()),
+            tags: Some(vec!["work".into(), "urgent".into()]),
+            notes: Some("line one\nline two".into()),
+            created: Some(1_700_000_000),
+            ..TodoItem::default()
+        }
+    }
+
+    fn bare() -> TodoItem {
+        TodoItem {
+            id: "0000ffff".into(),
+            description: "Bare".into(),
+            done: true,
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn json_has_documented_shape() {
+        let (a, b) = (sample(), bare());
+        let out = render(OutputFormat::Json, None, &[(0, &a), (4, &b)]);
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+
+        assert_eq!(
+            value[1],
+            serde_json::json!({
+                "id": "0000ffff",
+                "index": 5,
+                "description": "Bare",
+                "done": true,
+                "priority": null,
+                "due": null,
+                "tags": [],
+                "notes": null,
+                "created": null,
+            })
+        );
+        assert_eq!(value[0]["tags"], serde_json::json!(["work", "urgent"]));
+    }
+
+    #[test]
+    fn jsonl_is_one_object_per_line() {
+        let (a, b) = (sample(), bare());
+        let out = render(OutputFormat::Jsonl, None, &[(0, &a), (1, &b)]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("{\"id\":\"0000abcd\",\"index\":1,"));
+    }
+
+    #[test]
+    fn json_keeps_only_the_fields_asked_for() {
+        let a = sample();
+        let out = render(
+            OutputFormat::Jsonl,
+            Some(&[Column::Id, Column::Due]),
+            &[(0, &a)],
+        );
+        assert_eq!(out, "{\"id\":\"0000abcd\",\"due\":\"2025-07-10\"}\n");
+    }
+
+    #[test]
+    fn csv_quotes_special_characters() {
+        let a = sample();
+        let columns = [Column::Id, Column::Description, Column::Tags, Column::Notes];
+        let out = render(OutputFormat::Csv, Some(&columns), &[(0, &a)]);
+        assert_eq!(
+            out,
+            "id,description,tags,notes\n0000abcd,\"Write \"\"report\"\", v2\",\"work,urgent\",\"line one\nline two\"\n"
+        );
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_and_newlines() {
+        let mut a = sample();
+        a.description = "a\tb\\c".into();
+        let columns = [Column::Description, Column::Notes, Column::Done];
+        let out = render(OutputFormat::Tsv, Some(&columns), &[(0, &a)]);
+        assert_eq!(
+            out,
+            "description\tnotes\tdone\na\\tb\\\\c\tline one\\nline two\tfalse\n"
+        );
+    }
+
+    #[test]
+    fn table_aligns_columns() {
+        let (a, b) = (sample(), bare());
+        let columns = [
+            Column::Index,
+            Column::Done,
+            Column::Priority,
+            Column::Description,
+        ];
+        let out = render(OutputFormat::Table, Some(&columns), &[(0, &a), (9, &b)]);
+        assert_eq!(
+            out,
+            "INDEX  DONE  PRIORITY  DESCRIPTION\n\
+             1      [ ]   1         Write \"report\", v2\n\
+             10     [x]             Bare\n"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes_pipes() {
+        let mut a = bare();
+        a.description = "a | b".into();
+        let out = render(
+            OutputFormat::Markdown,
+            Some(&[Column::Id, Column::Description]),
+            &[(0, &a)],
+        );
+        assert_eq!(
+            out,
+            "| id | description |\n| --- | --- |\n| 0000ffff | a \\| b |\n"
+        );
+    }
+
+    fn plain(width: Option<usize>) -> PrettyOptions {
+        PrettyOptions {
+            today: NaiveDate::from_ymd_opt(2025, 7, 10).unwrap(),
+            width,
+            styled: false,
+            config: Config::default(),
+        }
+    }
+
+    #[test]
+    fn pretty_aligns_rows_and_shows_notes() {
+        let (a, b) = (sample(), bare());
+        let out = pretty(&[(0, &a), (9, &b)], &plain(None));
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                " #       PRI  DUE         DESCRIPTION         TAGS",
+                " 1  [ ]  1    2025-07-10  Write \"report\", v2  work, urgent",
+                "                            line one",
+                "                            line two",
+                "10  [x]                   Bare",
+            ]
+        );
+    }
+
+    #[test]
+    fn pretty_wraps_descriptions_to_fit_width() {
+        let mut a = bare();
+        a.description = "one two three four five six seven eight nine ten".into();
+        let out = pretty(&[(0, &a)], &plain(Some(50)));
+
+        assert!(out.lines().all(|l| l.chars().count() <= 50));
+        let description_lines: Vec<&str> = out.lines().skip(1).map(|l| l.trim()).collect();
+        assert_eq!(description_lines.len(), 2);
+        assert!(description_lines[0].ends_with("one two three four five"));
+        assert_eq!(description_lines[1], "six seven eight nine ten");
+    }
+
+    #[test]
+    fn wrap_splits_overlong_words() {
+        assert_eq!(wrap("abcdefgh ij", 3), vec!["abc", "def", "gh", "ij"]);
+        assert_eq!(wrap("", 3), vec![""]);
+    }
+
+    #[test]
+    fn truncate_adds_ellipsis() {
+        assert_eq!(truncate("abcdef", 4), "abc…");
+        assert_eq!(truncate("abc", 4), "abc");
+    }
+
+    #[test]
+    fn row_style_reflects_due_date_priority_and_completion() {
+        let mut options = plain(None);
+        let (red, yellow) = ("\x1b[31m", "\x1b[33m");
+        let mut item = bare();
+        item.done = false;
+
+        item.due = Some("2025-07-09".into());
+        assert_eq!(row_style(&item, &options), red);
+        item.due = Some("2025-07-10".into());
+        assert_eq!(row_style(&item, &options), yellow);
+        item.priority = Some(0);
+        assert_eq!(row_style(&item, &options), format!("{}{}", BOLD, yellow));
+        item.due = Some("not a date".into());
+        assert_eq!(row_style(&item, &options), BOLD);
+        item.priority = Some(2);
+        assert_eq!(row_style(&item, &options), "");
+        options.config.priority.high = 2;
+        assert_eq!(row_style(&item, &options), BOLD);
+
+        item.done = true;
+        assert_eq!(row_style(&item, &options), DIM);
+    }
+
+    #[test]
+    fn due_dates_use_the_configured_format() {
+        assert_eq!(format_date("2025-07-09", "%d %b %Y"), "09 Jul 2025");
+        assert_eq!(format_date("someday", "%d %b %Y"), "someday");
+    }
+
+    #[test]
+    fn pretty_styles_only_when_asked() {
+        let mut a = bare();
+        a.done = false;
+        a.due = Some("2000-01-01".into());
+        let mut options = plain(None);
+        assert!(!pretty(&[(0, &a)], &options).contains('\x1b'));
+
+        options.styled = true;
+        let out = pretty(&[(0, &a)], &options);
+        assert!(out.lines().nth(1).unwrap().starts_with("\x1b[31m"));
+    }
+}
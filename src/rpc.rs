@@ -0,0 +1,333 @@
+//! `todo rpc`: JSON-RPC 2.0 for editor integrations, one message per line
+//! on stdin and stdout.
+//!
+//! Methods (all parameters are named):
+//!
+//! - `version` → `{"protocol": 1, "version": "<crate version>"}`
+//! - `query` `{all?, priority?, tag?, due?, sort?}` → the matching items,
+//!   filtered and sorted like `todo list`
+//! - `add` `{description, priority?, due?, tags?, notes?}` → the new item
+//! - `update` `{id, changes}` → the changed item; `changes` holds the fields
+//!   to set, with `null` clearing an optional one
+//! - `complete` `{id}` → the completed item
+//! - `delete` `{id}` → the deleted item
+//! - `subscribe` / `unsubscribe` → `true`; while subscribed, a `changed`
+//!   notification is sent whenever the list changes, whoever changed it
+//!
+//! [`PROTOCOL_VERSION`] goes up whenever a method or result changes in a way
+//! that would break an existing client.
+
+use crate::error::TodoError;
+use crate::filter::ListFilter;
+use crate::ops::{self, NewItem, Patch};
+use crate::sort::SortSpec;
+use crate::storage::{Storage, TodoItem};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// No item has the given ID.
+pub const NO_SUCH_ITEM: i64 = -32001;
+
+#[derive(Debug, Deserialize)]
+struct Call {
+    jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueryParams {
+    #[serde(default)]
+    all: bool,
+    priority: Option<u8>,
+    tag: Option<String>,
+    due: Option<String>,
+    sort: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateParams {
+    id: String,
+    changes: Patch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Failure {
+    code: i64,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Failure {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<TodoError> for Failure {
+    fn from(e: TodoError) -> Self {
+        let code = match e {
+            TodoError::InvalidInput(_) => INVALID_PARAMS,
+            _ => INTERNAL_ERROR,
+        };
+        Failure::new(code, e.to_string())
+    }
+}
+
+/// One client's connection: its storage and whether it wants notifications.
+pub struct Session<S> {
+    storage: S,
+    subscribed: bool,
+}
+
+impl<S: Storage> Session<S> {
+    pub fn new(storage: S) -> Self {
+        Session {
+            storage,
+            subscribed: false,
+        }
+    }
+
+    pub fn subscribed(&self) -> bool {
+        self.subscribed
+    }
+
+    /// The response line for one request line, or `None` for a notification.
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => return Some(error(Value::Null, Failure::new(PARSE_ERROR, e.to_string()))),
+        };
+        let call: Call = match serde_json::from_value(value) {
+            Ok(call) => call,
+            Err(e) => {
+                let failure = Failure::new(INVALID_REQUEST, e.to_string());
+                return Some(error(Value::Null, failure));
+            }
+        };
+        if call.jsonrpc != "2.0" {
+            let failure = Failure::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"");
+            return Some(error(call.id.unwrap_or(Value::Null), failure));
+        }
+
+        let outcome = self.call(&call.method, call.params.unwrap_or(Value::Null));
+        let id = call.id?;
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err(failure) => error(id, failure),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, Failure> {
+        match method {
+            "version" => Ok(json!({
+                "protocol": PROTOCOL_VERSION,
+                "version": env!("CARGO_PKG_VERSION"),
+            })),
+            "query" => {
+                let query: QueryParams = params_or_default(params)?;
+                let filter = ListFilter {
+                    show_all: query.all,
+                    priority: query.priority,
+                    tag: query.tag,
+                    due: query.due,
+                };
+                let sort = query
+                    .sort
+                    .map(|spec| spec.parse::<SortSpec>())
+                    .transpose()
+                    .map_err(|e| Failure::new(INVALID_PARAMS, format!("invalid sort: {}", e)))?;
+                let items = self.storage.load_items()?;
+                Ok(to_value(ops::select(items, &filter, sort.as_ref())))
+            }
+            "add" => {
+                let item = parse::<NewItem>(params)?.into_item()?;
+                Ok(to_value(ops::add(&self.storage, item)?))
+            }
+            "update" => {
+                let UpdateParams { id, changes } = parse(params)?;
+                let item = ops::update(&self.storage, &id, |item| changes.apply(item))?;
+                found(&id, item)
+            }
+            "complete" => {
+                let IdParams { id } = parse(params)?;
+                let item = ops::update(&self.storage, &id, |item| {
+                    item.done = true;
+                    Ok::<_, TodoError>(())
+                })?;
+                found(&id, item)
+            }
+            "delete" => {
+                let IdParams { id } = parse(params)?;
+                let item = ops::delete(&self.storage, &id, |_| Ok::<_, TodoError>(()))?;
+                found(&id, item)
+            }
+            "subscribe" | "unsubscribe" => {
+                no_params(params)?;
+                self.subscribed = method == "subscribe";
+                Ok(Value::Bool(true))
+            }
+            _ => Err(Failure::new(
+                METHOD_NOT_FOUND,
+                format!("no method named '{}'", method),
+            )),
+        }
+    }
+}
+
+/// The notification sent to subscribers when the list changes.
+pub fn changed_notification() -> String {
+    json!({ "jsonrpc": "2.0", "method": "changed" }).to_string()
+}
+
+fn error(id: Value, failure: Failure) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": failure.code, "message": failure.message },
+    })
+    .to_string()
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, Failure> {
+    serde_json::from_value(params)
+        .map_err(|e| Failure::new(INVALID_PARAMS, format!("invalid params: {}", e)))
+}
+
+fn params_or_default<T: DeserializeOwned + Default>(params: Value) -> Result<T, Failure> {
+    match params {
+        Value::Null => Ok(T::default()),
+        params => parse(params),
+    }
+}
+
+fn no_params(params: Value) -> Result<(), Failure> {
+    match params {
+        Value::Null => Ok(()),
+        Value::Object(map) if map.is_empty() => Ok(()),
+        _ => Err(Failure::new(INVALID_PARAMS, "this method takes no params")),
+    }
+}
+
+fn found(id: &str, item: Option<TodoItem>) -> Result<Value, Failure> {
+    item.map(to_value)
+        .ok_or_else(|| Failure::new(NO_SUCH_ITEM, format!("no item with ID {}", id)))
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Value {
+    serde_json::to_value(value).expect("items serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use tempfile::TempDir;
+
+    fn call(session: &mut Session<FileStorage>, request: &str) -> Value {
+        serde_json::from_str(&session.handle(request).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn items_can_be_added_changed_and_queried() {
+        let dir = TempDir::new().unwrap();
+        let mut session = Session::new(FileStorage::new(
+            dir.path().join("todo.json").to_str().unwrap(),
+        ));
+
+        let added = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":1,"method":"add","params":{"description":"Walk dog","priority":2}}"#,
+        );
+        let id = added["result"]["id"].as_str().unwrap().to_string();
+        let updated = call(
+            &mut session,
+            &json!({
+                "jsonrpc": "2.0", "id": 2, "method": "update",
+                "params": { "id": id, "changes": { "priority": null, "tags": ["home"] } },
+            })
+            .to_string(),
+        );
+        assert_eq!(updated["result"]["priority"], Value::Null);
+        assert_eq!(updated["result"]["tags"], json!(["home"]));
+
+        let complete =
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "complete", "params": { "id": id } });
+        assert_eq!(
+            call(&mut session, &complete.to_string())["result"]["done"],
+            true
+        );
+
+        let pending = call(&mut session, r#"{"jsonrpc":"2.0","id":4,"method":"query"}"#);
+        assert_eq!(pending["result"], json!([]));
+        let all = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":5,"method":"query","params":{"all":true,"tag":"home"}}"#,
+        );
+        assert_eq!(all["result"][0]["id"], id.as_str());
+        assert_eq!(all["id"], 5);
+    }
+
+    #[test]
+    fn errors_use_json_rpc_codes() {
+        let dir = TempDir::new().unwrap();
+        let mut session = Session::new(FileStorage::new(
+            dir.path().join("todo.json").to_str().unwrap(),
+        ));
+        let code = |session: &mut Session<FileStorage>, request: &str| {
+            call(session, request)["error"]["code"].as_i64().unwrap()
+        };
+
+        assert_eq!(code(&mut session, "{"), PARSE_ERROR);
+        assert_eq!(
+            code(&mut session, r#"{"jsonrpc":"1.0","id":1,"method":"query"}"#),
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            code(&mut session, r#"{"jsonrpc":"2.0","id":1,"method":"nap"}"#),
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            code(
+                &mut session,
+                r#"{"jsonrpc":"2.0","id":1,"method":"add","params":{"description":"x","due":"soon"}}"#
+            ),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(
+                &mut session,
+                r#"{"jsonrpc":"2.0","id":1,"method":"delete","params":{"id":"nope"}}"#
+            ),
+            NO_SUCH_ITEM
+        );
+
+        assert_eq!(
+            session.handle(r#"{"jsonrpc":"2.0","method":"subscribe"}"#),
+            None
+        );
+        assert!(session.subscribed());
+    }
+}
@@ -0,0 +1,256 @@
+//! Fuzzy matching of items against a query, as `todo search` and the TUI use.
+
+use crate::storage::TodoItem;
+use std::cmp::Reverse;
+
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 24;
+const WORD_START_BONUS: i64 = 16;
+const MAX_GAP_PENALTY: i64 = 10;
+
+/// A successful fuzzy match of a query against a single piece of text.
+/// `indices` are char (not byte) positions into the text.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub indices: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Field {
+    Description,
+    Notes,
+    Tag(usize),
+}
+
+impl Field {
+    // Description hits should outrank the same hit buried in notes.
+    fn weight(&self) -> i64 {
+        match self {
+            Field::Description => 3,
+            Field::Tag(_) => 2,
+            Field::Notes => 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldMatch {
+    pub field: Field,
+    pub indices: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SearchHit {
+    pub index: usize,
+    pub score: i64,
+    pub matches: Vec<FieldMatch>,
+}
+
+impl SearchHit {
+    pub fn indices_for(&self, field: Field) -> Option<&[usize]> {
+        self.matches
+            .iter()
+            .find(|m| m.field == field)
+            .map(|m| m.indices.as_slice())
+    }
+}
+
+/// Case-insensitive subsequence match. Every char of `query` must appear in
+/// `text` in order; consecutive runs and matches at word starts score higher.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let text: Vec<char> = text.chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    // folding char-by-char keeps `indices` aligned with `text`
+    let lowered: Vec<char> = text.iter().copied().map(fold).collect();
+
+    // Greedy matching from the leftmost position can miss a much better
+    // alignment later on ("pro" in "apple project"), so try every viable start.
+    (0..lowered.len())
+        .filter(|&start| lowered[start] == query[0])
+        .filter_map(|start| match_from(&query, &text, &lowered, start))
+        .max_by(|a, b| a.score.cmp(&b.score).then(b.indices[0].cmp(&a.indices[0])))
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn match_from(query: &[char], text: &[char], lowered: &[char], start: usize) -> Option<FuzzyMatch> {
+    let mut indices = Vec::with_capacity(query.len());
+    let mut pos = start;
+
+    for &qc in query {
+        while pos < lowered.len() && lowered[pos] != qc {
+            pos += 1;
+        }
+        if pos == lowered.len() {
+            return None;
+        }
+        indices.push(pos);
+        pos += 1;
+    }
+
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &i in &indices {
+        score += MATCH_SCORE;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        match previous {
+            Some(p) if p + 1 == i => score += CONSECUTIVE_BONUS,
+            Some(p) => score -= ((i - p - 1) as i64).min(MAX_GAP_PENALTY),
+            None => score -= (i as i64).min(MAX_GAP_PENALTY),
+        }
+        previous = Some(i);
+    }
+
+    Some(FuzzyMatch { score, indices })
+}
+
+/// Matches `query` against an item's description, tags and notes.
+///
+/// The query is split on whitespace and every term has to match at least one
+/// field; the score is the sum of each term's best weighted field score.
+pub fn match_item(item: &TodoItem, query: &str) -> Option<(i64, Vec<FieldMatch>)> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return None;
+    }
+
+    let mut fields: Vec<(Field, &str)> = vec![(Field::Description, &item.description)];
+    if let Some(tags) = &item.tags {
+        fields.extend(
+            tags.iter()
+                .enumerate()
+                .map(|(i, t)| (Field::Tag(i), t.as_str())),
+        );
+    }
+    if let Some(notes) = &item.notes {
+        fields.push((Field::Notes, notes));
+    }
+
+    let mut total = 0;
+    let mut matches: Vec<FieldMatch> = Vec::new();
+
+    for term in terms {
+        let mut best: Option<(i64, Field, FuzzyMatch)> = None;
+        for (field, text) in &fields {
+            if let Some(m) = fuzzy_match(term, text) {
+                let weighted = m.score * field.weight();
+                if best.as_ref().is_none_or(|(s, _, _)| weighted > *s) {
+                    best = Some((weighted, *field, m));
+                }
+            }
+        }
+
+        let (score, field, m) = best?;
+        total += score;
+        match matches.iter_mut().find(|fm| fm.field == field) {
+            Some(existing) => {
+                existing.indices.extend(m.indices);
+                existing.indices.sort_unstable();
+                existing.indices.dedup();
+            }
+            None => matches.push(FieldMatch {
+                field,
+                indices: m.indices,
+            }),
+        }
+    }
+
+    Some((total, matches))
+}
+
+/// Ranks every item matching `query`, best first. Ties keep file order.
+pub fn search(items: &[TodoItem], query: &str) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            match_item(item, query).map(|(score, matches)| SearchHit {
+                index,
+                score,
+                matches,
+            })
+        })
+        .collect();
+
+    hits.sort_by_key(|hit| Reverse(hit.score));
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(description: &str, tags: Option<Vec<&str>>, notes: Option<&str>) -> TodoItem {
+        TodoItem {
+            description: description.into(),
+            tags: tags.map(|t| t.into_iter().map(String::from).collect()),
+            notes: notes.map(String::from),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn fuzzy_match_requires_all_chars_in_order() {
+        assert!(fuzzy_match("frp", "Finish Rust project").is_some());
+        assert!(fuzzy_match("prf", "Finish Rust project").is_none());
+        assert!(fuzzy_match("", "anything").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_is_case_insensitive_and_reports_char_indices() {
+        let m = fuzzy_match("RUST", "a 🔥 rust").unwrap();
+        assert_eq!(m.indices, vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_word_starts_and_runs() {
+        let contiguous = fuzzy_match("rust", "rust project").unwrap();
+        let scattered = fuzzy_match("rust", "r u s t").unwrap();
+        assert!(contiguous.score > scattered.score);
+
+        // a later, better alignment wins over the leftmost one
+        let m = fuzzy_match("pro", "apple project").unwrap();
+        assert_eq!(m.indices, vec![6, 7, 8]);
+    }
+
+    #[test]
+    fn match_item_requires_every_term() {
+        let todo = item("Buy milk", Some(vec!["errands"]), Some("semi-skimmed"));
+        assert!(match_item(&todo, "milk errands").is_some());
+        assert!(match_item(&todo, "milk skimmed").is_some());
+        assert!(match_item(&todo, "milk bread").is_none());
+        assert!(match_item(&todo, "   ").is_none());
+    }
+
+    #[test]
+    fn match_item_reports_matching_field() {
+        let todo = item("Call", Some(vec!["home", "phone"]), Some("dentist"));
+        let (_, matches) = match_item(&todo, "phone").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].field, Field::Tag(1));
+        assert_eq!(matches[0].indices, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn search_ranks_description_above_notes() {
+        let items = vec![
+            item("Water plants", None, Some("report due friday")),
+            item("Write report", None, None),
+            item("Unrelated", None, None),
+        ];
+
+        let hits = search(&items, "report");
+        assert_eq!(hits.iter().map(|h| h.index).collect::<Vec<_>>(), vec![1, 0]);
+        assert!(hits[0].indices_for(Field::Description).is_some());
+        assert!(hits[1].indices_for(Field::Notes).is_some());
+    }
+}
@@ -0,0 +1,260 @@
+//! Ordering items by a list of keys, as `todo list --sort -priority,due` does.
+
+use crate::storage::TodoItem;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortField {
+    Due,
+    Priority,
+    Created,
+    Description,
+    Done,
+}
+
+impl SortField {
+    fn name(&self) -> &'static str {
+        match self {
+            SortField::Due => "due",
+            SortField::Priority => "priority",
+            SortField::Created => "created",
+            SortField::Description => "description",
+            SortField::Done => "done",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+/// An ordered list of sort keys, e.g. `due,-priority,created`.
+///
+/// A leading `-` sorts that key descending. Items missing a value (no due
+/// date, no priority) always sort after items that have one, whatever the
+/// direction, and anything still tied keeps its file order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortSpec {
+    keys: Vec<SortKey>,
+}
+
+impl SortSpec {
+    pub fn new(keys: Vec<SortKey>) -> Self {
+        Self { keys }
+    }
+
+    pub fn compare(&self, a: &TodoItem, b: &TodoItem) -> Ordering {
+        self.keys
+            .iter()
+            .map(|key| compare_key(key, a, b))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Indices into `items` in sorted order.
+    pub fn order(&self, items: &[TodoItem]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..items.len()).collect();
+        // `sort_by` is stable, which gives us the file-order tie-break.
+        indices.sort_by(|&a, &b| self.compare(&items[a], &items[b]));
+        indices
+    }
+}
+
+fn compare_key(key: &SortKey, a: &TodoItem, b: &TodoItem) -> Ordering {
+    match key.field {
+        SortField::Due => missing_last(a.due.as_deref(), b.due.as_deref(), key.descending),
+        SortField::Priority => missing_last(a.priority, b.priority, key.descending),
+        SortField::Created => missing_last(a.created, b.created, key.descending),
+        SortField::Description => directed(
+            a.description
+                .to_lowercase()
+                .cmp(&b.description.to_lowercase()),
+            key.descending,
+        ),
+        SortField::Done => directed(a.done.cmp(&b.done), key.descending),
+    }
+}
+
+fn missing_last<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => directed(a.cmp(&b), descending),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn directed(ordering: Ordering, descending: bool) -> Ordering {
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+impl FromStr for SortSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split(',')
+            .map(str::trim)
+            .map(|part| {
+                let (descending, name) = match part.strip_prefix('-') {
+                    Some(rest) => (true, rest),
+                    None => (false, part.strip_prefix('+').unwrap_or(part)),
+                };
+                let field = match name {
+                    "due" => SortField::Due,
+                    "priority" => SortField::Priority,
+                    "created" => SortField::Created,
+                    "description" => SortField::Description,
+                    "done" => SortField::Done,
+                    other => {
+                        return Err(format!(
+                            "unknown sort key '{}' (expected due, priority, created, description or done)",
+                            other
+                        ));
+                    }
+                };
+                Ok(SortKey { field, descending })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(keys))
+    }
+}
+
+impl fmt::Display for SortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = self
+            .keys
+            .iter()
+            .map(|k| format!("{}{}", if k.descending { "-" } else { "" }, k.field.name()))
+            .collect::<Vec<_>>();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+/// The named orderings the TUI cycles through.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortMode {
+    #[default]
+    Priority,
+    Due,
+    Created,
+    Description,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Priority => SortMode::Due,
+            SortMode::Due => SortMode::Created,
+            SortMode::Created => SortMode::Description,
+            SortMode::Description => SortMode::Priority,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Priority => "priority",
+            SortMode::Due => "due date",
+            SortMode::Created => "created",
+            SortMode::Description => "description",
+        }
+    }
+
+    pub fn spec(&self) -> SortSpec {
+        let spec = match self {
+            SortMode::Priority => "priority",
+            SortMode::Due => "due,priority",
+            SortMode::Created => "created",
+            SortMode::Description => "description",
+        };
+        spec.parse().expect("built-in sort spec")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(description: &str, priority: Option<u8>, due: Option<&str>) -> TodoItem {
+        TodoItem {
+            description: description.into(),
+            priority,
+            due: due.map(String::from),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn parses_and_displays_spec() {
+        let spec: SortSpec = "due, -priority,+created,description".parse().unwrap();
+        assert_eq!(spec.to_string(), "due,-priority,created,description");
+        assert_eq!(
+            spec.keys[1],
+            SortKey {
+                field: SortField::Priority,
+                descending: true
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = "due,colour".parse::<SortSpec>().unwrap_err();
+        assert!(err.contains("colour"));
+        assert!("".parse::<SortSpec>().is_err());
+    }
+
+    #[test]
+    fn missing_values_sort_last_in_both_directions() {
+        let items = vec![
+            todo("a", None, None),
+            todo("b", Some(2), None),
+            todo("c", Some(1), None),
+        ];
+
+        let asc: SortSpec = "priority".parse().unwrap();
+        assert_eq!(asc.order(&items), vec![2, 1, 0]);
+
+        let desc: SortSpec = "-priority".parse().unwrap();
+        assert_eq!(desc.order(&items), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn later_keys_break_ties_and_file_order_breaks_the_rest() {
+        let items = vec![
+            todo("x", Some(1), Some("2025-02-01")),
+            todo("y", Some(3), Some("2025-01-01")),
+            todo("z", Some(2), Some("2025-02-01")),
+            todo("w", Some(1), Some("2025-02-01")),
+        ];
+
+        let spec: SortSpec = "due,-priority".parse().unwrap();
+        assert_eq!(spec.order(&items), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn description_sort_ignores_case() {
+        let items = vec![todo("banana", None, None), todo("Apple", None, None)];
+        let spec: SortSpec = "description".parse().unwrap();
+        assert_eq!(spec.order(&items), vec![1, 0]);
+    }
+
+    #[test]
+    fn sort_modes_cycle_and_parse() {
+        let mut mode = SortMode::default();
+        for _ in 0..4 {
+            mode.spec();
+            mode = mode.next();
+        }
+        assert_eq!(mode, SortMode::Priority);
+    }
+}
@@ -0,0 +1,276 @@
+use super::{Storage, TodoItem, new_id};
+use crate::error::{Result, TodoError};
+use crate::formats::todotxt::decode;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Keeps each item in its own `<id>.json` file inside a directory, so that
+/// edits to different items never touch the same file and merge cleanly.
+///
+/// Items are listed in the order they were created. Saving only writes the
+/// files whose item changed and removes those of deleted items.
+pub struct DirStorage {
+    dir: PathBuf,
+}
+
+impl DirStorage {
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    fn path_for(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_stem(id)))
+    }
+
+    /// Every item file with the ID its name stands for, in no particular
+    /// order.
+    pub fn item_files(&self) -> Result<Vec<(PathBuf, String)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(TodoError::io(&self.dir, e)),
+        };
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| TodoError::io(&self.dir, e))?.path();
+            if let Some(stem) = item_stem(&path) {
+                let id = decode(stem);
+                files.push((path, id));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Every item file with its item, in no particular order.
+    fn read_all(&self) -> Result<Vec<(PathBuf, TodoItem)>> {
+        let mut items = Vec::new();
+        for (path, id) in self.item_files()? {
+            let content = fs::read_to_string(&path).map_err(|e| TodoError::io(&path, e))?;
+            let mut item: TodoItem =
+                serde_json::from_str(&content).map_err(|e| TodoError::json(&path, e))?;
+            if item.id.is_empty() {
+                item.id = id;
+            }
+            items.push((path, item));
+        }
+        Ok(items)
+    }
+
+    fn write(&self, item: &TodoItem) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| TodoError::io(&self.dir, e))?;
+        let path = self.path_for(&item.id);
+        let mut json = serde_json::to_string_pretty(item).map_err(|e| TodoError::json(&path, e))?;
+        json.push('\n');
+
+        // Write then rename, so a crash never leaves half an item behind.
+        let tmp = self.dir.join(format!(".{}.tmp", file_stem(&item.id)));
+        fs::write(&tmp, json).map_err(|e| TodoError::io(&tmp, e))?;
+        fs::rename(&tmp, &path).map_err(|e| TodoError::io(&path, e))
+    }
+}
+
+/// The ID part of an item file's name; `None` for anything else in the
+/// directory, such as a README or an editor's backup.
+fn item_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".json")?;
+    (!stem.is_empty() && !stem.starts_with('.')).then_some(stem)
+}
+
+// IDs we generate are hex, but imported ones may hold anything. Other bytes
+// are percent-encoded so that different IDs never share a file.
+fn file_stem(id: &str) -> String {
+    let mut stem = String::with_capacity(id.len());
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            stem.push(byte as char);
+        } else {
+            stem.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    stem
+}
+
+impl Storage for DirStorage {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        let mut items: Vec<TodoItem> = self.read_all()?.into_iter().map(|(_, i)| i).collect();
+        items.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.id.cmp(&b.id)));
+        Ok(items)
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let existing = self.read_all()?;
+
+        for item in items {
+            let unchanged = existing
+                .iter()
+                .any(|(path, old)| old == item && *path == self.path_for(&item.id));
+            if item.id.is_empty() {
+                self.add_item(item.clone())?;
+            } else if !unchanged {
+                self.write(item)?;
+            }
+        }
+        // Also removes files named under an older scheme, now rewritten.
+        for (path, old) in &existing {
+            if !items.iter().any(|item| item.id == old.id) || *path != self.path_for(&old.id) {
+                fs::remove_file(path).map_err(|e| TodoError::io(path, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_item(&self, mut item: TodoItem) -> Result<()> {
+        if item.id.is_empty() {
+            item.id = new_id();
+        }
+        self.write(&item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn item(id: &str, created: Option<u64>) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: format!("item {}", id),
+            created,
+            ..TodoItem::default()
+        }
+    }
+
+    fn storage(dir: &TempDir) -> DirStorage {
+        DirStorage::new(dir.path().join(".todo").to_str().unwrap())
+    }
+
+    #[test]
+    fn missing_directory_is_empty() {
+        let dir = TempDir::new().unwrap();
+        assert!(storage(&dir).load_items().unwrap().is_empty());
+    }
+
+    #[test]
+    fn one_file_per_item_in_creation_order() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        let items = vec![
+            item("bbbb0002", Some(20)),
+            item("aaaa0001", Some(10)),
+            item("old", None),
+        ];
+        storage.save_items(&items).unwrap();
+
+        let files: Vec<String> = {
+            let mut names: Vec<String> = fs::read_dir(dir.path().join(".todo"))
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(files, vec!["aaaa0001.json", "bbbb0002.json", "old.json"]);
+
+        let loaded = storage.load_items().unwrap();
+        let ids: Vec<&str> = loaded.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["old", "aaaa0001", "bbbb0002"]);
+    }
+
+    #[test]
+    fn save_leaves_unchanged_files_alone_and_removes_deleted() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        storage
+            .save_items(&[item("aaaa0001", Some(1)), item("bbbb0002", Some(2))])
+            .unwrap();
+
+        // Reformatted by hand but equal in content: must not be rewritten.
+        let first = dir.path().join(".todo/aaaa0001.json");
+        let compact = serde_json::to_string(&item("aaaa0001", Some(1))).unwrap();
+        fs::write(&first, &compact).unwrap();
+
+        let mut changed = item("aaaa0001", Some(1));
+        storage.save_items(std::slice::from_ref(&changed)).unwrap();
+        assert_eq!(fs::read_to_string(&first).unwrap(), compact);
+        assert!(!dir.path().join(".todo/bbbb0002.json").exists());
+
+        changed.done = true;
+        storage.save_items(std::slice::from_ref(&changed)).unwrap();
+        assert_eq!(storage.load_items().unwrap(), vec![changed]);
+    }
+
+    #[test]
+    fn add_writes_only_the_new_file_and_ignores_other_files() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        fs::create_dir_all(dir.path().join(".todo")).unwrap();
+        fs::write(dir.path().join(".todo/README.md"), "notes").unwrap();
+
+        storage.add_item(item("", Some(5))).unwrap();
+        let loaded = storage.load_items().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id.len(), 8);
+        assert!(
+            dir.path()
+                .join(format!(".todo/{}.json", loaded[0].id))
+                .exists()
+        );
+    }
+
+    #[test]
+    fn unsafe_ids_are_encoded_in_file_names() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        let items = vec![
+            item("a/b", Some(1)),
+            item("a_b", Some(2)),
+            item("a.b", Some(3)),
+        ];
+        storage.save_items(&items).unwrap();
+
+        assert!(dir.path().join(".todo/a%2Fb.json").exists());
+        assert!(dir.path().join(".todo/a%2Eb.json").exists());
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn save_gives_items_without_an_id_one() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        storage.save_items(&[item("", Some(1))]).unwrap();
+
+        let loaded = storage.load_items().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id.len(), 8);
+    }
+
+    #[test]
+    fn files_named_the_old_way_are_renamed() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        fs::create_dir_all(dir.path().join(".todo")).unwrap();
+        let old = serde_json::to_string(&item("a/b", Some(1))).unwrap();
+        fs::write(dir.path().join(".todo/a_b.json"), old).unwrap();
+
+        let items = storage.load_items().unwrap();
+        storage.save_items(&items).unwrap();
+        assert!(!dir.path().join(".todo/a_b.json").exists());
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn malformed_file_is_a_parse_error() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        fs::create_dir_all(dir.path().join(".todo")).unwrap();
+        fs::write(dir.path().join(".todo/bad.json"), "{").unwrap();
+
+        assert!(matches!(storage.load_items(), Err(TodoError::Parse { .. })));
+    }
+}
@@ -0,0 +1,491 @@
+use super::{Storage, TodoItem};
+use crate::error::{Result, TodoError};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload, rand_core::RngCore};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+
+/// Marks a description holding a sealed item rather than a real one.
+const SEALED_PREFIX: &str = "crix-sealed:v1:";
+/// The ID of the sealed, empty record that marks a list as encrypted, so
+/// that it stays encrypted even while it has no items.
+const MARKER_ID: &str = "crix-encrypted";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Where the passphrase comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeySource {
+    /// The contents of a file, without a trailing newline.
+    Keyfile(PathBuf),
+    Passphrase(String),
+    /// Asked for on the terminal when first needed.
+    Prompt,
+}
+
+impl KeySource {
+    /// `CRIX_TODO_KEYFILE`, then `CRIX_TODO_PASSPHRASE`, then a prompt.
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        if let Some(path) = var("CRIX_TODO_KEYFILE") {
+            KeySource::Keyfile(PathBuf::from(path))
+        } else if let Some(passphrase) = var("CRIX_TODO_PASSPHRASE") {
+            KeySource::Passphrase(passphrase)
+        } else {
+            KeySource::Prompt
+        }
+    }
+}
+
+/// Encrypts the items of any other storage.
+///
+/// Each item is stored as its ID plus a description holding the whole item,
+/// sealed with XChaCha20-Poly1305 under a key derived from the passphrase
+/// with Argon2id. The ID is authenticated too, so items cannot be swapped.
+///
+/// Lists without sealed items pass through untouched; once a list has been
+/// encrypted with `encrypt`, it holds a sealed marker record and every save
+/// keeps it encrypted, even when every item is deleted. Records left as they
+/// were keep their ciphertext, so only changed items change on disk, and an
+/// unsealed record in an encrypted list is refused rather than trusted.
+///
+/// Items are sealed one by one: without the passphrase nobody can read,
+/// change or add an item, but deleting one, or putting back an older copy of
+/// it, goes unnoticed.
+pub struct EncryptedStorage<S> {
+    inner: S,
+    path: PathBuf,
+    source: KeySource,
+    passphrase: RefCell<Option<Vec<u8>>>,
+    /// The salt and key last used, so the key is only derived once.
+    key: RefCell<Option<([u8; SALT_LEN], [u8; 32])>>,
+}
+
+/// What the inner storage holds.
+enum Stored {
+    Plain(Vec<TodoItem>),
+    /// The marker, and every other record with the item it unseals to.
+    Encrypted {
+        marker: TodoItem,
+        records: Vec<(TodoItem, TodoItem)>,
+    },
+}
+
+impl<S: Storage> EncryptedStorage<S> {
+    pub fn new(inner: S, path: &str, source: KeySource) -> Self {
+        Self {
+            inner,
+            path: PathBuf::from(path),
+            source,
+            passphrase: RefCell::new(None),
+            key: RefCell::new(None),
+        }
+    }
+
+    /// Seals every item of a plain list. Returns how many there were.
+    pub fn encrypt(&self) -> Result<usize> {
+        let items = self.inner.load_items()?;
+        if items.iter().any(is_sealed) {
+            return Err(TodoError::InvalidInput(format!(
+                "{} is already encrypted",
+                self.path.display()
+            )));
+        }
+        let mut records = vec![self.marker()?];
+        for item in &items {
+            records.push(self.seal(item)?);
+        }
+        self.inner.save_items(&records)?;
+        Ok(items.len())
+    }
+
+    /// Writes an encrypted list back in plain text. Returns how many items
+    /// there were.
+    pub fn decrypt(&self) -> Result<usize> {
+        let Stored::Encrypted { records, .. } = self.unlock()? else {
+            return Err(TodoError::InvalidInput(format!(
+                "{} is not encrypted",
+                self.path.display()
+            )));
+        };
+        let items = records
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect::<Vec<_>>();
+        self.inner.save_items(&items)?;
+        Ok(items.len())
+    }
+
+    /// The stored list, unsealed if it is encrypted. A wrong passphrase
+    /// fails here, before anything is sealed with it, and new items share
+    /// the existing key. Lists encrypted before the marker existed get a
+    /// new one.
+    fn unlock(&self) -> Result<Stored> {
+        let stored = self.inner.load_items()?;
+        if !stored.iter().any(is_sealed) {
+            return Ok(Stored::Plain(stored));
+        }
+        let mut marker = None;
+        let mut records = Vec::with_capacity(stored.len());
+        for record in stored {
+            if is_marker(&record) {
+                self.unseal(&record)?;
+                marker = Some(record);
+            } else if is_sealed(&record) {
+                let item = self.unseal(&record)?;
+                records.push((record, item));
+            } else {
+                return Err(TodoError::InvalidInput(format!(
+                    "{} is encrypted, but item {} is not, so it was not written \
+                     with the passphrase; remove it from the file to continue",
+                    self.path.display(),
+                    record.id
+                )));
+            }
+        }
+        let marker = match marker {
+            Some(marker) => marker,
+            None => self.marker()?,
+        };
+        Ok(Stored::Encrypted { marker, records })
+    }
+
+    fn marker(&self) -> Result<TodoItem> {
+        self.seal(&TodoItem {
+            id: MARKER_ID.into(),
+            ..TodoItem::default()
+        })
+    }
+
+    fn passphrase(&self, confirm: bool) -> Result<Vec<u8>> {
+        if let Some(passphrase) = self.passphrase.borrow().as_ref() {
+            return Ok(passphrase.clone());
+        }
+        let passphrase = match &self.source {
+            KeySource::Keyfile(path) => {
+                let mut bytes = fs::read(path).map_err(|e| TodoError::io(path, e))?;
+                while bytes.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+                    bytes.pop();
+                }
+                bytes
+            }
+            KeySource::Passphrase(passphrase) => passphrase.clone().into_bytes(),
+            KeySource::Prompt => self.prompt(confirm)?,
+        };
+        if passphrase.is_empty() {
+            return Err(TodoError::InvalidInput("the passphrase is empty".into()));
+        }
+        *self.passphrase.borrow_mut() = Some(passphrase.clone());
+        Ok(passphrase)
+    }
+
+    fn prompt(&self, confirm: bool) -> Result<Vec<u8>> {
+        let no_terminal = |_| {
+            TodoError::InvalidInput(format!(
+                "{} is encrypted: set CRIX_TODO_PASSPHRASE or CRIX_TODO_KEYFILE, or run from a terminal",
+                self.path.display()
+            ))
+        };
+        let passphrase =
+            rpassword::prompt_password(format!("Passphrase for {}: ", self.path.display()))
+                .map_err(no_terminal)?;
+        if confirm {
+            let again = rpassword::prompt_password("Repeat passphrase: ").map_err(no_terminal)?;
+            if again != passphrase {
+                return Err(TodoError::InvalidInput("passphrases do not match".into()));
+            }
+        }
+        Ok(passphrase.into_bytes())
+    }
+
+    /// The key for `salt`, or for a fresh salt if `None`.
+    fn key(&self, salt: Option<[u8; SALT_LEN]>) -> Result<([u8; SALT_LEN], [u8; 32])> {
+        if let Some((cached_salt, key)) = *self.key.borrow()
+            && salt.is_none_or(|salt| salt == cached_salt)
+        {
+            return Ok((cached_salt, key));
+        }
+
+        // A fresh salt means a list is being encrypted for the first time,
+        // which is when a typed passphrase should be confirmed.
+        let confirm = salt.is_none();
+        let salt = salt.unwrap_or_else(|| {
+            let mut salt = [0; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        });
+        let passphrase = self.passphrase(confirm)?;
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(&passphrase, &salt, &mut key)
+            .map_err(|e| TodoError::InvalidInput(format!("could not derive key: {}", e)))?;
+        *self.key.borrow_mut() = Some((salt, key));
+        Ok((salt, key))
+    }
+
+    fn seal(&self, item: &TodoItem) -> Result<TodoItem> {
+        let (salt, key) = self.key(None)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plain = serde_json::to_vec(item).map_err(|e| TodoError::json(&self.path, e))?;
+        let sealed = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plain,
+                    aad: item.id.as_bytes(),
+                },
+            )
+            .map_err(|_| TodoError::InvalidInput("encryption failed".into()))?;
+
+        let mut bytes = salt.to_vec();
+        bytes.extend_from_slice(&nonce);
+        bytes.extend(sealed);
+        Ok(TodoItem {
+            id: item.id.clone(),
+            description: format!("{}{}", SEALED_PREFIX, URL_SAFE_NO_PAD.encode(bytes)),
+            ..TodoItem::default()
+        })
+    }
+
+    fn unseal(&self, item: &TodoItem) -> Result<TodoItem> {
+        let bytes = item
+            .description
+            .strip_prefix(SEALED_PREFIX)
+            .and_then(|data| URL_SAFE_NO_PAD.decode(data).ok())
+            .filter(|bytes| bytes.len() > SALT_LEN + NONCE_LEN)
+            .ok_or_else(|| self.undecryptable(&item.id))?;
+        let (salt, rest) = bytes.split_at(SALT_LEN);
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+        let (_, key) = self.key(Some(salt.try_into().expect("split at SALT_LEN")))?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let plain = cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: sealed,
+                    aad: item.id.as_bytes(),
+                },
+            )
+            .map_err(|_| self.wrong_key())?;
+        serde_json::from_slice(&plain).map_err(|_| self.undecryptable(&item.id))
+    }
+
+    fn wrong_key(&self) -> TodoError {
+        TodoError::InvalidInput(format!(
+            "could not decrypt {}: wrong passphrase or key file",
+            self.path.display()
+        ))
+    }
+
+    fn undecryptable(&self, id: &str) -> TodoError {
+        TodoError::InvalidInput(format!(
+            "could not decrypt {}: item {} is damaged",
+            self.path.display(),
+            id
+        ))
+    }
+}
+
+pub fn is_sealed(item: &TodoItem) -> bool {
+    item.description.starts_with(SEALED_PREFIX)
+}
+
+/// Whether `item` is the record marking an encrypted list rather than a task.
+pub fn is_marker(item: &TodoItem) -> bool {
+    item.id == MARKER_ID && is_sealed(item)
+}
+
+impl<S: Storage> Storage for EncryptedStorage<S> {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        match self.unlock()? {
+            Stored::Plain(items) => Ok(items),
+            Stored::Encrypted { records, .. } => {
+                Ok(records.into_iter().map(|(_, item)| item).collect())
+            }
+        }
+    }
+
+    /// Seals the items that changed after the marker; the rest keep the
+    /// record they are stored as.
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let (marker, mut records) = match self.unlock()? {
+            Stored::Plain(_) => return self.inner.save_items(items),
+            Stored::Encrypted { marker, records } => (marker, records),
+        };
+        let mut sealed = vec![marker];
+        for item in items {
+            let unchanged = records.iter().position(|(_, stored)| stored == item);
+            match unchanged {
+                Some(index) => sealed.push(records.swap_remove(index).0),
+                None => sealed.push(self.seal(item)?),
+            }
+        }
+        self.inner.save_items(&sealed)
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        match self.unlock()? {
+            Stored::Plain(_) => self.inner.add_item(item),
+            Stored::Encrypted { .. } => self.inner.add_item(self.seal(&item)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use tempfile::TempDir;
+
+    fn item(id: &str, description: &str) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: description.into(),
+            priority: Some(1),
+            tags: Some(vec!["client".into()]),
+            notes: Some("ask Jane for the password hint".into()),
+            created: Some(1),
+            ..TodoItem::default()
+        }
+    }
+
+    fn storage(dir: &TempDir, passphrase: &str) -> EncryptedStorage<FileStorage> {
+        let path = dir.path().join("todo.json");
+        let path = path.to_str().unwrap();
+        EncryptedStorage::new(
+            FileStorage::new(path),
+            path,
+            KeySource::Passphrase(passphrase.into()),
+        )
+    }
+
+    #[test]
+    fn plain_lists_pass_through() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir, "secret");
+        storage.add_item(item("a", "Call Acme")).unwrap();
+
+        let raw = fs::read_to_string(dir.path().join("todo.json")).unwrap();
+        assert!(raw.contains("Call Acme"));
+        assert_eq!(storage.load_items().unwrap(), vec![item("a", "Call Acme")]);
+    }
+
+    #[test]
+    fn encrypted_lists_stay_encrypted() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir, "secret");
+        storage.save_items(&[item("a", "Call Acme")]).unwrap();
+        assert_eq!(storage.encrypt().unwrap(), 1);
+
+        let reopened = self::storage(&dir, "secret");
+        reopened.add_item(item("b", "Email Globex")).unwrap();
+        let raw = fs::read_to_string(dir.path().join("todo.json")).unwrap();
+        assert!(!raw.contains("Acme") && !raw.contains("Globex") && !raw.contains("Jane"));
+        assert!(raw.contains("\"id\": \"b\""));
+
+        let items = self::storage(&dir, "secret").load_items().unwrap();
+        assert_eq!(
+            items,
+            vec![item("a", "Call Acme"), item("b", "Email Globex")]
+        );
+
+        assert_eq!(self::storage(&dir, "secret").decrypt().unwrap(), 2);
+        let raw = fs::read_to_string(dir.path().join("todo.json")).unwrap();
+        assert!(raw.contains("Email Globex"));
+    }
+
+    #[test]
+    fn empty_lists_stay_encrypted() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(storage(&dir, "secret").encrypt().unwrap(), 0);
+
+        let storage = self::storage(&dir, "secret");
+        storage.add_item(item("a", "customer secret")).unwrap();
+        let path = dir.path().join("todo.json");
+        assert!(!fs::read_to_string(&path).unwrap().contains("customer"));
+        assert_eq!(
+            storage.load_items().unwrap(),
+            vec![item("a", "customer secret")]
+        );
+
+        // deleting every item keeps the list encrypted too
+        storage.save_items(&[]).unwrap();
+        storage.add_item(item("b", "customer secret")).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("customer"));
+        assert!(self::storage(&dir, "guess").load_items().is_err());
+    }
+
+    #[test]
+    fn only_changed_items_are_sealed_again() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir, "secret");
+        storage
+            .save_items(&[item("a", "Call Acme"), item("b", "Email Globex")])
+            .unwrap();
+        storage.encrypt().unwrap();
+        let raw = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+        let before = raw.load_items().unwrap();
+
+        let mut items = storage.load_items().unwrap();
+        items[1].done = true;
+        storage.save_items(&items).unwrap();
+        let after = raw.load_items().unwrap();
+        assert_eq!(after[..2], before[..2]);
+        assert_ne!(after[2], before[2]);
+        assert!(storage.load_items().unwrap()[1].done);
+    }
+
+    #[test]
+    fn unsealed_items_in_an_encrypted_list_are_refused() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir, "secret");
+        storage.save_items(&[item("a", "Call Acme")]).unwrap();
+        storage.encrypt().unwrap();
+
+        let raw = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+        raw.add_item(item("evil0001", "Wire $10k to Initech"))
+            .unwrap();
+        let err = storage.load_items().unwrap_err();
+        assert!(err.to_string().contains("item evil0001 is not"), "{}", err);
+        assert!(storage.save_items(&[]).is_err());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_reported_as_such() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir, "secret");
+        storage.save_items(&[item("a", "Call Acme")]).unwrap();
+        storage.encrypt().unwrap();
+
+        let wrong = self::storage(&dir, "guess");
+        let err = wrong.load_items().unwrap_err();
+        assert!(err.to_string().contains("wrong passphrase or key file"));
+        // Nothing may be sealed with a key that does not open the list.
+        assert!(wrong.add_item(item("b", "Email Globex")).is_err());
+        assert_eq!(storage.load_items().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn swapped_ids_do_not_decrypt() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir, "secret");
+        storage
+            .save_items(&[item("a", "Call Acme"), item("b", "Email Globex")])
+            .unwrap();
+        storage.encrypt().unwrap();
+
+        let path = dir.path().join("todo.json");
+        let raw = fs::read_to_string(&path).unwrap();
+        let swapped = raw
+            .replace("\"id\": \"a\"", "\"id\": \"x\"")
+            .replace("\"id\": \"b\"", "\"id\": \"a\"");
+        fs::write(&path, swapped).unwrap();
+        assert!(storage.load_items().is_err());
+    }
+}
@@ -0,0 +1,478 @@
+use super::encrypted::{is_marker, is_sealed};
+use super::{Storage, TodoItem};
+use crate::error::{Result, TodoError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Wraps another storage and records every change as a git commit in the
+/// repository whose top level is the todo file's directory, creating one if
+/// there is none. A list inside some other repository is refused before
+/// anything is saved, rather than committed to that repository.
+///
+/// Each commit touches only the todo file (or directory). Its subject
+/// summarises the change and its body has one `<verb> <id>: <description>`
+/// line per changed item, which is what `log` uses to follow a single item.
+/// Encrypted items are named by ID alone.
+pub struct GitHistory<S> {
+    inner: S,
+    path: PathBuf,
+}
+
+impl<S: Storage> GitHistory<S> {
+    pub fn new(inner: S, path: &str) -> Self {
+        Self {
+            inner,
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+impl<S: Storage> Storage for GitHistory<S> {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        self.inner.load_items()
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        prepare(&self.path)?;
+        let before = self.inner.load_items()?;
+        self.inner.save_items(items)?;
+        match describe(&before, items) {
+            Some(message) => commit(&self.path, &message),
+            None => Ok(()),
+        }
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        prepare(&self.path)?;
+        let message = describe(&[], std::slice::from_ref(&item));
+        self.inner.add_item(item)?;
+        commit(&self.path, &message.unwrap_or_default())
+    }
+}
+
+/// A commit message for the change from `before` to `after`, or `None` if
+/// nothing changed.
+pub fn describe(before: &[TodoItem], after: &[TodoItem]) -> Option<String> {
+    // The record marking an encrypted list is not a change anyone made.
+    let tasks = |items: &[TodoItem]| -> Vec<TodoItem> {
+        items.iter().filter(|i| !is_marker(i)).cloned().collect()
+    };
+    let (before, after) = (&tasks(before), &tasks(after));
+    let mut changes: Vec<(&str, &TodoItem, String)> = Vec::new();
+    for item in after {
+        let change = match before.iter().find(|old| old.id == item.id) {
+            None => ("add", String::new()),
+            Some(old) if old == item => continue,
+            Some(old) if !old.done && item.done => ("complete", String::new()),
+            Some(old) if old.done && !item.done => ("reopen", String::new()),
+            // The fields of an encrypted item are not ours to show.
+            Some(_) if is_sealed(item) => ("edit", String::new()),
+            Some(old) => (
+                "edit",
+                format!(" ({})", changed_fields(old, item).join(", ")),
+            ),
+        };
+        changes.push((change.0, item, change.1));
+    }
+    for old in before {
+        if !after.iter().any(|item| item.id == old.id) {
+            changes.push(("delete", old, String::new()));
+        }
+    }
+
+    let subject = match changes.as_slice() {
+        [] if before == after => return None,
+        [] => return Some("Reorder items".to_string()),
+        [(verb, item, _)] if is_sealed(item) => format!("{} item {}", capitalize(verb), item.id),
+        [(verb, item, _)] => format!("{} \"{}\"", capitalize(verb), item.description),
+        [(verb, ..), rest @ ..] if rest.iter().all(|(v, ..)| v == verb) => {
+            format!("{} {} items", capitalize(verb), changes.len())
+        }
+        _ => format!("Update {} items", changes.len()),
+    };
+    let body: Vec<String> = changes
+        .iter()
+        .map(|(verb, item, detail)| {
+            if is_sealed(item) {
+                format!("{} {}", verb, item.id)
+            } else {
+                format!("{} {}: {}{}", verb, item.id, item.description, detail)
+            }
+        })
+        .collect();
+    Some(format!("{}\n\n{}", subject, body.join("\n")))
+}
+
+fn changed_fields(old: &TodoItem, new: &TodoItem) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if old.description != new.description {
+        fields.push("description");
+    }
+    if old.priority != new.priority {
+        fields.push("priority");
+    }
+    if old.due != new.due {
+        fields.push("due");
+    }
+    if old.tags != new.tags {
+        fields.push("tags");
+    }
+    if old.notes != new.notes {
+        fields.push("notes");
+    }
+    if old.created != new.created {
+        fields.push("created");
+    }
+    fields
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// One commit in the history of the todo file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    /// Abbreviated commit hash.
+    pub rev: String,
+    /// Commit date, as YYYY-MM-DD.
+    pub date: String,
+    pub subject: String,
+    /// The `<verb> <id>: <description>` lines from the message body.
+    pub changes: Vec<String>,
+}
+
+/// The commits that touched the todo file at `path`, newest first. With an
+/// `id`, only those that changed that item, each with just its own line.
+pub fn log(path: &Path, id: Option<&str>) -> Result<Vec<Revision>> {
+    let (dir, name) = split(path);
+    if !is_repository(&dir)? {
+        return Err(not_enabled(path));
+    }
+    let output = git(
+        &dir,
+        &[
+            "log",
+            "--date=short",
+            "--format=%h%x1f%ad%x1f%s%x1f%b%x1e",
+            "--",
+            &name,
+        ],
+    )?;
+    if !output.status.success() {
+        // A repository without commits has no history yet.
+        return Ok(Vec::new());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut revisions = Vec::new();
+    for record in stdout
+        .split('\x1e')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    {
+        let fields: Vec<&str> = record.splitn(4, '\x1f').collect();
+        let [rev, date, subject, body] = fields[..] else {
+            continue;
+        };
+        let mut changes: Vec<String> = body.lines().map(str::to_string).collect();
+        if let Some(id) = id {
+            changes.retain(|line| change_id(line) == Some(id));
+            if changes.is_empty() {
+                continue;
+            }
+        }
+        revisions.push(Revision {
+            rev: rev.to_string(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+            changes,
+        });
+    }
+    Ok(revisions)
+}
+
+// "edit ac4382c6: Write report (due)" -> "ac4382c6"; encrypted items have
+// no description.
+fn change_id(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once(' ')?;
+    rest.split(": ").next()
+}
+
+/// Puts the todo file back the way it was at `rev` and commits that.
+/// Returns `false` if it already was.
+pub fn restore(path: &Path, rev: &str) -> Result<bool> {
+    let (dir, name) = split(path);
+    if !is_repository(&dir)? {
+        return Err(not_enabled(path));
+    }
+    let commit_rev = format!("{}^{{commit}}", rev);
+    if !git(&dir, &["rev-parse", "--verify", "--quiet", &commit_rev])?
+        .status
+        .success()
+    {
+        return Err(TodoError::InvalidInput(format!(
+            "unknown revision '{}'",
+            rev
+        )));
+    }
+    let source = format!("--source={}", rev);
+    let output = git(
+        &dir,
+        &["restore", &source, "--staged", "--worktree", "--", &name],
+    )?;
+    if !output.status.success() {
+        return Err(TodoError::InvalidInput(format!(
+            "{} did not exist at revision '{}'",
+            path.display(),
+            rev
+        )));
+    }
+    let short = git_ok(&dir, &["rev-parse", "--short", rev])?;
+    commit_in(&dir, &name, &format!("Restore list as of {}", short.trim()))
+}
+
+/// Makes sure the todo file has a repository of its own to commit to.
+fn prepare(path: &Path) -> Result<()> {
+    let (dir, _) = split(path);
+    match repository(&dir)? {
+        Repository::Own => Ok(()),
+        Repository::None => git_ok(&dir, &["init", "--quiet"]).map(|_| ()),
+        Repository::Enclosing(top) => Err(TodoError::InvalidInput(format!(
+            "cannot record the history of {}: it is inside the git repository at {}; \
+             keep the list in a directory of its own or turn history off",
+            path.display(),
+            top.display()
+        ))),
+    }
+}
+
+fn commit(path: &Path, message: &str) -> Result<()> {
+    let (dir, name) = split(path);
+    commit_in(&dir, &name, message).map(|_| ())
+}
+
+/// Commits the current state of `name` alone; `false` if it had not changed.
+fn commit_in(dir: &Path, name: &str, message: &str) -> Result<bool> {
+    git_ok(dir, &["add", "--all", "--", name])?;
+    if git(dir, &["diff", "--cached", "--quiet", "--", name])?
+        .status
+        .success()
+    {
+        return Ok(false);
+    }
+
+    // Fall back to a fixed identity rather than failing on a fresh machine.
+    let mut args = vec![];
+    if !git(dir, &["config", "user.email"])?.status.success() {
+        args.extend([
+            "-c",
+            "user.name=crix-todo",
+            "-c",
+            "user.email=crix-todo@localhost",
+        ]);
+    }
+    args.extend(["commit", "--quiet", "-m", message, "--", name]);
+    git_ok(dir, &args)?;
+    Ok(true)
+}
+
+/// The directory to run git in and the todo file's name within it.
+fn split(path: &Path) -> (PathBuf, String) {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| ".".to_string());
+    (dir, name)
+}
+
+/// Where `dir` stands with respect to git.
+enum Repository {
+    /// `dir` is the top level of a repository.
+    Own,
+    None,
+    /// `dir` is further down in the repository at this top level.
+    Enclosing(PathBuf),
+}
+
+fn repository(dir: &Path) -> Result<Repository> {
+    let output = git(dir, &["rev-parse", "--show-toplevel"])?;
+    if !output.status.success() {
+        return Ok(Repository::None);
+    }
+    let top = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    let same = |a: &Path, b: &Path| match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    if same(&top, dir) {
+        Ok(Repository::Own)
+    } else {
+        Ok(Repository::Enclosing(top))
+    }
+}
+
+fn is_repository(dir: &Path) -> Result<bool> {
+    Ok(matches!(repository(dir)?, Repository::Own))
+}
+
+fn not_enabled(path: &Path) -> TodoError {
+    TodoError::InvalidInput(format!(
+        "{} has no history: set CRIX_TODO_HISTORY=git to record changes",
+        path.display()
+    ))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| {
+            TodoError::io(
+                dir,
+                io::Error::new(e.kind(), format!("could not run git: {}", e)),
+            )
+        })
+}
+
+fn git_ok(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TodoError::io(
+            dir,
+            io::Error::other(format!("git {} failed: {}", args[0], stderr.trim())),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use tempfile::TempDir;
+
+    fn item(id: &str, description: &str) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: description.into(),
+            ..TodoItem::default()
+        }
+    }
+
+    #[test]
+    fn describes_changes() {
+        let before = vec![item("a", "Write report"), item("b", "Walk dog")];
+        let mut after = before.clone();
+        after[0].done = true;
+        assert_eq!(
+            describe(&before, &after).unwrap(),
+            "Complete \"Write report\"\n\ncomplete a: Write report"
+        );
+
+        after[1].due = Some("2025-01-01".into());
+        after[1].priority = Some(1);
+        after.push(item("c", "New"));
+        assert_eq!(
+            describe(&before, &after).unwrap(),
+            "Update 3 items\n\ncomplete a: Write report\nedit b: Walk dog (priority, due)\nadd c: New"
+        );
+
+        assert_eq!(
+            describe(&before, &[]).unwrap(),
+            "Delete 2 items\n\ndelete a: Write report\ndelete b: Walk dog"
+        );
+        assert_eq!(describe(&before, &before), None);
+    }
+
+    #[test]
+    fn commits_each_change_and_restores() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        let path_str = path.to_str().unwrap();
+        let storage = GitHistory::new(FileStorage::new(path_str), path_str);
+
+        storage.add_item(item("a", "Write report")).unwrap();
+        storage.add_item(item("b", "Walk dog")).unwrap();
+        let mut items = storage.load_items().unwrap();
+        items[0].done = true;
+        storage.save_items(&items).unwrap();
+        // Saving the same list again records nothing.
+        storage.save_items(&items).unwrap();
+
+        let history = log(&path, None).unwrap();
+        let subjects: Vec<&str> = history.iter().map(|r| r.subject.as_str()).collect();
+        assert_eq!(
+            subjects,
+            vec![
+                "Complete \"Write report\"",
+                "Add \"Walk dog\"",
+                "Add \"Write report\""
+            ]
+        );
+
+        let of_a = log(&path, Some("a")).unwrap();
+        assert_eq!(of_a.len(), 2);
+        assert_eq!(of_a[0].changes, vec!["complete a: Write report"]);
+
+        assert!(restore(&path, &history[2].rev).unwrap());
+        assert_eq!(
+            storage.load_items().unwrap(),
+            vec![item("a", "Write report")]
+        );
+        assert!(
+            log(&path, None).unwrap()[0]
+                .subject
+                .starts_with("Restore list as of ")
+        );
+        assert!(matches!(
+            restore(&path, "no-such-rev"),
+            Err(TodoError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn lists_inside_another_repository_are_not_committed_there() {
+        let dir = TempDir::new().unwrap();
+        git_ok(dir.path(), &["init", "--quiet"]).unwrap();
+        let project = dir.path().join("proj");
+        std::fs::create_dir(&project).unwrap();
+        let path = project.join("todo.json");
+        let path_str = path.to_str().unwrap();
+        let storage = GitHistory::new(FileStorage::new(path_str), path_str);
+
+        let err = storage.add_item(item("a", "secret task")).unwrap_err();
+        assert!(
+            err.to_string().contains("inside the git repository"),
+            "{}",
+            err
+        );
+        assert!(!path.exists());
+        assert!(
+            !git(dir.path(), &["rev-parse", "HEAD"])
+                .unwrap()
+                .status
+                .success()
+        );
+        assert!(log(&path, None).is_err());
+    }
+
+    #[test]
+    fn log_without_repository_explains_how_to_enable() {
+        let dir = TempDir::new().unwrap();
+        let err = log(&dir.path().join("todo.json"), None).unwrap_err();
+        assert!(err.to_string().contains("CRIX_TODO_HISTORY=git"));
+    }
+}
@@ -0,0 +1,389 @@
+use super::{Storage, TodoItem};
+use crate::error::{Result, TodoError};
+use serde::Serialize;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a hook may run before it is killed and the change refused.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What happened to an item, and the prefix of the hooks run for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    Add,
+    Modify,
+    Complete,
+    Delete,
+}
+
+impl Event {
+    fn prefix(self) -> &'static str {
+        match self {
+            Event::Add => "on-add",
+            Event::Modify => "on-modify",
+            Event::Complete => "on-complete",
+            Event::Delete => "on-delete",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HookInput<'a> {
+    event: Event,
+    old: Option<&'a TodoItem>,
+    new: Option<&'a TodoItem>,
+}
+
+/// Wraps another storage and runs the executables in a hooks directory on
+/// every change, whichever command or screen made it.
+///
+/// A hook named `on-add`, `on-modify`, `on-complete` or `on-delete` (or one
+/// of those followed by anything, such as `on-add.notify`) gets
+/// `{"event": ..., "old": ..., "new": ...}` as JSON on stdin. It refuses the
+/// change by exiting non-zero, with the reason on stderr or stdout, or
+/// replaces the new item by printing it on stdout. Hooks for one event run
+/// in name order, each seeing the item as the previous one left it. A hook
+/// still running after ten seconds is killed, refusing the change.
+pub struct HookedStorage<S> {
+    inner: S,
+    dir: PathBuf,
+    timeout: Duration,
+}
+
+impl<S: Storage> HookedStorage<S> {
+    pub fn new(inner: S, dir: PathBuf) -> Self {
+        Self {
+            inner,
+            dir,
+            timeout: TIMEOUT,
+        }
+    }
+
+    /// The item to store for `event`, or an error if a hook refused it.
+    fn run(
+        &self,
+        event: Event,
+        old: Option<&TodoItem>,
+        new: Option<TodoItem>,
+    ) -> Result<Option<TodoItem>> {
+        let mut new = new;
+        for hook in hooks(&self.dir, event) {
+            let input = HookInput {
+                event,
+                old,
+                new: new.as_ref(),
+            };
+            let input = serde_json::to_string(&input).expect("items serialize");
+            let replacement = run_hook(&hook, &input, self.timeout)?;
+            if let (Some(replacement), Some(current)) = (replacement, &new) {
+                if replacement.id != current.id {
+                    return Err(TodoError::InvalidInput(format!(
+                        "hook {} changed the ID of item {}",
+                        hook.file_name().unwrap_or_default().to_string_lossy(),
+                        current.id
+                    )));
+                }
+                new = Some(replacement);
+            }
+        }
+        Ok(new)
+    }
+}
+
+/// The executables in `dir` for `event`, in the order they run.
+fn hooks(dir: &Path, event: Event) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut hooks = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(event.prefix()))
+        })
+        .map(|entry| entry.path())
+        .filter(|path| is_executable(path))
+        .collect::<Vec<_>>();
+    hooks.sort();
+    hooks
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Runs one hook, returning the item it printed, if any.
+fn run_hook(hook: &Path, input: &str, timeout: Duration) -> Result<Option<TodoItem>> {
+    let name = hook.file_name().unwrap_or_default().to_string_lossy();
+    let mut child = Command::new(hook)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| TodoError::io(hook, e))?;
+    // Feed the input and read the output while the hook runs, so neither side
+    // blocks on a full pipe and the timeout always applies.
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = input.to_string();
+        // A hook that does not read its input is fine.
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
+    }
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| TodoError::io(hook, e))? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(TodoError::InvalidInput(format!(
+                "hook {} refused the change: it did not finish within {} seconds",
+                name,
+                timeout.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let collect = |output: Option<thread::JoinHandle<Vec<u8>>>| {
+        output
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    let stdout = collect(stdout);
+    let stderr = collect(stderr);
+    let stdout = String::from_utf8_lossy(&stdout);
+    let stderr = String::from_utf8_lossy(&stderr);
+
+    if !status.success() {
+        let reason = [stderr.trim(), stdout.trim()]
+            .into_iter()
+            .find(|s| !s.is_empty())
+            .unwrap_or("no reason given");
+        return Err(TodoError::InvalidInput(format!(
+            "hook {} refused the change: {}",
+            name, reason
+        )));
+    }
+    if stdout.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&stdout).map(Some).map_err(|e| {
+        TodoError::InvalidInput(format!("hook {} printed an invalid item: {}", name, e))
+    })
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        output
+    })
+}
+
+impl<S: Storage> Storage for HookedStorage<S> {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        self.inner.load_items()
+    }
+
+    /// Runs the hooks for every item that was added, changed or removed.
+    /// Refused changes are left out and the rest are saved; the refusals
+    /// are then reported together.
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let before = self.inner.load_items()?;
+        let old = |id: &str| before.iter().find(|item| item.id == id);
+
+        let mut saved = Vec::with_capacity(items.len());
+        let mut refusals = Vec::new();
+        for item in items {
+            let (event, old) = match old(&item.id) {
+                None => (Event::Add, None),
+                Some(old) if old == item => {
+                    saved.push(item.clone());
+                    continue;
+                }
+                Some(old) if !old.done && item.done => (Event::Complete, Some(old)),
+                Some(old) => (Event::Modify, Some(old)),
+            };
+            match self.run(event, old, Some(item.clone())) {
+                Ok(new) => saved.extend(new),
+                Err(e) => {
+                    refusals.push(e.to_string());
+                    saved.extend(old.cloned());
+                }
+            }
+        }
+        for (index, item) in before.iter().enumerate() {
+            if items.iter().any(|i| i.id == item.id) {
+                continue;
+            }
+            if let Err(e) = self.run(Event::Delete, Some(item), None) {
+                refusals.push(e.to_string());
+                // Back after the last saved item that came before it.
+                let earlier = |kept: &TodoItem| before[..index].iter().any(|b| b.id == kept.id);
+                let at = saved.iter().rposition(earlier).map_or(0, |p| p + 1);
+                saved.insert(at, item.clone());
+            }
+        }
+
+        self.inner.save_items(&saved)?;
+        if refusals.is_empty() {
+            Ok(())
+        } else {
+            Err(TodoError::InvalidInput(refusals.join("; ")))
+        }
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        match self.run(Event::Add, None, Some(item))? {
+            Some(item) => self.inner.add_item(item),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn item(id: &str, done: bool) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            description: format!("item {}", id),
+            done,
+            ..TodoItem::default()
+        }
+    }
+
+    fn hook(dir: &Path, name: &str, script: &str) {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn setup() -> (TempDir, HookedStorage<FileStorage>) {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("hooks")).unwrap();
+        let file = FileStorage::new(dir.path().join("todo.json").to_str().unwrap());
+        let storage = HookedStorage::new(file, dir.path().join("hooks"));
+        (dir, storage)
+    }
+
+    #[test]
+    fn hooks_can_refuse_a_change_and_others_are_still_saved() {
+        let (dir, storage) = setup();
+        let hooks = dir.path().join("hooks");
+        storage
+            .save_items(&[item("a", false), item("b", false)])
+            .unwrap();
+        hook(
+            &hooks,
+            "on-complete",
+            "echo 'finish the review first' >&2; exit 1",
+        );
+        hook(
+            &hooks,
+            "on-delete.log",
+            &format!("cat >> {}", dir.path().join("deleted").display()),
+        );
+
+        let err = storage.save_items(&[item("a", true)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "hook on-complete refused the change: finish the review first"
+        );
+        assert_eq!(storage.load_items().unwrap(), vec![item("a", false)]);
+        let logged = fs::read_to_string(dir.path().join("deleted")).unwrap();
+        assert!(logged.starts_with(r#"{"event":"delete","old":{"id":"b""#));
+    }
+
+    #[test]
+    fn refused_deletes_keep_their_place() {
+        let (dir, storage) = setup();
+        let items = [item("a", false), item("b", false), item("c", false)];
+        storage.save_items(&items).unwrap();
+        hook(&dir.path().join("hooks"), "on-delete", "exit 1");
+
+        storage
+            .save_items(&[item("a", false), item("c", false)])
+            .unwrap_err();
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn hooks_can_replace_the_item_in_turn() {
+        let (dir, storage) = setup();
+        let hooks = dir.path().join("hooks");
+        hook(
+            &hooks,
+            "on-add.1",
+            "sed 's/.*\"new\"://; s/}$//; s/\"tags\":null/\"tags\":[\"inbox\"]/'",
+        );
+        hook(
+            &hooks,
+            "on-add.2",
+            "sed 's/.*\"new\"://; s/}$//; s/item/task/'",
+        );
+        hook(&hooks, "on-add.off", "exit 1");
+        fs::set_permissions(hooks.join("on-add.off"), fs::Permissions::from_mode(0o644)).unwrap();
+
+        storage.add_item(item("a", false)).unwrap();
+        let stored = storage.load_items().unwrap();
+        assert_eq!(stored[0].description, "task a");
+        assert_eq!(stored[0].tags, Some(vec!["inbox".to_string()]));
+    }
+
+    #[test]
+    fn hooks_that_hang_are_killed() {
+        let (dir, storage) = setup();
+        let storage = HookedStorage {
+            timeout: Duration::from_millis(200),
+            ..storage
+        };
+        hook(&dir.path().join("hooks"), "on-add", "exec sleep 30");
+
+        let started = Instant::now();
+        let err = storage.add_item(item("a", false)).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("did not finish"), "{}", err);
+        assert!(storage.load_items().unwrap().is_empty());
+    }
+
+    #[test]
+    fn hooks_that_do_not_read_a_large_input_are_killed() {
+        let (dir, storage) = setup();
+        let storage = HookedStorage {
+            timeout: Duration::from_millis(200),
+            ..storage
+        };
+        hook(&dir.path().join("hooks"), "on-add", "exec sleep 30");
+        let large = TodoItem {
+            notes: Some("x".repeat(1 << 20)),
+            ..item("a", false)
+        };
+
+        let started = Instant::now();
+        let err = storage.add_item(large).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("did not finish"), "{}", err);
+    }
+}
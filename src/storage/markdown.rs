@@ -0,0 +1,268 @@
+use super::{Storage, TodoItem, assign_missing_ids};
+use crate::error::{Result, TodoError};
+use crate::formats::markdown::{self, Block};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Keeps the list as the checklist items of a Markdown file such as a
+/// repository's `TODO.md`.
+///
+/// Only checklist lines and the notes nested under them are ever rewritten;
+/// headings, prose and code blocks stay exactly as they are. Lines whose item
+/// did not change are written back byte for byte. New and changed items keep
+/// their ID in an HTML comment at the end of their line; the others keep one
+/// derived from their description. New items are added after the last
+/// existing one.
+pub struct MarkdownStorage {
+    path: PathBuf,
+}
+
+impl MarkdownStorage {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn read(&self) -> Result<String> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(TodoError::io(&self.path, e)),
+        }
+    }
+}
+
+/// The file's items, with IDs filled in.
+fn items_of(lines: &[&str]) -> Vec<Block> {
+    let mut blocks: Vec<Block> = markdown::blocks(lines)
+        .into_iter()
+        .filter(|b| !b.item.description.is_empty())
+        .collect();
+
+    let mut items: Vec<TodoItem> = blocks.iter().map(|b| b.item.clone()).collect();
+    assign_missing_ids(&mut items);
+    for (block, item) in blocks.iter_mut().zip(items) {
+        block.item.id = item.id;
+    }
+    blocks
+}
+
+/// `item` as lines, indented and bulleted like `template`, the line it replaces.
+fn render(item: &TodoItem, template: &str) -> Vec<String> {
+    let indent = &template[..template.len() - template.trim_start().len()];
+    let bullet = template.trim_start().chars().next().unwrap_or('-');
+
+    let mut lines = markdown::item_lines(item);
+    lines[0] = format!(
+        "{}{}{} {}",
+        indent,
+        bullet,
+        &lines[0][1..],
+        markdown::metadata_comment(item)
+    );
+    for line in lines.iter_mut().skip(1) {
+        *line = format!("{}{}", indent, line);
+    }
+    lines
+}
+
+impl Storage for MarkdownStorage {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        let content = self.read()?;
+        let lines: Vec<&str> = content.lines().collect();
+        Ok(items_of(&lines).into_iter().map(|b| b.item).collect())
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let content = self.read()?;
+        let lines: Vec<&str> = content.lines().collect();
+        let blocks = items_of(&lines);
+
+        // Notes that follow a nested item go with their own item's lines
+        // when it is rewritten or removed.
+        let dropped: Vec<usize> = blocks
+            .iter()
+            .filter(|b| !items.contains(&b.item))
+            .flat_map(|b| b.later_notes.iter().copied())
+            .collect();
+        let verbatim = |from: usize, to: usize| {
+            (from..to)
+                .filter(|i| !dropped.contains(i))
+                .map(|i| lines[i].to_string())
+        };
+
+        let mut out: Vec<String> = Vec::new();
+        let mut cursor = 0;
+        for block in &blocks {
+            out.extend(verbatim(cursor, block.start));
+            cursor = block.end;
+
+            let Some(item) = items.iter().find(|i| i.id == block.item.id) else {
+                continue;
+            };
+            let old = &lines[block.start..block.end];
+            if *item == block.item {
+                out.extend(old.iter().map(|l| l.to_string()));
+            } else {
+                out.extend(render(item, old[0]));
+            }
+        }
+
+        let new_items = items
+            .iter()
+            .filter(|item| !blocks.iter().any(|b| b.item.id == item.id));
+        let rendered: Vec<String> = new_items.flat_map(|item| render(item, "-")).collect();
+        if blocks.is_empty() {
+            out.extend(lines.iter().map(|l| l.to_string()));
+            if !rendered.is_empty() && out.last().is_some_and(|l| !l.trim().is_empty()) {
+                out.push(String::new());
+            }
+            out.extend(rendered);
+        } else {
+            out.extend(rendered);
+            out.extend(verbatim(cursor, lines.len()));
+        }
+
+        let mut text = out.join("\n");
+        text.push('\n');
+        fs::write(&self.path, text).map_err(|e| TodoError::io(&self.path, e))
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        let mut items = self.load_items()?;
+        items.push(item);
+        self.save_items(&items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const TODO_MD: &str = "# Project\n\
+                           \n\
+                           Some *prose* that - [ ] mentions a box.\n\
+                           \n\
+                           ## Tasks\n\
+                           \n\
+                           * [ ] Write docs #docs <!-- id:00000001 -->\n\
+                           \x20 - start with the README\n\
+                           - [x] Ship 1.0\n\
+                           \n\
+                           ```\n\
+                           - [ ] not a task\n\
+                           ```\n\
+                           \n\
+                           Thanks!\n";
+
+    fn storage_with(content: &str) -> (NamedTempFile, MarkdownStorage) {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", content).unwrap();
+        let storage = MarkdownStorage::new(file.path().to_str().unwrap());
+        (file, storage)
+    }
+
+    #[test]
+    fn loads_checklist_items_only() {
+        let (_file, storage) = storage_with(TODO_MD);
+        let items = storage.load_items().unwrap();
+
+        let descriptions: Vec<_> = items.iter().map(|i| i.description.as_str()).collect();
+        assert_eq!(descriptions, vec!["Write docs", "Ship 1.0"]);
+        assert_eq!(items[0].id, "00000001");
+        assert_eq!(items[0].notes.as_deref(), Some("start with the README"));
+        assert!(items[1].done);
+    }
+
+    #[test]
+    fn saving_rewrites_only_changed_items() {
+        let (file, storage) = storage_with(TODO_MD);
+        let mut items = storage.load_items().unwrap();
+        items[0].done = true;
+        items[0].notes = None;
+        let shipped = items.remove(1);
+        items.push(TodoItem {
+            id: "00000003".into(),
+            description: "Announce".into(),
+            priority: Some(1),
+            created: Some(1_735_776_000),
+            ..TodoItem::default()
+        });
+        storage.save_items(&items).unwrap();
+
+        let expected = TODO_MD
+            .replace(
+                "* [ ] Write docs #docs <!-- id:00000001 -->\n  - start with the README\n",
+                "* [x] Write docs #docs <!-- id:00000001 -->\n",
+            )
+            .replace(
+                "- [x] Ship 1.0\n",
+                "- [ ] Announce !1 <!-- id:00000003 created:1735776000 -->\n",
+            );
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), expected);
+        assert!(!storage.load_items().unwrap().contains(&shipped));
+    }
+
+    #[test]
+    fn only_changed_items_gain_an_id_comment() {
+        let (file, storage) = storage_with("Intro\n\n- [ ] One\n- [ ] Two\n");
+        let mut items = storage.load_items().unwrap();
+        storage.save_items(&items).unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "Intro\n\n- [ ] One\n- [ ] Two\n"
+        );
+
+        items[1].done = true;
+        storage.save_items(&items).unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            format!(
+                "Intro\n\n- [ ] One\n- [x] Two <!-- id:{} -->\n",
+                items[1].id
+            )
+        );
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn notes_after_a_nested_item_move_with_their_parent() {
+        let (file, storage) =
+            storage_with("- [ ] Top\n  - [ ] Nested child\n  - plain note\nEnd\n");
+        let mut items = storage.load_items().unwrap();
+        assert_eq!(items[0].notes.as_deref(), Some("plain note"));
+
+        items[0].done = true;
+        storage.save_items(&items).unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            format!(
+                "- [x] Top <!-- id:{} -->\n  - plain note\n  - [ ] Nested child\nEnd\n",
+                items[0].id
+            )
+        );
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn adds_after_prose_when_there_is_no_list() {
+        let (file, storage) = storage_with("# TODO\n");
+        let item = TodoItem {
+            id: "00000001".into(),
+            description: "First".into(),
+            notes: Some("details".into()),
+            ..TodoItem::default()
+        };
+        storage.add_item(item.clone()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "# TODO\n\n- [ ] First <!-- id:00000001 -->\n  - details\n"
+        );
+        assert_eq!(storage.load_items().unwrap(), vec![item]);
+    }
+}
@@ -0,0 +1,428 @@
+//! The data model and the backends that store it.
+
+mod dir;
+pub mod encrypted;
+pub mod history;
+pub mod hooks;
+mod markdown;
+mod todotxt;
+
+pub use dir::DirStorage;
+pub use encrypted::{EncryptedStorage, KeySource};
+pub use history::GitHistory;
+pub use hooks::HookedStorage;
+pub use markdown::MarkdownStorage;
+pub use todotxt::TodoTxtStorage;
+
+use crate::config::{Config, History};
+use crate::error::{Result, TodoError};
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One task on the list, as stored in `todo.json`.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct TodoItem {
+    /// Short hex identifier that survives reordering and edits.
+    #[serde(default)]
+    pub id: String,
+    pub description: String,
+    pub priority: Option<u8>,
+    pub due: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub done: bool,
+    pub notes: Option<String>,
+    /// Seconds since the Unix epoch; `None` for items added before it was tracked.
+    pub created: Option<u64>,
+}
+
+/// A fresh random 8-hex-digit ID for a new item.
+pub fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    hasher.write_u128(nanos);
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u32(std::process::id());
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+/// Seconds since the Unix epoch, as stored in `TodoItem::created`.
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Gives items written before IDs existed one derived from their description
+/// and how many ID-less items before them share it. Repeated loads of an old
+/// file agree until it is next saved, and so do two versions of it, which
+/// lets them be merged even after items were added or removed.
+pub fn assign_missing_ids(items: &mut [TodoItem]) {
+    let mut seen: Vec<&str> = Vec::new();
+    let mut ids = Vec::new();
+    for item in items.iter() {
+        if item.id.is_empty() {
+            let occurrence = seen.iter().filter(|d| **d == item.description).count();
+            seen.push(&item.description);
+            ids.push(Some(legacy_id(occurrence, &item.description)));
+        } else {
+            ids.push(None);
+        }
+    }
+    for (item, id) in items.iter_mut().zip(ids) {
+        if let Some(id) = id {
+            item.id = id;
+        }
+    }
+}
+
+// FNV-1a: unlike `DefaultHasher`, guaranteed not to change between builds.
+fn legacy_id(occurrence: usize, description: &str) -> String {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in (occurrence as u64)
+        .to_le_bytes()
+        .iter()
+        .chain(description.as_bytes())
+    {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    format!("{:08x}", hash)
+}
+
+/// Where a todo list is kept.
+#[automock]
+pub trait Storage {
+    /// The whole list, in order.
+    fn load_items(&self) -> Result<Vec<TodoItem>>;
+    /// Replaces the whole list with `items`.
+    fn save_items(&self, items: &[TodoItem]) -> Result<()>;
+    /// Appends `item` to the list.
+    fn add_item(&self, item: TodoItem) -> Result<()>;
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        (**self).load_items()
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        (**self).save_items(items)
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        (**self).add_item(item)
+    }
+}
+
+/// The file formats the list can be kept in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    #[default]
+    Json,
+    TodoTxt,
+    Markdown,
+    Dir,
+}
+
+impl Backend {
+    pub fn default_path(self) -> &'static str {
+        match self {
+            Backend::Json => "todo.json",
+            Backend::TodoTxt => "todo.txt",
+            Backend::Markdown => "TODO.md",
+            Backend::Dir => ".todo",
+        }
+    }
+
+    pub fn open(self, path: &str) -> Box<dyn Storage> {
+        match self {
+            Backend::Json => Box::new(FileStorage::new(path)),
+            Backend::TodoTxt => Box::new(TodoTxtStorage::new(path)),
+            Backend::Markdown => Box::new(MarkdownStorage::new(path)),
+            Backend::Dir => Box::new(DirStorage::new(path)),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Backend::Json),
+            "todotxt" => Ok(Backend::TodoTxt),
+            "markdown" => Ok(Backend::Markdown),
+            "dir" => Ok(Backend::Dir),
+            other => Err(format!(
+                "unknown storage backend '{}': expected json, todotxt, markdown or dir",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Backend::Json => "json",
+            Backend::TodoTxt => "todotxt",
+            Backend::Markdown => "markdown",
+            Backend::Dir => "dir",
+        })
+    }
+}
+
+/// Opens the list the `storage` section of `config` describes, recording
+/// each change in git when `storage.history = "git"`, decrypting it if it has
+/// been encrypted and running the hooks in `storage.hooks`. While a
+/// `todo daemon` is running, the list is read and written through it instead.
+pub fn open(config: &Config) -> Result<Box<dyn Storage>> {
+    let direct = open_direct(config)?;
+    #[cfg(unix)]
+    {
+        let socket = crate::daemon::socket_path(&config.storage.path());
+        Ok(Box::new(crate::daemon::DaemonStorage::new(socket, direct)))
+    }
+    #[cfg(not(unix))]
+    Ok(direct)
+}
+
+/// Like [`open`], but never through a daemon; what a daemon itself serves.
+pub fn open_direct(config: &Config) -> Result<Box<dyn Storage>> {
+    let encrypted = open_encrypted(config)?;
+    match config.storage.hooks_dir().filter(|dir| dir.is_dir()) {
+        Some(dir) => Ok(Box::new(HookedStorage::new(encrypted, dir))),
+        None => Ok(Box::new(encrypted)),
+    }
+}
+
+/// Like [`open`], but as the encryption layer itself so that the list can
+/// be encrypted or decrypted.
+pub fn open_encrypted(config: &Config) -> Result<EncryptedStorage<Box<dyn Storage>>> {
+    let path = config.storage.path();
+    let storage = config.storage.backend.open(&path);
+    let storage: Box<dyn Storage> = match config.storage.history {
+        History::Git => Box::new(GitHistory::new(storage, &path)),
+        History::Off => storage,
+    };
+    Ok(EncryptedStorage::new(storage, &path, KeySource::from_env()))
+}
+
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+impl Storage for FileStorage {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        let file = match OpenOptions::new().read(true).open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                // Treat as empty store
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(TodoError::io(&self.path, e)),
+        };
+
+        // make sure we handle empty files gracefully
+        let metadata = file.metadata().map_err(|e| TodoError::io(&self.path, e))?;
+        if metadata.len() == 0 {
+            return Ok(vec![]);
+        }
+
+        let reader = BufReader::new(file);
+        let mut items: Vec<TodoItem> =
+            serde_json::from_reader(reader).map_err(|e| TodoError::json(&self.path, e))?;
+        assign_missing_ids(&mut items);
+        Ok(items)
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(items).map_err(|e| TodoError::json(&self.path, e))?;
+
+        // Write then rename, so a crash never leaves half a list behind.
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = self.path.with_file_name(format!(".{}.tmp", name));
+        fs::write(&tmp, json).map_err(|e| TodoError::io(&tmp, e))?;
+        fs::rename(&tmp, &self.path).map_err(|e| TodoError::io(&self.path, e))
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        let mut items = self.load_items()?;
+        items.push(item);
+        self.save_items(&items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_load_non_existent_file() {
+        let storage = FileStorage::new("does_not_exist.json");
+        let items = storage.load_items().unwrap();
+        assert_eq!(items.len(), 0);
+    }
+
+    #[test]
+    fn test_load_empty_file() {
+        let file = NamedTempFile::new().unwrap();
+        let storage = FileStorage::new(file.path().to_str().unwrap());
+        let items = storage.load_items().unwrap();
+        assert_eq!(items.len(), 0);
+    }
+
+    #[test]
+    fn test_save_and_load_single_item() {
+        let file = NamedTempFile::new().unwrap();
+        let storage = FileStorage::new(file.path().to_str().unwrap());
+
+        let todo = TodoItem {
+            id: "0a1b2c3d".to_string(),
+            description: "Test".to_string(),
+            priority: Some(1),
+            due: Some("2021-01-01".to_string()),
+            tags: Some(vec!["tag1".to_string(), "tag2".to_string()]),
+            notes: Some("Notes".to_string()),
+            created: Some(1_700_000_000),
+            ..TodoItem::default()
+        };
+
+        storage.add_item(todo.clone()).unwrap();
+
+        let todos_from_storage = storage.load_items().unwrap();
+        assert_eq!(todos_from_storage.len(), 1);
+
+        let todo_from_storage = &todos_from_storage[0];
+        assert_eq!(todo_from_storage, &todo)
+    }
+
+    #[test]
+    fn test_save_and_load_multiple_items() {
+        let file = NamedTempFile::new().unwrap();
+        let storage = FileStorage::new(file.path().to_str().unwrap());
+
+        let todo1 = TodoItem {
+            id: "00000001".to_string(),
+            description: "Test 1".to_string(),
+            priority: Some(1),
+            due: Some("2021-01-01".to_string()),
+            tags: Some(vec!["first".to_string(), "todo".to_string()]),
+            notes: Some("first todo".to_string()),
+            ..TodoItem::default()
+        };
+        let todo2 = TodoItem {
+            id: "00000002".to_string(),
+            description: "Test 2".to_string(),
+            priority: Some(1),
+            due: Some("2021-02-02".to_string()),
+            tags: Some(vec!["second".to_string(), "todo".to_string()]),
+            done: true,
+            notes: Some("second todo".to_string()),
+            ..TodoItem::default()
+        };
+
+        let todos = vec![todo1, todo2];
+        storage.save_items(&todos).unwrap();
+
+        let todos_from_storage = storage.load_items().unwrap();
+        assert_eq!(todos_from_storage.len(), 2);
+        assert_eq!(todos_from_storage, todos);
+    }
+
+    #[test]
+    fn test_save_replaces_the_file_without_leftovers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        let storage = FileStorage::new(path.to_str().unwrap());
+        fs::write(&path, "[{\"description\": \"Old\", \"done\": false}]").unwrap();
+
+        let todo = TodoItem {
+            id: "00000001".to_string(),
+            description: "New".to_string(),
+            ..TodoItem::default()
+        };
+        storage.save_items(std::slice::from_ref(&todo)).unwrap();
+
+        assert_eq!(storage.load_items().unwrap(), vec![todo]);
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["todo.json"]);
+    }
+
+    #[test]
+    fn test_load_assigns_stable_ids_to_legacy_items() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"[{{"description":"old","priority":null,"due":null,"tags":null,"done":false,"notes":null}},
+               {{"description":"old","priority":null,"due":null,"tags":null,"done":false,"notes":null}}]"#
+        )
+        .unwrap();
+        let storage = FileStorage::new(file.path().to_str().unwrap());
+
+        let first = storage.load_items().unwrap();
+        let second = storage.load_items().unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first[0].id.len(), 8);
+        assert_ne!(first[0].id, first[1].id);
+        assert_eq!(first[0].created, None);
+    }
+
+    #[test]
+    fn test_load_reports_json_position() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "[\n  {{\"description\": \"x\",}}\n]").unwrap();
+        let storage = FileStorage::new(file.path().to_str().unwrap());
+
+        match storage.load_items() {
+            Err(TodoError::Parse { path, line, .. }) => {
+                assert_eq!(path, file.path());
+                assert_eq!(line, 2);
+            }
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_backend_names() {
+        assert_eq!("todotxt".parse(), Ok(Backend::TodoTxt));
+        assert_eq!(" JSON ".parse(), Ok(Backend::Json));
+        assert!("yaml".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn test_new_ids_differ() {
+        assert_ne!(new_id(), new_id());
+        assert_eq!(new_id().len(), 8);
+    }
+}
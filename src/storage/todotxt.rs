@@ -0,0 +1,230 @@
+use super::{Storage, TodoItem, assign_missing_ids};
+use crate::error::{Result, TodoError};
+use crate::formats::todotxt::{self, Task};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Keeps the list in a todo.txt file that other todo.txt tools can share.
+///
+/// Lines whose item did not change are written back byte for byte; changed
+/// ones are rewritten, keeping their completion date, `@context` sigils and
+/// any tokens we do not understand, which stay part of the description.
+/// Blank lines and lines without a task stay where they are. Only new and
+/// changed lines get an `id:`; the others keep one derived from their place.
+pub struct TodoTxtStorage {
+    path: PathBuf,
+}
+
+/// A line as it was on disk; `task` is `None` for lines that hold none.
+struct Line {
+    raw: String,
+    task: Option<Task>,
+}
+
+impl TodoTxtStorage {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn read(&self) -> Result<Vec<Line>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(TodoError::io(&self.path, e)),
+        };
+
+        let mut lines: Vec<Line> = content
+            .lines()
+            .map(|raw| Line {
+                raw: raw.to_string(),
+                task: Some(todotxt::parse_line(raw))
+                    .filter(|task| !task.item.description.is_empty()),
+            })
+            .collect();
+
+        let mut items: Vec<TodoItem> = tasks(&lines).map(|task| task.item.clone()).collect();
+        assign_missing_ids(&mut items);
+        let tasks = lines.iter_mut().filter_map(|line| line.task.as_mut());
+        for (task, item) in tasks.zip(items) {
+            task.item.id = item.id;
+        }
+        Ok(lines)
+    }
+}
+
+fn tasks(lines: &[Line]) -> impl Iterator<Item = &Task> {
+    lines.iter().filter_map(|line| line.task.as_ref())
+}
+
+/// The ID of the item each line without a task follows, `None` for those
+/// before the first task, skipping items that are no longer in `items`.
+fn anchors<'a>(lines: &'a [Line], items: &[TodoItem]) -> Vec<(Option<&'a str>, &'a str)> {
+    let mut anchor = None;
+    let mut anchored = Vec::new();
+    for line in lines {
+        match &line.task {
+            Some(task) if items.iter().any(|item| item.id == task.item.id) => {
+                anchor = Some(task.item.id.as_str())
+            }
+            Some(_) => {}
+            None => anchored.push((anchor, line.raw.as_str())),
+        }
+    }
+    anchored
+}
+
+impl Storage for TodoTxtStorage {
+    fn load_items(&self) -> Result<Vec<TodoItem>> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .filter_map(|line| line.task.map(|task| task.item))
+            .collect())
+    }
+
+    fn save_items(&self, items: &[TodoItem]) -> Result<()> {
+        let existing = self.read()?;
+        let anchored = anchors(&existing, items);
+        let today = chrono::Local::now().date_naive();
+        let mut out = String::new();
+        let push_anchored = |out: &mut String, anchor: Option<&str>| {
+            for (_, raw) in anchored.iter().filter(|(a, _)| *a == anchor) {
+                out.push_str(raw);
+                out.push('\n');
+            }
+        };
+
+        push_anchored(&mut out, None);
+        for item in items {
+            let old = existing
+                .iter()
+                .find(|line| line.task.as_ref().is_some_and(|t| t.item.id == item.id));
+            let line = match old.and_then(|line| line.task.as_ref().map(|task| (line, task))) {
+                Some((line, task)) if task.item == *item => line.raw.clone(),
+                Some((_, task)) => {
+                    let completed = if task.item.done {
+                        task.completed
+                    } else {
+                        Some(today)
+                    };
+                    todotxt::write_line(item, completed.filter(|_| item.done), &task.contexts)
+                }
+                None => todotxt::write_line(item, Some(today).filter(|_| item.done), &[]),
+            };
+            out.push_str(&line);
+            out.push('\n');
+            push_anchored(&mut out, Some(&item.id));
+        }
+
+        fs::write(&self.path, out).map_err(|e| TodoError::io(&self.path, e))
+    }
+
+    fn add_item(&self, item: TodoItem) -> Result<()> {
+        let mut items = self.load_items()?;
+        items.push(item);
+        self.save_items(&items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn storage_with(content: &str) -> (NamedTempFile, TodoTxtStorage) {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", content).unwrap();
+        let storage = TodoTxtStorage::new(file.path().to_str().unwrap());
+        (file, storage)
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        let storage = TodoTxtStorage::new("does_not_exist.txt");
+        assert!(storage.load_items().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unchanged_lines_and_lines_without_a_task_are_kept() {
+        let content = "# chores\n\
+                       (A) Call Mom @phone rec:1w id:00000001\n\
+                       \n\
+                       x 2025-01-01\n\
+                       x 2025-01-05 2025-01-01 Pay  rent +home\n";
+        let (file, storage) = storage_with(content);
+
+        let items = storage.load_items().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[1].description, "Call Mom rec:1w");
+        storage.save_items(&items).unwrap();
+
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), content);
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn lines_without_a_task_follow_the_task_before_them() {
+        let (file, storage) = storage_with(
+            "First id:00000001\n\
+             \n\
+             Second id:00000002\n\
+             x 2025-01-01\n\
+             Third id:00000003\n",
+        );
+
+        let mut items = storage.load_items().unwrap();
+        items.remove(1);
+        items.reverse();
+        storage.save_items(&items).unwrap();
+
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "Third id:00000003\nFirst id:00000001\n\nx 2025-01-01\n"
+        );
+    }
+
+    #[test]
+    fn edited_lines_keep_contexts_unknown_tokens_and_order() {
+        let (file, storage) = storage_with(
+            "Call Mom @phone t:2025-01-01 id:00000001\n\
+             x 2025-01-05 Pay rent id:00000002\n\
+             Water plants id:00000003\n",
+        );
+
+        let mut items = storage.load_items().unwrap();
+        items[0].done = true;
+        items[0].priority = Some(2);
+        items[1].notes = Some("via bank".into());
+        items.remove(2);
+        storage.save_items(&items).unwrap();
+
+        let today = chrono::Local::now().date_naive();
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "x {} Call Mom t:2025-01-01 @phone pri:C id:00000001\n\
+                 x 2025-01-05 Pay rent note:via%20bank id:00000002\n",
+                today
+            )
+        );
+        assert_eq!(storage.load_items().unwrap(), items);
+    }
+
+    #[test]
+    fn add_appends_a_line() {
+        let (file, storage) = storage_with("First id:00000001\n");
+        let mut item = storage.load_items().unwrap().remove(0);
+        item.id = "00000002".into();
+        item.description = "Second".into();
+        storage.add_item(item).unwrap();
+
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "First id:00000001\nSecond id:00000002\n");
+    }
+}
@@ -0,0 +1,37 @@
+use crix_todo::config::ColorMode;
+use std::io::IsTerminal;
+
+pub const BOLD: &str = "\x1b[1m";
+pub const DIM: &str = "\x1b[2m";
+pub const RESET: &str = "\x1b[0m";
+
+/// Whether stdout should get ANSI styling. In `auto` mode, only when it is a
+/// terminal and the user has not opted out via a non-empty `NO_COLOR`
+/// (https://no-color.org).
+pub fn enabled(mode: ColorMode) -> bool {
+    match mode {
+        ColorMode::Always => return true,
+        ColorMode::Never => return false,
+        ColorMode::Auto => {}
+    }
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !no_color && std::io::stdout().is_terminal()
+}
+
+/// Width of the terminal stdout is attached to, or `None` when piped.
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size()
+        .ok()
+        .map(|(columns, _)| columns as usize)
+}
+
+pub fn paint(text: &str, code: &str, styled: bool) -> String {
+    if styled && !code.is_empty() {
+        format!("{}{}{}", code, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
@@ -1,10 +1,31 @@
-use crate::storage::{Storage, TodoItem};
+use crate::tui::keymap::{self, Keymap};
 use crate::tui::state::edit_buffer::EditBuffer;
+use crate::tui::state::field_buffer::{FieldBuffer, FieldMode};
+use crate::tui::state::history::History;
+use crix_todo::config::{Config, Preset};
+use crix_todo::error::Result;
+use crix_todo::merge::{self, Side};
+use crix_todo::search;
+use crix_todo::sort::SortMode;
+use crix_todo::storage::{self, Storage, TodoItem};
+use std::time::{Duration, Instant};
+
+/// How long an informational notice stays on screen.
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
 
 #[derive(PartialEq, Debug)]
 pub enum InputMode {
     Normal,
     Editing,
+    Searching,
+}
+
+/// A one-line message shown at the bottom of the list.
+#[derive(PartialEq, Debug)]
+pub enum Status {
+    Error(String),
+    /// Goes away by itself after a few seconds.
+    Info(String),
 }
 
 pub struct App {
@@ -14,17 +35,25 @@ pub struct App {
     pub expanded: Option<usize>,
     pub mode: InputMode,
     pub edit_buffer: Option<EditBuffer>,
+    pub search: Option<FieldBuffer>,
+    pub search_origin: usize,
+    pub sort_mode: SortMode,
+    pub status: Option<Status>,
+    /// When an `Info` status should disappear.
+    pub status_until: Option<Instant>,
+    /// The list as last loaded or saved, to merge outside changes against.
+    pub on_disk: Vec<TodoItem>,
+    pub config: Config,
+    pub keymap: Keymap,
+    /// Earlier versions of the list since it was last reloaded.
+    pub history: History,
 }
 
 impl App {
     pub fn new(todos: Vec<TodoItem>) -> Self {
-        let mut priority_sorted = todos
-            .iter()
-            .enumerate()
-            .collect::<Vec<(usize, &TodoItem)>>();
-
-        priority_sorted.sort_by_key(|(_, t)| t.priority.unwrap_or(99));
-        let visual_order = priority_sorted.into_iter().map(|(i, _)| i).collect();
+        let sort_mode = SortMode::default();
+        let visual_order = sort_mode.spec().order(&todos);
+        let todos_on_disk = todos.clone();
 
         Self {
             todos,
@@ -33,9 +62,25 @@ impl App {
             expanded: None,
             mode: InputMode::Normal,
             edit_buffer: None,
+            search: None,
+            search_origin: 0,
+            sort_mode,
+            status: None,
+            status_until: None,
+            on_disk: todos_on_disk,
+            config: Config::default(),
+            keymap: Keymap::default(),
+            history: History::default(),
         }
     }
 
+    /// Fails if the configured keys conflict.
+    pub fn with_config(mut self, config: Config) -> Result<Self> {
+        self.keymap = Keymap::new(&config.keys)?;
+        self.config = config;
+        Ok(self)
+    }
+
     pub fn next(&mut self) {
         match self.mode {
             InputMode::Normal => {
@@ -44,13 +89,14 @@ impl App {
                 }
             }
             InputMode::Editing => {
-                if let Some(buf) = self.edit_buffer.as_mut() {
-                    if buf.selected_field + 1 < 5 {
-                        buf.fields[buf.selected_field].reset_cursor();
-                        buf.selected_field += 1;
-                    }
+                if let Some(buf) = self.edit_buffer.as_mut()
+                    && buf.selected_field + 1 < 5
+                {
+                    buf.fields[buf.selected_field].reset_cursor();
+                    buf.selected_field += 1;
                 }
             }
+            InputMode::Searching => self.next_match(),
         }
     }
 
@@ -62,13 +108,14 @@ impl App {
                 }
             }
             InputMode::Editing => {
-                if let Some(buf) = self.edit_buffer.as_mut() {
-                    if buf.selected_field > 0 {
-                        buf.fields[buf.selected_field].reset_cursor();
-                        buf.selected_field -= 1;
-                    }
+                if let Some(buf) = self.edit_buffer.as_mut()
+                    && buf.selected_field > 0
+                {
+                    buf.fields[buf.selected_field].reset_cursor();
+                    buf.selected_field -= 1;
                 }
             }
+            InputMode::Searching => self.previous_match(),
         }
     }
 
@@ -86,6 +133,7 @@ impl App {
 
     pub fn toggle_done(&mut self) {
         if let Some(&actual_index) = self.visual_order.get(self.selected) {
+            self.history.record(&self.todos);
             self.todos[actual_index].done = !self.todos[actual_index].done;
         }
     }
@@ -112,17 +160,143 @@ impl App {
         }
     }
 
-    pub fn save(&self, storage: &impl Storage) {
-        if let Err(e) = storage.save_items(&self.todos) {
-            eprintln!("Failed to save todos: {}", e);
+    /// Applies a cursor motion or edit to the field being edited.
+    pub fn edit_field(&mut self, change: impl FnOnce(&mut FieldBuffer)) {
+        if let Some(buf) = self.edit_buffer.as_mut() {
+            change(buf.current_field_mut());
+        }
+    }
+
+    /// Switches the edit modal between typing and moving around. Like vim,
+    /// leaving insert mode steps back onto the last char typed.
+    pub fn set_field_mode(&mut self, mode: FieldMode) {
+        if let Some(buf) = self.edit_buffer.as_mut() {
+            if mode == FieldMode::Normal && buf.mode == FieldMode::Insert {
+                buf.current_field_mut().move_left();
+            }
+            buf.mode = mode;
+        }
+    }
+
+    /// Which keys apply right now, or `None` while typing a search.
+    pub fn keymap_mode(&self) -> Option<keymap::Mode> {
+        match self.mode {
+            InputMode::Normal => Some(keymap::Mode::Normal),
+            InputMode::Editing => match self.edit_buffer.as_ref().map(|buf| buf.mode) {
+                Some(FieldMode::Normal) => Some(keymap::Mode::EditNormal),
+                _ => Some(keymap::Mode::Edit),
+            },
+            InputMode::Searching => None,
+        }
+    }
+
+    /// Persists the list. A failure is also kept in `status` so it can be
+    /// shown on screen; a later successful save clears it.
+    pub fn save(&mut self, storage: &impl Storage) -> Result<()> {
+        let result = storage.save_items(&self.todos);
+        match &result {
+            Ok(()) => {
+                self.on_disk = self.todos.clone();
+                if matches!(self.status, Some(Status::Error(_))) {
+                    self.status = None;
+                }
+            }
+            Err(e) => self.status = Some(Status::Error(format!("Save failed: {}", e))),
+        }
+        result
+    }
+
+    /// Takes in a list that was changed outside the TUI. Changes made here
+    /// and not yet saved are merged in, and win where both changed the same
+    /// field. The selection and expanded item stay on the same items.
+    pub fn reload(&mut self, disk: Vec<TodoItem>, now: Instant) {
+        let merged = merge::merge(&self.on_disk, &self.todos, &disk);
+        let conflicts = merged.conflicts();
+        self.set_todos(merged.resolve(Side::Ours));
+        self.on_disk = disk;
+        // Undoing past this point would also undo the outside changes.
+        self.history.clear();
+
+        let message = match conflicts {
+            0 => "Reloaded: the file changed on disk".to_string(),
+            n => format!(
+                "Reloaded: kept your edits to {} item{} also changed on disk",
+                n,
+                if n == 1 { "" } else { "s" }
+            ),
+        };
+        self.show_info(message, now);
+    }
+
+    /// Replaces the list, keeping the selection and expanded item on the
+    /// same items where they are still there.
+    fn set_todos(&mut self, todos: Vec<TodoItem>) {
+        let id_at = |app: &App, idx: Option<usize>| idx.map(|i| app.todos[i].id.clone());
+        let selected = id_at(self, self.visual_order.get(self.selected).copied());
+        let expanded = id_at(self, self.expanded);
+
+        self.todos = todos;
+        let index_of = |app: &App, id: Option<String>| {
+            id.and_then(|id| app.todos.iter().position(|t| t.id == id))
+        };
+        self.expanded = index_of(self, expanded);
+        self.visual_order = self.sort_mode.spec().order(&self.todos);
+        self.selected = index_of(self, selected)
+            .and_then(|idx| self.visual_order.iter().position(|&i| i == idx))
+            .unwrap_or_else(|| self.selected.min(self.visual_order.len().saturating_sub(1)));
+    }
+
+    pub fn undo(&mut self, now: Instant) {
+        match self.history.undo(&self.todos) {
+            Some(todos) => self.set_todos(todos),
+            None => self.show_info("Nothing to undo".to_string(), now),
+        }
+    }
+
+    pub fn redo(&mut self, now: Instant) {
+        match self.history.redo(&self.todos) {
+            Some(todos) => self.set_todos(todos),
+            None => self.show_info("Nothing to redo".to_string(), now),
+        }
+    }
+
+    /// Takes in the list as it was actually saved, after hooks changed or
+    /// refused some of the changes.
+    pub fn replace_with_saved(&mut self, saved: Vec<TodoItem>, now: Instant) {
+        // Everything here was just handed to storage, so nothing of ours
+        // should win over what it kept.
+        self.on_disk = self.todos.clone();
+        self.reload(saved, now);
+        self.show_info("Saved, with changes made by hooks".to_string(), now);
+    }
+
+    pub fn show_info(&mut self, message: String, now: Instant) {
+        // An unresolved error is more important than a notice.
+        if !matches!(self.status, Some(Status::Error(_))) {
+            self.status = Some(Status::Info(message));
+            self.status_until = Some(now + NOTICE_DURATION);
+        }
+    }
+
+    /// Clears an `Info` status once its time is up.
+    pub fn expire_status(&mut self, now: Instant) {
+        if matches!(self.status, Some(Status::Info(_)))
+            && self.status_until.is_some_and(|until| now >= until)
+        {
+            self.status = None;
+            self.status_until = None;
         }
     }
 
     pub fn toggle_mode(&mut self) {
         if self.mode == InputMode::Normal {
             let idx = self.visual_order[self.selected];
-            let todo = &self.todos[idx];
-            self.edit_buffer = Some(EditBuffer::new(todo));
+            let mut buffer = EditBuffer::new(&self.todos[idx]);
+            if self.config.keys.preset == Preset::Vim {
+                buffer.mode = FieldMode::Normal;
+                buffer.current_field_mut().move_to_start();
+            }
+            self.edit_buffer = Some(buffer);
             self.mode = InputMode::Editing;
         } else {
             self.mode = InputMode::Normal;
@@ -132,21 +306,71 @@ impl App {
     }
 
     fn commit_edit(&mut self) {
-        if let Some(buf) = &self.edit_buffer {
-            if let Some(&idx) = self.visual_order.get(self.selected) {
-                let todo = &mut self.todos[idx];
-                buf.update_todo(todo);
-                self.recompute_visual_order(idx)
+        if let Some(buf) = &self.edit_buffer
+            && let Some(&idx) = self.visual_order.get(self.selected)
+        {
+            let mut edited = self.todos[idx].clone();
+            buf.update_todo(&mut edited);
+            if buf.is_new && edited.description.trim().is_empty() {
+                // nothing was written, so there is nothing to add or undo
+                self.history.discard_last();
+                self.remove_at(idx);
+                return;
+            }
+            if !buf.is_new && edited != self.todos[idx] {
+                self.history.record(&self.todos);
             }
+            self.todos[idx] = edited;
+            self.recompute_visual_order(idx)
+        }
+    }
+
+    /// Adds an empty item just below the selected one, with the same
+    /// priority so that it sorts next to it, and opens it for editing.
+    pub fn add_below(&mut self) {
+        self.history.record(&self.todos);
+        let selected = self.visual_order.get(self.selected).copied();
+        let idx = selected.map_or(self.todos.len(), |i| i + 1);
+        let item = TodoItem {
+            id: storage::new_id(),
+            description: String::new(),
+            priority: selected.and_then(|i| self.todos[i].priority),
+            due: None,
+            tags: None,
+            done: false,
+            notes: None,
+            created: Some(storage::now_timestamp()),
+        };
+        self.todos.insert(idx, item);
+        if let Some(expanded) = self.expanded
+            && expanded >= idx
+        {
+            self.expanded = Some(expanded + 1);
+        }
+        self.recompute_visual_order(idx);
+
+        let mut buffer = EditBuffer::new(&self.todos[idx]);
+        buffer.is_new = true;
+        self.edit_buffer = Some(buffer);
+        self.mode = InputMode::Editing;
+    }
+
+    /// Selects the item at `pos` in the list, or the last one.
+    pub fn go_to(&mut self, pos: usize) {
+        self.selected = pos.min(self.visual_order.len().saturating_sub(1));
+    }
+
+    pub fn cycle_sort(&mut self) {
+        self.sort_mode = self.sort_mode.next();
+        match self.visual_order.get(self.selected) {
+            Some(&idx) => self.recompute_visual_order(idx),
+            None => self.visual_order = self.sort_mode.spec().order(&self.todos),
         }
     }
 
     fn recompute_visual_order(&mut self, edited_idx: usize) {
         // Re-sort
-        let mut pairs: Vec<(usize, &TodoItem)> = self.todos.iter().enumerate().collect();
-        pairs.sort_by_key(|(_, t)| t.priority.unwrap_or(99));
-
-        self.visual_order = pairs.into_iter().map(|(i, _)| i).collect();
+        self.visual_order = self.sort_mode.spec().order(&self.todos);
 
         // Where did the edited tod0 land?
         if let Some(pos) = self.visual_order.iter().position(|&i| i == edited_idx) {
@@ -158,6 +382,39 @@ impl App {
 
     pub fn remove_selected(&mut self) {
         if let Some(&idx) = self.visual_order.get(self.selected) {
+            self.history.record(&self.todos);
+            self.remove_at(idx);
+        }
+    }
+
+    /// Removes the selected item and the `count - 1` below it in one undo
+    /// step, as vim's `3dd` does, keeping the cursor on the same line.
+    pub fn remove_below(&mut self, count: usize) {
+        let end = self
+            .selected
+            .saturating_add(count)
+            .min(self.visual_order.len());
+        if self.selected >= end {
+            return;
+        }
+        self.history.record(&self.todos);
+        let mut removed = self
+            .visual_order
+            .drain(self.selected..end)
+            .collect::<Vec<_>>();
+        removed.sort_unstable();
+        for &idx in removed.iter().rev() {
+            self.todos.remove(idx);
+        }
+        for i in self.visual_order.iter_mut() {
+            *i -= removed.partition_point(|&r| r < *i);
+        }
+        self.selected = self.selected.min(self.visual_order.len().saturating_sub(1));
+    }
+
+    fn remove_at(&mut self, idx: usize) {
+        if let Some(pos) = self.visual_order.iter().position(|&i| i == idx) {
+            self.selected = pos;
             self.todos.remove(idx);
             self.visual_order.remove(self.selected);
             for i in self.visual_order.iter_mut() {
@@ -173,6 +430,7 @@ impl App {
     }
     pub fn promote_selected(&mut self) {
         let idx = self.visual_order[self.selected];
+        self.history.record(&self.todos);
         let new_priority = match self.todos[idx].priority {
             Some(p) if p > 0 => Some(p - 1),
             Some(_) => Some(0), // already zero
@@ -185,9 +443,10 @@ impl App {
 
     pub fn demote_selected(&mut self) {
         let idx = self.visual_order[self.selected];
+        self.history.record(&self.todos);
         let new_priority = match self.todos[idx].priority {
-            // 99 == None
-            Some(p) if p < 98 => Some(p + 1),
+            // one past the lowest priority means none
+            Some(p) if p < self.config.priority.lowest => Some(p + 1),
             _ => None,
         };
 
@@ -195,6 +454,69 @@ impl App {
         self.recompute_visual_order(idx);
     }
 
+    pub fn start_search(&mut self) {
+        self.search = Some(FieldBuffer::new(String::new()));
+        self.search_origin = self.selected;
+        self.mode = InputMode::Searching;
+    }
+
+    pub fn search_insert(&mut self, ch: char) {
+        if let Some(query) = self.search.as_mut() {
+            query.insert_char(ch);
+            self.refresh_search();
+        }
+    }
+
+    pub fn search_backspace(&mut self) {
+        if let Some(query) = self.search.as_mut() {
+            query.backspace();
+            self.refresh_search();
+        }
+    }
+
+    /// Leaves search mode on the current match; the query is kept so `n`
+    /// can keep jumping through the results.
+    pub fn confirm_search(&mut self) {
+        self.mode = InputMode::Normal;
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.selected = self.search_origin;
+        self.search = None;
+        self.mode = InputMode::Normal;
+    }
+
+    pub fn next_match(&mut self) {
+        let len = self.visual_order.len();
+        if let Some(pos) = self.find_match((1..=len).map(|step| (self.selected + step) % len)) {
+            self.selected = pos;
+        }
+    }
+
+    pub fn previous_match(&mut self) {
+        let len = self.visual_order.len();
+        if let Some(pos) = self.find_match((1..=len).map(|step| (self.selected + len - step) % len))
+        {
+            self.selected = pos;
+        }
+    }
+
+    // Incremental search restarts from where `/` was pressed so that
+    // refining the query never skips past an earlier match.
+    fn refresh_search(&mut self) {
+        let len = self.visual_order.len();
+        let origin = self.search_origin;
+        self.selected = self
+            .find_match((0..len).map(|step| (origin + step) % len))
+            .unwrap_or(origin);
+    }
+
+    fn find_match(&self, mut positions: impl Iterator<Item = usize>) -> Option<usize> {
+        let query = self.search.as_ref()?.value.as_str();
+        positions
+            .find(|&pos| search::match_item(&self.todos[self.visual_order[pos]], query).is_some())
+    }
+
     pub fn get_last_non_none_priority(&mut self) -> Option<u8> {
         self.visual_order
             .iter()
@@ -207,7 +529,8 @@ impl App {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::MockStorage;
+    use crix_todo::error::TodoError;
+    use crix_todo::storage::MockStorage;
     use mockall::predicate::eq;
 
     #[test]
@@ -228,11 +551,11 @@ mod tests {
 
         app.toggle_done();
         let completed_todo = app.todos[0].clone();
-        assert_eq!(completed_todo.done, true);
+        assert!(completed_todo.done);
 
         app.toggle_done();
         let incomplete_todo = app.todos[0].clone();
-        assert_eq!(incomplete_todo.done, false);
+        assert!(!incomplete_todo.done);
     }
 
     #[test]
@@ -251,7 +574,7 @@ mod tests {
 
     #[test]
     fn test_save() {
-        let app = App::new(vec![make_todo("1")]);
+        let mut app = App::new(vec![make_todo("1")]);
         assert_eq!(app.selected, 0);
 
         let mut storage = MockStorage::new();
@@ -261,7 +584,29 @@ mod tests {
             .times(1)
             .returning(|_| Ok(()));
 
-        app.save(&storage)
+        assert!(app.save(&storage).is_ok());
+        assert_eq!(app.status, None);
+    }
+
+    #[test]
+    fn failed_save_sets_error_status_until_next_success() {
+        let mut app = App::new(vec![make_todo("1")]);
+
+        let mut failing = MockStorage::new();
+        failing
+            .expect_save_items()
+            .times(1)
+            .returning(|_| Err(TodoError::InvalidInput("disk full".into())));
+        assert!(app.save(&failing).is_err());
+        assert_eq!(
+            app.status,
+            Some(Status::Error("Save failed: disk full".into()))
+        );
+
+        let mut working = MockStorage::new();
+        working.expect_save_items().times(1).returning(|_| Ok(()));
+        assert!(app.save(&working).is_ok());
+        assert_eq!(app.status, None);
     }
 
     #[test]
@@ -378,7 +723,7 @@ mod tests {
 
         // visual_order should be rebuilt and reindexed correctly
         assert_eq!(app.visual_order.len(), 2);
-        assert_eq!(app.visual_order.iter().all(|&i| i < app.todos.len()), true);
+        assert!(app.visual_order.iter().all(|&i| i < app.todos.len()));
 
         // selected index should be clamped to 0
         assert_eq!(app.selected, 0);
@@ -442,6 +787,37 @@ mod tests {
         assert_eq!(app.selected, 0);
     }
 
+    #[test]
+    fn remove_below_deletes_a_counted_run_and_keeps_the_cursor() {
+        let mut app = App::new(
+            ["a", "b", "c", "d", "e"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, desc)| todo_with(desc, Some(i as u8)))
+                .collect(),
+        );
+        app.selected = 1;
+        app.remove_below(3);
+
+        let shown = |app: &App| {
+            app.visual_order
+                .iter()
+                .map(|&i| app.todos[i].description.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shown(&app), ["a", "e"]);
+        assert_eq!(app.selected, 1);
+
+        // a count past the end stops there
+        app.remove_below(usize::MAX);
+        assert_eq!(shown(&app), ["a"]);
+        assert_eq!(app.selected, 0);
+
+        app.undo(Instant::now());
+        app.undo(Instant::now());
+        assert_eq!(shown(&app), ["a", "b", "c", "d", "e"]);
+    }
+
     #[test]
     fn get_last_non_none_priority_returns_highest_defined_priority() {
         let mut app = App::new(vec![
@@ -535,6 +911,20 @@ mod tests {
         assert_eq!(app.todos[app.visual_order[1]].priority, None);
     }
 
+    #[test]
+    fn demote_selected_stops_at_the_configured_lowest_priority() {
+        let mut config = Config::default();
+        config.priority.lowest = 3;
+        let mut app = App::new(vec![todo_with("a", Some(2))])
+            .with_config(config)
+            .unwrap();
+
+        app.demote_selected();
+        assert_eq!(app.todos[0].priority, Some(3));
+        app.demote_selected();
+        assert_eq!(app.todos[0].priority, None);
+    }
+
     #[test]
     fn demote_selected_keeps_none_priority() {
         let mut app = App::new(vec![todo_with("a", None)]);
@@ -544,25 +934,223 @@ mod tests {
         assert_eq!(app.todos[app.visual_order[0]].priority, None);
     }
 
+    #[test]
+    fn incremental_search_jumps_to_first_match_from_origin() {
+        let mut app = App::new(vec![
+            make_todo("buy milk"),
+            make_todo("write report"),
+            make_todo("read report"),
+        ]);
+        app.selected = 2;
+
+        app.start_search();
+        assert_eq!(app.mode, InputMode::Searching);
+
+        // matching starts at the origin and wraps around
+        app.search_insert('r');
+        app.search_insert('e');
+        app.search_insert('p');
+        assert_eq!(app.selected, 2);
+
+        app.search_insert('o');
+        app.search_insert('r');
+        app.search_insert('t');
+        app.search_insert(' ');
+        app.search_insert('w');
+        assert_eq!(app.selected, 1);
+
+        app.search_backspace();
+        app.search_backspace();
+        assert_eq!(app.selected, 2);
+
+        app.confirm_search();
+        assert_eq!(app.mode, InputMode::Normal);
+        assert_eq!(app.search.as_ref().unwrap().value, "report");
+
+        app.next_match();
+        assert_eq!(app.selected, 1);
+        app.next_match();
+        assert_eq!(app.selected, 2);
+    }
+
+    #[test]
+    fn cancel_search_restores_selection() {
+        let mut app = App::new(vec![make_todo("alpha"), make_todo("beta")]);
+
+        app.start_search();
+        app.search_insert('b');
+        assert_eq!(app.selected, 1);
+
+        app.cancel_search();
+        assert_eq!(app.selected, 0);
+        assert_eq!(app.mode, InputMode::Normal);
+        assert!(app.search.is_none());
+    }
+
+    #[test]
+    fn search_navigation_without_match_keeps_selection() {
+        let mut app = App::new(vec![make_todo("alpha"), make_todo("beta")]);
+        app.selected = 1;
+
+        app.start_search();
+        app.search_insert('z');
+        assert_eq!(app.selected, 1);
+
+        app.next();
+        app.previous();
+        assert_eq!(app.selected, 1);
+    }
+
+    #[test]
+    fn cycle_sort_reorders_and_keeps_selected_item() {
+        let mut app = App::new(vec![
+            todo_with("b", Some(1)),
+            todo_with("a", Some(2)),
+            todo_with("c", None),
+        ]);
+        app.selected = 1; // "a"
+
+        app.cycle_sort(); // due: nobody has one, so priority breaks the tie
+        assert_eq!(app.sort_mode, SortMode::Due);
+        assert_eq!(app.visual_order, vec![0, 1, 2]);
+
+        app.cycle_sort(); // created: all unknown, file order
+        app.cycle_sort(); // description
+        assert_eq!(app.sort_mode, SortMode::Description);
+        assert_eq!(app.visual_order, vec![1, 0, 2]);
+        assert_eq!(app.todos[app.visual_order[app.selected]].description, "a");
+
+        app.cycle_sort();
+        assert_eq!(app.sort_mode, SortMode::Priority);
+        assert_eq!(app.visual_order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_list() {
+        let now = Instant::now();
+        let mut app = App::new(vec![make_todo("1"), make_todo("2")]);
+        app.toggle_done();
+        app.remove_selected();
+        assert_eq!(app.todos.len(), 1);
+
+        app.undo(now);
+        assert_eq!(app.todos.len(), 2);
+        assert!(app.todos[0].done);
+        app.undo(now);
+        assert!(!app.todos[0].done);
+        app.undo(now);
+        assert_eq!(app.status, Some(Status::Info("Nothing to undo".into())));
+
+        app.redo(now);
+        assert!(app.todos[0].done);
+    }
+
+    #[test]
+    fn add_below_opens_a_new_item_that_is_dropped_if_left_empty() {
+        let mut app = App::new(vec![todo_with("a", Some(1)), todo_with("b", Some(2))]);
+        app.add_below();
+        assert_eq!(app.mode, InputMode::Editing);
+        assert_eq!(app.todos.len(), 3);
+        assert_eq!(app.todos[app.visual_order[app.selected]].priority, Some(1));
+
+        app.toggle_mode();
+        assert_eq!(app.todos.len(), 2);
+        app.undo(Instant::now());
+        assert_eq!(app.status, Some(Status::Info("Nothing to undo".into())));
+
+        app.add_below();
+        app.edit_insert('c');
+        app.toggle_mode();
+        assert_eq!(app.todos[app.visual_order[1]].description, "c");
+        app.undo(Instant::now());
+        assert_eq!(app.todos.len(), 2);
+    }
+
     fn todo_with(desc: &str, prio: Option<u8>) -> TodoItem {
         TodoItem {
             description: desc.into(),
             priority: prio,
-            due: None,
-            tags: None,
-            notes: None,
-            done: false,
+            ..TodoItem::default()
         }
     }
 
+    fn todo_with_id(id: &str, description: &str) -> TodoItem {
+        TodoItem {
+            id: id.into(),
+            ..make_todo(description)
+        }
+    }
+
+    #[test]
+    fn reload_keeps_selection_by_id_and_merges_unsaved_edits() {
+        let now = Instant::now();
+        let mut app = App::new(vec![todo_with_id("a", "One"), todo_with_id("b", "Two")]);
+        app.selected = 1;
+        app.expanded = Some(1);
+        // Unsaved edit made in the TUI.
+        app.todos[1].notes = Some("mine".into());
+
+        // Meanwhile another process added an item at the front and edited "b".
+        let mut disk = vec![
+            todo_with_id("c", "Zero"),
+            todo_with_id("a", "One"),
+            todo_with_id("b", "Two"),
+        ];
+        disk[2].done = true;
+        app.reload(disk.clone(), now);
+
+        let selected = &app.todos[app.visual_order[app.selected]];
+        assert_eq!(selected.id, "b");
+        assert!(selected.done);
+        assert_eq!(selected.notes.as_deref(), Some("mine"));
+        assert_eq!(app.todos[app.expanded.unwrap()].id, "b");
+        assert_eq!(app.todos.len(), 3);
+        assert_eq!(app.on_disk, disk);
+        assert_eq!(
+            app.status,
+            Some(Status::Info("Reloaded: the file changed on disk".into()))
+        );
+
+        app.expire_status(now + Duration::from_secs(1));
+        assert!(app.status.is_some());
+        app.expire_status(now + NOTICE_DURATION);
+        assert_eq!(app.status, None);
+    }
+
+    #[test]
+    fn reload_prefers_unsaved_edits_on_conflict() {
+        let mut app = App::new(vec![todo_with_id("a", "One")]);
+        app.todos[0].description = "One (mine)".into();
+
+        app.reload(vec![todo_with_id("a", "One (theirs)")], Instant::now());
+        assert_eq!(app.todos[0].description, "One (mine)");
+        assert_eq!(
+            app.status,
+            Some(Status::Info(
+                "Reloaded: kept your edits to 1 item also changed on disk".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn replace_with_saved_drops_refused_edits() {
+        let mut app = App::new(vec![todo_with_id("a", "One"), todo_with_id("b", "Two")]);
+        app.todos[0].description = "One (refused)".into();
+        app.todos[1].description = "Two (changed by a hook)".into();
+
+        let saved = vec![
+            todo_with_id("a", "One"),
+            todo_with_id("b", "Two (changed again)"),
+        ];
+        app.replace_with_saved(saved.clone(), Instant::now());
+        assert_eq!(app.todos, saved);
+        assert_eq!(app.on_disk, saved);
+    }
+
     fn make_todo(description: &str) -> TodoItem {
         TodoItem {
             description: description.into(),
-            priority: None,
-            due: None,
-            tags: None,
-            notes: None,
-            done: false,
+            ..TodoItem::default()
         }
     }
 }
@@ -1,7 +1,8 @@
-use crate::tui::app::InputMode;
+use crate::tui::app::App;
 use crossterm::event::{self, Event, KeyCode};
 use std::time::Duration;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputEvent {
     Quit,
     Down,
@@ -15,49 +16,54 @@ pub enum InputEvent {
     Backspace,
     PromotePriority,
     DemotePriority,
+    StartSearch,
+    NextMatch,
+    PreviousMatch,
+    ConfirmSearch,
+    CancelSearch,
+    CycleSort,
+    Top,
+    Bottom,
+    AddBelow,
+    Undo,
+    Redo,
+    NormalMode,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    Insert,
+    Append,
+    InsertAtStart,
+    AppendAtEnd,
+    DeleteChar,
     Char(char),
     None,
 }
 
-pub fn poll_input(timeout: Duration, mode: InputMode) -> std::io::Result<InputEvent> {
-    if event::poll(timeout)? {
-        if let Event::Key(key) = event::read()? {
-            return Ok(match mode {
-                InputMode::Normal => match_key_code_for_normal_mode(key.code),
-                InputMode::Editing => match_key_code_for_edit_mode(key.code),
-            });
-        }
+pub fn poll_input(timeout: Duration, app: &mut App) -> std::io::Result<InputEvent> {
+    if let Some(event) = app.keymap.queued() {
+        return Ok(event);
     }
-    Ok(InputEvent::None)
-}
-
-fn match_key_code_for_normal_mode(code: KeyCode) -> InputEvent {
-    match code {
-        KeyCode::Char('q') => InputEvent::Quit,
-        KeyCode::Down => InputEvent::Down,
-        KeyCode::Up => InputEvent::Up,
-        KeyCode::Left => InputEvent::Left,
-        KeyCode::Right => InputEvent::Right,
-        KeyCode::Enter => InputEvent::ToggleDone,
-        KeyCode::Char(' ') => InputEvent::ToggleExpand,
-        KeyCode::Char('e') => InputEvent::EnableEditing,
-        KeyCode::Esc => InputEvent::DisableEditing,
-        KeyCode::Backspace => InputEvent::Backspace,
-        KeyCode::Char('p') => InputEvent::PromotePriority,
-        KeyCode::Char('l') => InputEvent::DemotePriority,
-        _ => InputEvent::None,
+    if event::poll(timeout)?
+        && let Event::Key(key) = event::read()?
+    {
+        return Ok(match app.keymap_mode() {
+            Some(mode) => app.keymap.event(mode, key),
+            None => match_key_code_for_search_mode(key.code),
+        });
     }
+    Ok(InputEvent::None)
 }
 
-fn match_key_code_for_edit_mode(code: KeyCode) -> InputEvent {
+fn match_key_code_for_search_mode(code: KeyCode) -> InputEvent {
     match code {
         KeyCode::Down => InputEvent::Down,
         KeyCode::Up => InputEvent::Up,
-        KeyCode::Left => InputEvent::Left,
-        KeyCode::Right => InputEvent::Right,
-        KeyCode::Esc => InputEvent::DisableEditing,
+        KeyCode::Enter => InputEvent::ConfirmSearch,
+        KeyCode::Esc => InputEvent::CancelSearch,
         KeyCode::Backspace => InputEvent::Backspace,
-        KeyCode::Enter => InputEvent::ToggleDone,
         KeyCode::Char(c) => InputEvent::Char(c),
         _ => InputEvent::None,
     }
@@ -0,0 +1,615 @@
+use crate::tui::events::InputEvent;
+use crix_todo::config::{Keys, Preset};
+use crix_todo::error::{Result, TodoError};
+use crix_todo::keys::{Bindings, Chord, Key, Sequence};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::VecDeque;
+
+/// The sets of keys that apply at different times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Browsing the list.
+    Normal,
+    /// Typing in the edit modal.
+    Edit,
+    /// Moving around the edit modal in its normal mode.
+    EditNormal,
+}
+
+struct Binding {
+    sequence: Sequence,
+    /// The config key it was set by, for error messages.
+    action: &'static str,
+    event: InputEvent,
+}
+
+/// What the keys do in each [`Mode`], built from the `keys` config.
+pub struct Keymap {
+    normal: Vec<Binding>,
+    edit: Vec<Binding>,
+    edit_normal: Vec<Binding>,
+    /// Whether digits typed before a key repeat it, as in vim's `5j`.
+    counts: bool,
+    count: Option<usize>,
+    /// The chords typed so far of a sequence that is not finished yet.
+    pending: Vec<Chord>,
+    /// Events a single key gave beyond the one [`Keymap::event`] returned.
+    queued: VecDeque<InputEvent>,
+}
+
+/// The largest count taken; more digits leave it there, so a long run of
+/// them cannot keep the TUI busy repeating an action.
+const MAX_COUNT: usize = 9999;
+
+/// The header of the list: actions shown together and their caption.
+const NORMAL_HEADER: &[(&[&str], &str)] = &[
+    (&["keys.normal.up", "keys.normal.down"], "Move"),
+    (&["keys.normal.toggle_done"], "Toggle Done"),
+    (&["keys.normal.expand"], "Expand"),
+    (&["keys.normal.edit"], "Edit"),
+    (&["keys.normal.add"], "Add"),
+    (&["keys.normal.delete"], "Delete"),
+    (
+        &["keys.normal.promote", "keys.normal.demote"],
+        "Toggle Priority",
+    ),
+    (&["keys.normal.search"], "Search"),
+    (
+        &["keys.normal.next_match", "keys.normal.previous_match"],
+        "Next/Previous Match",
+    ),
+    (&["keys.normal.sort"], "Sort"),
+    (&["keys.normal.undo", "keys.normal.redo"], "Undo/Redo"),
+    (&["keys.normal.quit"], "Quit"),
+];
+
+const EDIT_HEADER: &[(&[&str], &str)] = &[
+    (
+        &["keys.edit.previous_field", "keys.edit.next_field"],
+        "Move field",
+    ),
+    (&["keys.edit.left", "keys.edit.right"], "Move cursor"),
+    (&["keys.edit.normal_mode"], "Normal mode"),
+    (&["keys.edit.close"], "Save & exit"),
+    (&["keys.edit.toggle_done"], "Toggle Done"),
+];
+
+const EDIT_NORMAL_HEADER: &[(&[&str], &str)] = &[
+    (
+        &[
+            "keys.edit_normal.previous_field",
+            "keys.edit_normal.next_field",
+        ],
+        "Move field",
+    ),
+    (
+        &[
+            "keys.edit_normal.word_forward",
+            "keys.edit_normal.word_backward",
+            "keys.edit_normal.word_end",
+        ],
+        "Word",
+    ),
+    (
+        &["keys.edit_normal.line_start", "keys.edit_normal.line_end"],
+        "Start/End",
+    ),
+    (
+        &["keys.edit_normal.insert", "keys.edit_normal.append"],
+        "Insert",
+    ),
+    (&["keys.edit_normal.delete_char"], "Delete"),
+    (&["keys.edit_normal.close"], "Save & exit"),
+    (&["keys.edit_normal.toggle_done"], "Toggle Done"),
+];
+
+impl Keymap {
+    /// Fails if one binding in a mode is the same as, or the start of,
+    /// another one for a different action.
+    pub fn new(keys: &Keys) -> Result<Self> {
+        let n = &keys.normal;
+        let normal = bind(&[
+            ("keys.normal.quit", &n.quit, InputEvent::Quit),
+            ("keys.normal.down", &n.down, InputEvent::Down),
+            ("keys.normal.up", &n.up, InputEvent::Up),
+            (
+                "keys.normal.toggle_done",
+                &n.toggle_done,
+                InputEvent::ToggleDone,
+            ),
+            ("keys.normal.expand", &n.expand, InputEvent::ToggleExpand),
+            ("keys.normal.edit", &n.edit, InputEvent::EnableEditing),
+            ("keys.normal.delete", &n.delete, InputEvent::Backspace),
+            (
+                "keys.normal.promote",
+                &n.promote,
+                InputEvent::PromotePriority,
+            ),
+            ("keys.normal.demote", &n.demote, InputEvent::DemotePriority),
+            ("keys.normal.search", &n.search, InputEvent::StartSearch),
+            (
+                "keys.normal.next_match",
+                &n.next_match,
+                InputEvent::NextMatch,
+            ),
+            (
+                "keys.normal.previous_match",
+                &n.previous_match,
+                InputEvent::PreviousMatch,
+            ),
+            ("keys.normal.sort", &n.sort, InputEvent::CycleSort),
+            ("keys.normal.top", &n.top, InputEvent::Top),
+            ("keys.normal.bottom", &n.bottom, InputEvent::Bottom),
+            ("keys.normal.add", &n.add, InputEvent::AddBelow),
+            ("keys.normal.undo", &n.undo, InputEvent::Undo),
+            ("keys.normal.redo", &n.redo, InputEvent::Redo),
+        ]);
+        let e = &keys.edit;
+        let edit = bind(&[
+            ("keys.edit.next_field", &e.next_field, InputEvent::Down),
+            (
+                "keys.edit.previous_field",
+                &e.previous_field,
+                InputEvent::Up,
+            ),
+            ("keys.edit.left", &e.left, InputEvent::Left),
+            ("keys.edit.right", &e.right, InputEvent::Right),
+            ("keys.edit.close", &e.close, InputEvent::DisableEditing),
+            ("keys.edit.backspace", &e.backspace, InputEvent::Backspace),
+            (
+                "keys.edit.toggle_done",
+                &e.toggle_done,
+                InputEvent::ToggleDone,
+            ),
+            (
+                "keys.edit.normal_mode",
+                &e.normal_mode,
+                InputEvent::NormalMode,
+            ),
+        ]);
+        let v = &keys.edit_normal;
+        let edit_normal = bind(&[
+            (
+                "keys.edit_normal.next_field",
+                &v.next_field,
+                InputEvent::Down,
+            ),
+            (
+                "keys.edit_normal.previous_field",
+                &v.previous_field,
+                InputEvent::Up,
+            ),
+            ("keys.edit_normal.left", &v.left, InputEvent::Left),
+            ("keys.edit_normal.right", &v.right, InputEvent::Right),
+            (
+                "keys.edit_normal.word_forward",
+                &v.word_forward,
+                InputEvent::WordForward,
+            ),
+            (
+                "keys.edit_normal.word_backward",
+                &v.word_backward,
+                InputEvent::WordBackward,
+            ),
+            (
+                "keys.edit_normal.word_end",
+                &v.word_end,
+                InputEvent::WordEnd,
+            ),
+            (
+                "keys.edit_normal.line_start",
+                &v.line_start,
+                InputEvent::LineStart,
+            ),
+            (
+                "keys.edit_normal.line_end",
+                &v.line_end,
+                InputEvent::LineEnd,
+            ),
+            ("keys.edit_normal.insert", &v.insert, InputEvent::Insert),
+            ("keys.edit_normal.append", &v.append, InputEvent::Append),
+            (
+                "keys.edit_normal.insert_at_start",
+                &v.insert_at_start,
+                InputEvent::InsertAtStart,
+            ),
+            (
+                "keys.edit_normal.append_at_end",
+                &v.append_at_end,
+                InputEvent::AppendAtEnd,
+            ),
+            (
+                "keys.edit_normal.delete_char",
+                &v.delete_char,
+                InputEvent::DeleteChar,
+            ),
+            (
+                "keys.edit_normal.close",
+                &v.close,
+                InputEvent::DisableEditing,
+            ),
+            (
+                "keys.edit_normal.toggle_done",
+                &v.toggle_done,
+                InputEvent::ToggleDone,
+            ),
+        ]);
+
+        let conflicts = [&normal, &edit, &edit_normal]
+            .into_iter()
+            .flat_map(|bindings| conflicts(bindings))
+            .collect::<Vec<_>>();
+        if !conflicts.is_empty() {
+            return Err(TodoError::InvalidInput(format!(
+                "conflicting key bindings: {}",
+                conflicts.join("; ")
+            )));
+        }
+        Ok(Keymap {
+            normal,
+            edit,
+            edit_normal,
+            counts: keys.preset == Preset::Vim,
+            count: None,
+            pending: Vec::new(),
+            queued: VecDeque::new(),
+        })
+    }
+
+    fn bindings(&self, mode: Mode) -> &[Binding] {
+        match mode {
+            Mode::Normal => &self.normal,
+            Mode::Edit => &self.edit,
+            Mode::EditNormal => &self.edit_normal,
+        }
+    }
+
+    /// The event for a key pressed in `mode`. Keys that start a sequence
+    /// give `None` until the sequence is finished; a key that does not
+    /// continue it drops what was typed and counts on its own. While typing
+    /// in the edit modal, the dropped keys are typed as text first, so with
+    /// `j k` bound, `just` still gives `just`; the events after the first are
+    /// kept for [`Keymap::queued`]. With counts on, digits outside of a
+    /// sequence are kept for [`Keymap::take_count`].
+    pub fn event(&mut self, mode: Mode, key: KeyEvent) -> InputEvent {
+        let Some(chord) = chord(key) else {
+            self.reset();
+            return InputEvent::None;
+        };
+
+        if self.counts
+            && mode != Mode::Edit
+            && self.pending.is_empty()
+            && !chord.ctrl
+            && !chord.alt
+            && let Key::Char(c) = chord.key
+            && let Some(digit) = c.to_digit(10)
+            && (digit != 0 || self.count.is_some())
+        {
+            let count = self.count.unwrap_or(0).saturating_mul(10);
+            self.count = Some(count.saturating_add(digit as usize).min(MAX_COUNT));
+            return InputEvent::None;
+        }
+
+        self.pending.push(chord);
+        let bindings = self.bindings(mode);
+        if let Some(binding) = bindings.iter().find(|b| b.sequence.0 == self.pending) {
+            let event = binding.event;
+            self.pending.clear();
+            return event;
+        }
+        if bindings
+            .iter()
+            .any(|b| b.sequence.0.starts_with(&self.pending))
+        {
+            return InputEvent::None;
+        }
+        let mut dropped = std::mem::take(&mut self.pending);
+        dropped.pop();
+        if !dropped.is_empty() {
+            if mode == Mode::Edit {
+                self.queued
+                    .extend(dropped.iter().filter_map(|chord| match chord.key {
+                        Key::Char(c) if !chord.ctrl && !chord.alt => Some(InputEvent::Char(c)),
+                        _ => None,
+                    }));
+            }
+            let event = self.event(mode, key);
+            if event != InputEvent::None {
+                self.queued.push_back(event);
+            }
+            return self.queued.pop_front().unwrap_or(InputEvent::None);
+        }
+
+        self.count = None;
+        match (mode, key.code) {
+            (Mode::Edit, KeyCode::Char(c)) if !chord.ctrl && !chord.alt => InputEvent::Char(c),
+            _ => InputEvent::None,
+        }
+    }
+
+    /// The next event still due from keys already pressed, if any.
+    pub fn queued(&mut self) -> Option<InputEvent> {
+        self.queued.pop_front()
+    }
+
+    /// The count typed before the last event, if any, which it consumes.
+    pub fn take_count(&mut self) -> Option<usize> {
+        self.count.take()
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.count = None;
+    }
+
+    /// The header for `mode`, such as `[↑/↓] Move    [q] Quit`, skipping
+    /// actions that are not bound.
+    pub fn header(&self, mode: Mode) -> Vec<String> {
+        let items = match mode {
+            Mode::Normal => NORMAL_HEADER,
+            Mode::Edit => EDIT_HEADER,
+            Mode::EditNormal => EDIT_NORMAL_HEADER,
+        };
+        let bindings = self.bindings(mode);
+        items
+            .iter()
+            .filter_map(|(actions, caption)| {
+                let labels = actions
+                    .iter()
+                    .filter_map(|action| bindings.iter().find(|b| b.action == *action))
+                    .map(|binding| label(&binding.sequence))
+                    .collect::<Vec<_>>();
+                (!labels.is_empty()).then(|| format!("[{}] {}", labels.join("/"), caption))
+            })
+            .collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new(&Keys::default()).expect("the default keys do not conflict")
+    }
+}
+
+fn bind(actions: &[(&'static str, &Bindings, InputEvent)]) -> Vec<Binding> {
+    actions
+        .iter()
+        .flat_map(|(action, bindings, event)| {
+            bindings.0.iter().map(|sequence| Binding {
+                sequence: sequence.clone(),
+                action,
+                event: *event,
+            })
+        })
+        .collect()
+}
+
+fn conflicts(bindings: &[Binding]) -> Vec<String> {
+    let mut found = Vec::new();
+    for (i, a) in bindings.iter().enumerate() {
+        for b in &bindings[i + 1..] {
+            if a.action == b.action {
+                continue;
+            }
+            let (short, long) = if a.sequence.0.len() <= b.sequence.0.len() {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            if short.sequence == long.sequence {
+                found.push(format!(
+                    "'{}' is bound to both {} and {}",
+                    short.sequence, short.action, long.action
+                ));
+            } else if long.sequence.0.starts_with(&short.sequence.0) {
+                found.push(format!(
+                    "'{}' for {} is the start of '{}' for {}",
+                    short.sequence, short.action, long.sequence, long.action
+                ));
+            }
+        }
+    }
+    found
+}
+
+fn chord(key: KeyEvent) -> Option<Chord> {
+    let code = match key.code {
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Tab | KeyCode::BackTab => Key::Tab,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::F(n) => Key::F(n),
+        _ => return None,
+    };
+    let held = |modifier| key.modifiers.contains(modifier);
+    Some(Chord::new(
+        code,
+        held(KeyModifiers::CONTROL),
+        held(KeyModifiers::ALT),
+        held(KeyModifiers::SHIFT) || key.code == KeyCode::BackTab,
+    ))
+}
+
+/// How a sequence is shown in the header: `↑`, `Ctrl-r`, `gg`.
+fn label(sequence: &Sequence) -> String {
+    let chords = sequence
+        .0
+        .iter()
+        .map(|chord| {
+            let key = match chord.key {
+                Key::Up => "↑".to_string(),
+                Key::Down => "↓".to_string(),
+                Key::Left => "←".to_string(),
+                Key::Right => "→".to_string(),
+                Key::Enter => "⏎".to_string(),
+                Key::Backspace => "⌫".to_string(),
+                Key::Char(' ') => "Space".to_string(),
+                key => key.to_string(),
+            };
+            let modifiers = [
+                (chord.ctrl, "Ctrl-"),
+                (chord.alt, "Alt-"),
+                (chord.shift, "Shift-"),
+            ]
+            .into_iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, name)| name)
+            .collect::<String>();
+            modifiers + &key
+        })
+        .collect::<Vec<_>>();
+    let plain = sequence
+        .0
+        .iter()
+        .all(|chord| matches!(chord.key, Key::Char(c) if c != ' ') && !chord.ctrl && !chord.alt);
+    chords.join(if plain { "" } else { " " })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn sequences_and_modifiers_are_matched() {
+        let mut keys = Keys::default();
+        keys.normal.delete = "d d, ctrl-x".parse().unwrap();
+        keys.edit.close = "esc, ctrl-s".parse().unwrap();
+        let mut keymap = Keymap::new(&keys).unwrap();
+        let normal = Mode::Normal;
+
+        assert_eq!(
+            keymap.event(normal, press(KeyCode::Char('d'))),
+            InputEvent::None
+        );
+        assert_eq!(
+            keymap.event(normal, press(KeyCode::Char('d'))),
+            InputEvent::Backspace
+        );
+        // an unfinished sequence gives way to the next key
+        keymap.event(normal, press(KeyCode::Char('d')));
+        assert_eq!(
+            keymap.event(normal, press(KeyCode::Char('q'))),
+            InputEvent::Quit
+        );
+        let ctrl_x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.event(normal, ctrl_x), InputEvent::Backspace);
+        assert_eq!(
+            keymap.event(normal, press(KeyCode::Backspace)),
+            InputEvent::None
+        );
+
+        let editing = Mode::Edit;
+        let ctrl_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.event(editing, ctrl_s), InputEvent::DisableEditing);
+        assert_eq!(
+            keymap.event(editing, press(KeyCode::Char('s'))),
+            InputEvent::Char('s')
+        );
+    }
+
+    #[test]
+    fn keys_of_an_unfinished_sequence_are_typed_while_editing() {
+        let mut keys = Keys::default();
+        keys.edit.normal_mode = "j k".parse().unwrap();
+        let mut keymap = Keymap::new(&keys).unwrap();
+        let mut typed = Vec::new();
+        for c in "just jk".chars() {
+            typed.push(keymap.event(Mode::Edit, press(KeyCode::Char(c))));
+            typed.extend(std::iter::from_fn(|| keymap.queued()));
+        }
+        typed.retain(|event| *event != InputEvent::None);
+
+        let mut expected = "just ".chars().map(InputEvent::Char).collect::<Vec<_>>();
+        expected.push(InputEvent::NormalMode);
+        assert_eq!(typed, expected);
+    }
+
+    #[test]
+    fn conflicting_bindings_are_refused() {
+        let mut keys = Keys::default();
+        keys.normal.delete = "q".parse().unwrap();
+        keys.normal.sort = "e x".parse().unwrap();
+        let err = Keymap::new(&keys).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "conflicting key bindings: 'q' is bound to both keys.normal.quit and keys.normal.delete; \
+             'e' for keys.normal.edit is the start of 'e x' for keys.normal.sort"
+        );
+
+        // the same keys may do different things in different modes
+        keys = Keys::default();
+        keys.edit.toggle_done = "q".parse().unwrap();
+        assert!(Keymap::new(&keys).is_ok());
+    }
+
+    #[test]
+    fn header_follows_the_bindings() {
+        let mut keys = Keys::default();
+        keys.normal.delete = "d d".parse().unwrap();
+        keys.normal.sort = "".parse().unwrap();
+        keys.normal.quit = "ctrl-q, q".parse().unwrap();
+        let header = Keymap::new(&keys).unwrap().header(Mode::Normal);
+
+        assert_eq!(header[0], "[↑/↓] Move");
+        assert!(header.contains(&"[dd] Delete".to_string()));
+        assert!(!header.iter().any(|item| item.ends_with("Sort")));
+        assert_eq!(header.last().unwrap(), "[Ctrl-q] Quit");
+    }
+
+    #[test]
+    fn the_vim_preset_takes_counts() {
+        let mut keymap = Keymap::new(&Keys::preset(Preset::Vim)).unwrap();
+        let normal = Mode::Normal;
+
+        for c in ['1', '2'] {
+            assert_eq!(
+                keymap.event(normal, press(KeyCode::Char(c))),
+                InputEvent::None
+            );
+        }
+        assert_eq!(
+            keymap.event(normal, press(KeyCode::Char('j'))),
+            InputEvent::Down
+        );
+        assert_eq!(keymap.take_count(), Some(12));
+        assert_eq!(keymap.take_count(), None);
+        for c in "99999999999999999999".chars() {
+            keymap.event(normal, press(KeyCode::Char(c)));
+        }
+        keymap.event(normal, press(KeyCode::Char('j')));
+        assert_eq!(keymap.take_count(), Some(MAX_COUNT));
+
+        keymap.event(normal, press(KeyCode::Char('g')));
+        assert_eq!(
+            keymap.event(normal, press(KeyCode::Char('g'))),
+            InputEvent::Top
+        );
+        let shift_g = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(keymap.event(normal, shift_g), InputEvent::Bottom);
+        // `0` only counts after another digit; on its own it moves the cursor
+        assert_eq!(
+            keymap.event(Mode::EditNormal, press(KeyCode::Char('0'))),
+            InputEvent::LineStart
+        );
+        assert_eq!(keymap.take_count(), None);
+        assert_eq!(
+            keymap.event(Mode::Edit, press(KeyCode::Char('5'))),
+            InputEvent::Char('5')
+        );
+    }
+}
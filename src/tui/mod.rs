@@ -1,6 +1,7 @@
 pub mod app;
 pub mod events;
-mod state;
+pub mod keymap;
+pub mod state;
 pub mod ui;
 mod view_models;
 mod views;
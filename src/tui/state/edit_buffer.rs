@@ -1,9 +1,13 @@
-use crate::storage::TodoItem;
-use crate::tui::state::field_buffer::FieldBuffer;
+use crate::tui::state::field_buffer::{FieldBuffer, FieldMode};
+use crix_todo::storage::TodoItem;
 
 pub struct EditBuffer {
     pub fields: [FieldBuffer; 5], // 0-4: desc, prio, due, tags, notes
     pub selected_field: usize,
+    pub mode: FieldMode,
+    /// Set for an item that was just added, which is dropped again if it is
+    /// left without a description.
+    pub is_new: bool,
 }
 
 impl EditBuffer {
@@ -17,6 +21,8 @@ impl EditBuffer {
                 FieldBuffer::new(todo.notes.clone().unwrap_or_default()),
             ],
             selected_field: 0,
+            mode: FieldMode::Insert,
+            is_new: false,
         }
     }
 
@@ -57,7 +63,7 @@ impl EditBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::TodoItem;
+    use crix_todo::storage::TodoItem;
 
     fn sample_todo() -> TodoItem {
         TodoItem {
@@ -65,8 +71,8 @@ mod tests {
             priority: Some(2),
             due: Some("2030-01-01".into()),
             tags: Some(vec!["foo".into(), "bar".into()]),
-            done: false,
             notes: Some("old note".into()),
+            ..TodoItem::default()
         }
     }
 
@@ -93,7 +99,7 @@ mod tests {
         let mut buf = EditBuffer::new(&todo);
 
         buf.selected_field = 2; // Due-date field
-        buf.current_field_mut().value.push_str("X"); // mutate through helper
+        buf.current_field_mut().value.push('X'); // mutate through helper
         assert_eq!(buf.fields[2].value, "2030-01-01X");
     }
 
@@ -1,3 +1,11 @@
+/// Whether keys in the edit modal type text or, as in vim's normal mode,
+/// move around it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldMode {
+    Insert,
+    Normal,
+}
+
 #[derive(Clone)]
 pub struct FieldBuffer {
     pub value: String,
@@ -40,6 +48,77 @@ impl FieldBuffer {
         self.move_right();
     }
 
+    pub fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Onto the last char, where normal mode's `$` goes.
+    pub fn move_to_last(&mut self) {
+        self.cursor = self.value.chars().count().saturating_sub(1);
+    }
+
+    /// To the start of the next word, or the end of the text.
+    pub fn word_forward(&mut self) {
+        let chars = self.value.chars().collect::<Vec<_>>();
+        let mut pos = self.cursor;
+        if let Some(&c) = chars.get(pos) {
+            let class = class(c);
+            while chars.get(pos).is_some_and(|&c| self::class(c) == class) {
+                pos += 1;
+            }
+        }
+        while chars.get(pos).is_some_and(|c| c.is_whitespace()) {
+            pos += 1;
+        }
+        self.cursor = pos;
+    }
+
+    /// To the start of this word, or of the previous one if already there.
+    pub fn word_backward(&mut self) {
+        let chars = self.value.chars().collect::<Vec<_>>();
+        let mut pos = self.cursor.min(chars.len());
+        while pos > 0 && chars[pos - 1].is_whitespace() {
+            pos -= 1;
+        }
+        if pos > 0 {
+            let class = class(chars[pos - 1]);
+            while pos > 0 && self::class(chars[pos - 1]) == class {
+                pos -= 1;
+            }
+        }
+        self.cursor = pos;
+    }
+
+    /// Onto the last char of this word, or of the next one if already there.
+    pub fn word_end(&mut self) {
+        let chars = self.value.chars().collect::<Vec<_>>();
+        let mut pos = self.cursor + 1;
+        while chars.get(pos).is_some_and(|c| c.is_whitespace()) {
+            pos += 1;
+        }
+        let Some(&c) = chars.get(pos) else {
+            return;
+        };
+        let class = class(c);
+        while chars.get(pos + 1).is_some_and(|&c| self::class(c) == class) {
+            pos += 1;
+        }
+        self.cursor = pos;
+    }
+
+    /// Deletes the char under the cursor, as normal mode's `x` does.
+    pub fn delete_char(&mut self) {
+        let len = self.value.chars().count();
+        if self.cursor >= len {
+            return;
+        }
+        self.move_right();
+        self.backspace();
+        if self.cursor + 1 >= len {
+            self.move_to_last();
+        }
+    }
+
     pub fn backspace(&mut self) {
         if self.cursor == 0 {
             return;
@@ -52,6 +131,18 @@ impl FieldBuffer {
     }
 }
 
+/// Words are runs of letters, digits and `_`, or runs of other
+/// non-blank chars.
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,4 +207,37 @@ mod tests {
         assert_eq!(buf.value, "a");
         assert_eq!(buf.cursor, 0);
     }
+
+    #[test]
+    fn word_motions_stop_at_words_and_punctuation() {
+        let mut buf = FieldBuffer::new("call bob, today".to_string());
+        buf.move_to_start();
+        buf.word_forward();
+        assert_eq!(buf.cursor, 5); // bob
+        buf.word_forward();
+        assert_eq!(buf.cursor, 8); // ,
+        buf.word_end();
+        assert_eq!(buf.cursor, 14); // end of today
+        buf.word_forward();
+        assert_eq!(buf.cursor, 15); // end of the text
+        buf.word_backward();
+        assert_eq!(buf.cursor, 10); // today
+        buf.word_backward();
+        buf.word_backward();
+        assert_eq!(buf.cursor, 5); // bob
+    }
+
+    #[test]
+    fn delete_char_removes_the_char_under_the_cursor() {
+        let mut buf = FieldBuffer::new("abc".to_string());
+        buf.move_to_start();
+        buf.delete_char();
+        assert_eq!((buf.value.as_str(), buf.cursor), ("bc", 0));
+        buf.move_to_last();
+        buf.delete_char();
+        assert_eq!((buf.value.as_str(), buf.cursor), ("b", 0));
+        buf.delete_char();
+        buf.delete_char();
+        assert_eq!((buf.value.as_str(), buf.cursor), ("", 0));
+    }
 }
@@ -0,0 +1,80 @@
+use crix_todo::storage::TodoItem;
+
+/// How many changes can be undone.
+const LIMIT: usize = 100;
+
+/// Earlier and undone versions of the list, for undo and redo.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<TodoItem>>,
+    redo: Vec<Vec<TodoItem>>,
+}
+
+impl History {
+    /// Remembers the list as it was before a change.
+    pub fn record(&mut self, before: &[TodoItem]) {
+        self.undo.push(before.to_vec());
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Forgets the last change recorded, when it came to nothing.
+    pub fn discard_last(&mut self) {
+        self.undo.pop();
+    }
+
+    /// The list before the last change, if there is one to undo.
+    pub fn undo(&mut self, current: &[TodoItem]) -> Option<Vec<TodoItem>> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current.to_vec());
+        Some(previous)
+    }
+
+    /// The list after the last undone change, if there is one.
+    pub fn redo(&mut self, current: &[TodoItem]) -> Option<Vec<TodoItem>> {
+        let next = self.redo.pop()?;
+        self.undo.push(current.to_vec());
+        Some(next)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(descriptions: &[&str]) -> Vec<TodoItem> {
+        descriptions
+            .iter()
+            .map(|d| TodoItem {
+                id: d.to_string(),
+                description: d.to_string(),
+                ..TodoItem::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn undo_and_redo_walk_through_the_changes() {
+        let mut history = History::default();
+        history.record(&list(&["a"]));
+        history.record(&list(&["a", "b"]));
+        let current = list(&["a", "b", "c"]);
+
+        let previous = history.undo(&current).unwrap();
+        assert_eq!(previous, list(&["a", "b"]));
+        assert_eq!(history.undo(&previous).unwrap(), list(&["a"]));
+        assert_eq!(history.undo(&list(&["a"])), None);
+        assert_eq!(history.redo(&list(&["a"])).unwrap(), list(&["a", "b"]));
+
+        // a new change drops what was undone
+        history.record(&list(&["a", "b"]));
+        assert_eq!(history.redo(&list(&["x"])), None);
+    }
+}
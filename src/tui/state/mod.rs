@@ -1,2 +1,3 @@
 pub mod edit_buffer;
 pub mod field_buffer;
+pub mod history;
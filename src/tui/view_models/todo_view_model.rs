@@ -1,27 +1,37 @@
-use crate::tui::app::App;
+use crate::tui::app::{App, InputMode, Status};
 use crate::tui::views::todo_list::Row;
+use crix_todo::search::{self, Field};
+use crix_todo::sort::SortMode;
+use crix_todo::storage::TodoItem;
 
 pub struct TodoListViewModel<'a> {
     pub rows: Vec<Row<'a>>,
     pub selected_index: Option<usize>,
+    pub search: Option<SearchBar>,
+    pub title: String,
+    pub status: Option<&'a Status>,
+}
+
+pub struct SearchBar {
+    pub query: String,
+    pub character_index: usize,
+    pub active: bool,
 }
 
 impl<'a> TodoListViewModel<'a> {
     pub fn from_app(app: &'a App) -> Self {
         let mut rows = Vec::new();
-        let mut last_priority: Option<u8> = None;
+        let mut last_group: Option<String> = None;
         let mut selected_index = None;
 
         for &i in &app.visual_order {
             let todo = &app.todos[i];
-            let priority = todo.priority.unwrap_or(99);
-
-            if Some(priority) != last_priority {
-                rows.push(Row::Header(match priority {
-                    99 => "Priority None".to_string(),
-                    p => format!("Priority {}", p),
-                }));
-                last_priority = Some(priority);
+
+            if let Some(group) = group_label(app.sort_mode, todo)
+                && Some(&group) != last_group.as_ref()
+            {
+                rows.push(Row::Header(group.clone()));
+                last_group = Some(group);
             }
 
             let is_expanded = app.expanded == Some(i);
@@ -29,24 +39,64 @@ impl<'a> TodoListViewModel<'a> {
                 selected_index = Some(rows.len());
             }
 
+            let highlighted = app
+                .search
+                .as_ref()
+                .and_then(|query| search::match_item(todo, &query.value))
+                .and_then(|(_, matches)| {
+                    matches
+                        .into_iter()
+                        .find(|m| m.field == Field::Description)
+                        .map(|m| m.indices)
+                })
+                .unwrap_or_default();
+
             rows.push(Row::Todo {
                 item: todo,
                 is_expanded,
+                highlighted,
             });
         }
 
+        let search = app.search.as_ref().map(|query| SearchBar {
+            query: query.value.clone(),
+            character_index: query.cursor,
+            active: app.mode == InputMode::Searching,
+        });
+
         Self {
             rows,
             selected_index,
+            search,
+            title: format!("Todos (by {})", app.sort_mode.name()),
+            status: app.status.as_ref(),
         }
     }
 }
 
+/// Section header for the item under the current sort, if that sort groups.
+fn group_label(mode: SortMode, todo: &TodoItem) -> Option<String> {
+    match mode {
+        SortMode::Priority => Some(match todo.priority {
+            Some(p) => format!("Priority {}", p),
+            None => "Priority None".to_string(),
+        }),
+        SortMode::Due => Some(match &todo.due {
+            Some(due) => format!("Due {}", due),
+            None => "No due date".to_string(),
+        }),
+        SortMode::Created | SortMode::Description => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::TodoItem;
     use crate::tui::app::{App, InputMode};
+    use crate::tui::keymap::Keymap;
+    use crate::tui::state::field_buffer::FieldBuffer;
+    use crate::tui::state::history::History;
+    use crix_todo::config::Config;
 
     fn make_todo(
         description: &str,
@@ -58,10 +108,8 @@ mod tests {
             TodoItem {
                 description: description.to_string(),
                 priority,
-                due: None,
-                tags: None,
                 done,
-                notes: None,
+                ..TodoItem::default()
             },
             expanded,
         )
@@ -86,6 +134,15 @@ mod tests {
             expanded: Some(1),
             mode: InputMode::Normal,
             edit_buffer: None,
+            search: None,
+            search_origin: 0,
+            sort_mode: SortMode::Priority,
+            status: None,
+            status_until: None,
+            on_disk: Vec::new(),
+            config: Config::default(),
+            keymap: Keymap::default(),
+            history: History::default(),
         };
 
         let vm = TodoListViewModel::from_app(&app);
@@ -124,6 +181,15 @@ mod tests {
             expanded: Some(1),
             mode: InputMode::Normal,
             edit_buffer: None,
+            search: None,
+            search_origin: 0,
+            sort_mode: SortMode::Priority,
+            status: None,
+            status_until: None,
+            on_disk: Vec::new(),
+            config: Config::default(),
+            keymap: Keymap::default(),
+            history: History::default(),
         };
 
         let vm = TodoListViewModel::from_app(&app);
@@ -154,11 +220,101 @@ mod tests {
             expanded: None,
             mode: InputMode::Normal,
             edit_buffer: None,
+            search: None,
+            search_origin: 0,
+            sort_mode: SortMode::Priority,
+            status: None,
+            status_until: None,
+            on_disk: Vec::new(),
+            config: Config::default(),
+            keymap: Keymap::default(),
+            history: History::default(),
         };
 
         let vm = TodoListViewModel::from_app(&app);
 
         assert!(vm.rows.is_empty());
         assert_eq!(vm.selected_index, None);
+        assert!(vm.search.is_none());
+    }
+
+    #[test]
+    fn groups_by_due_date_and_skips_headers_for_flat_sorts() {
+        let mut todos = vec![
+            make_todo("a", None, false, false).0,
+            make_todo("b", None, false, false).0,
+            make_todo("c", None, false, false).0,
+        ];
+        todos[0].due = Some("2025-01-01".into());
+        todos[1].due = Some("2025-01-01".into());
+
+        let mut app = App::new(todos);
+        app.sort_mode = SortMode::Due;
+
+        let headers = |vm: &TodoListViewModel| {
+            vm.rows
+                .iter()
+                .filter_map(|row| match row {
+                    Row::Header(h) => Some(h.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let vm = TodoListViewModel::from_app(&app);
+        assert_eq!(headers(&vm), vec!["Due 2025-01-01", "No due date"]);
+        assert_eq!(vm.title, "Todos (by due date)");
+
+        app.sort_mode = SortMode::Description;
+        let vm = TodoListViewModel::from_app(&app);
+        assert!(headers(&vm).is_empty());
+        assert_eq!(vm.rows.len(), 3);
+    }
+
+    #[test]
+    fn highlights_description_matches_while_searching() {
+        let todos = vec![
+            make_todo("write report", None, false, false).0,
+            make_todo("water plants", None, false, false).0,
+        ];
+
+        let app = App {
+            todos,
+            visual_order: vec![0, 1],
+            selected: 0,
+            expanded: None,
+            mode: InputMode::Searching,
+            edit_buffer: None,
+            search: Some(FieldBuffer::new("rep".to_string())),
+            search_origin: 0,
+            sort_mode: SortMode::Priority,
+            status: None,
+            status_until: None,
+            on_disk: Vec::new(),
+            config: Config::default(),
+            keymap: Keymap::default(),
+            history: History::default(),
+        };
+
+        let vm = TodoListViewModel::from_app(&app);
+
+        let highlighted = vm
+            .rows
+            .iter()
+            .filter_map(|row| {
+                if let Row::Todo { highlighted, .. } = row {
+                    Some(highlighted.clone())
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(highlighted, vec![vec![6, 7, 8], vec![]]);
+
+        let bar = vm.search.unwrap();
+        assert_eq!(bar.query, "rep");
+        assert_eq!(bar.character_index, 3);
+        assert!(bar.active);
     }
 }
@@ -1,9 +1,10 @@
 use crate::tui::app::App;
+use crate::tui::keymap::Mode;
 use crate::tui::view_models::edit_mode_modal_view_model::{EditModeModalViewModel, Input};
 use ratatui::Frame;
 use ratatui::layout::{Alignment, Constraint, Flex, Layout, Margin, Rect};
 use ratatui::prelude::{Color, Line, Modifier, Span, Style};
-use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
 
 pub fn render(f: &mut Frame, app: &App) {
     let outer_block = Block::bordered().borders(Borders::ALL);
@@ -28,21 +29,18 @@ pub fn render(f: &mut Frame, app: &App) {
     ])
     .split(inner_area);
 
-    let view_model = EditModeModalViewModel::from_app(&app);
-    render_edit_header(f, inner_chunks[0]);
+    let view_model = EditModeModalViewModel::from_app(app);
+    render_edit_header(f, app, inner_chunks[0]);
     render_edit_fields(f, inner_chunks[1..6].to_vec(), &view_model);
     render_status_span(f, inner_chunks[7], view_model.done);
     render_cursor(f, inner_area, &view_model)
 }
 
-fn render_edit_header(f: &mut Frame, area: Rect) {
-    let header = Paragraph::new(Line::from(vec![
-        Span::raw("[↑/↓] Move field    "),
-        Span::raw("[←/→] Move cursor    "),
-        Span::raw("[esc] Save & exit    "),
-        Span::raw("[⏎] Toggle Done    "),
-    ]))
-    .block(Block::default());
+fn render_edit_header(f: &mut Frame, app: &App, area: Rect) {
+    let mode = app.keymap_mode().unwrap_or(Mode::Edit);
+    let header = Paragraph::new(Line::from(app.keymap.header(mode).join("    ")))
+        .block(Block::default())
+        .wrap(Wrap { trim: true });
     f.render_widget(header, area);
 }
 
@@ -1,76 +1,122 @@
-use crate::storage::TodoItem;
-use crate::tui::app::App;
-use crate::tui::view_models::todo_view_model::TodoListViewModel;
+use crate::output;
+use crate::tui::app::{App, Status};
+use crate::tui::keymap::Mode;
+use crate::tui::view_models::todo_view_model::{SearchBar, TodoListViewModel};
+use crix_todo::config::{self, Config};
+use crix_todo::storage::TodoItem;
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::prelude::{Line, Modifier, Span, Style};
-use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::prelude::{Color, Line, Modifier, Span, Style, Text};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
 
 pub enum Row<'a> {
     Header(String),
     Todo {
         item: &'a TodoItem,
         is_expanded: bool,
+        highlighted: Vec<usize>,
     },
 }
 
 pub fn render(f: &mut Frame, app: &App) {
+    let view_model = TodoListViewModel::from_app(app);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
             Constraint::Length(2), // keybindings
             Constraint::Min(0),    // list
+            Constraint::Length(if view_model.search.is_some() { 1 } else { 0 }), // search
+            Constraint::Length(if view_model.status.is_some() { 1 } else { 0 }), // status
         ])
         .split(f.size());
 
-    render_keybindings(f, chunks[0]);
-    render_todo_list(f, app, chunks[1]);
+    render_keybindings(f, app, chunks[0]);
+    if let Some(search) = &view_model.search {
+        render_search_bar(f, search, chunks[2]);
+    }
+    if let Some(status) = view_model.status {
+        render_status(f, status, chunks[3]);
+    }
+    render_todo_list(f, view_model, &app.config, chunks[1]);
 }
 
-fn render_todo_list(f: &mut Frame, app: &App, chunk: Rect) {
-    let view_model = TodoListViewModel::from_app(app);
-    let items: Vec<ListItem> = view_model.rows.iter().map(render_row).collect();
+fn render_search_bar(f: &mut Frame, search: &SearchBar, rect: Rect) {
+    let bar = Paragraph::new(Line::from(vec![
+        Span::raw("/"),
+        Span::raw(search.query.clone()),
+    ]));
+    f.render_widget(bar, rect);
+
+    if search.active {
+        f.set_cursor(rect.x + 1 + search.character_index as u16, rect.y);
+    }
+}
+
+fn render_status(f: &mut Frame, status: &Status, rect: Rect) {
+    let line = match status {
+        Status::Error(message) => Span::styled(
+            message.clone(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        Status::Info(message) => Span::styled(message.clone(), Style::default().fg(Color::Green)),
+    };
+    f.render_widget(Paragraph::new(Line::from(line)), rect);
+}
+
+fn render_todo_list(f: &mut Frame, view_model: TodoListViewModel, config: &Config, chunk: Rect) {
+    let items: Vec<ListItem> = view_model
+        .rows
+        .iter()
+        .map(|row| render_row(row, config))
+        .collect();
 
     let mut state = ListState::default();
     state.select(view_model.selected_index);
 
     let list = List::new(items)
-        .block(Block::default().title("Todos").borders(Borders::ALL))
+        .block(
+            Block::default()
+                .title(view_model.title.clone())
+                .borders(Borders::ALL),
+        )
         .highlight_style(Style::default().add_modifier(Modifier::BOLD));
 
     f.render_stateful_widget(list, chunk, &mut state);
 }
 
-fn render_keybindings(f: &mut Frame, rect: Rect) {
-    let header = Paragraph::new(Line::from(vec![
-        Span::raw("[↑/↓] Move    "),
-        Span::raw("[⏎] Toggle Done    "),
-        Span::raw("[Space] Expand    "),
-        Span::raw("[e] Edit    "),
-        Span::raw("[⌫] Delete    "),
-        Span::raw("[p/l] Toggle Priority    "),
-        Span::raw("[q] Quit"),
-    ]))
-    .block(Block::default());
+fn render_keybindings(f: &mut Frame, app: &App, rect: Rect) {
+    let header = Paragraph::new(Line::from(app.keymap.header(Mode::Normal).join("    ")))
+        .block(Block::default())
+        .wrap(Wrap { trim: true });
 
     f.render_widget(header, rect);
 }
 
-fn render_row<'a>(row: &Row) -> ListItem<'a> {
+fn render_row<'a>(row: &Row, config: &Config) -> ListItem<'a> {
     match row {
         Row::Header(text) => ListItem::new(text.clone()),
         Row::Todo {
-            item, is_expanded, ..
+            item,
+            is_expanded,
+            highlighted,
         } => {
             let checkbox = if item.done { "[x]" } else { "[ ]" };
-            let mut lines = vec![format!(" -  {} {}", checkbox, item.description)];
+            let mut title = vec![Span::raw(format!(" -  {} ", checkbox))];
+            title.extend(highlight_spans(
+                &item.description,
+                highlighted,
+                color(config.colors.highlight),
+            ));
+
+            let mut lines = vec![];
 
             if *is_expanded {
                 if let Some(p) = item.priority {
                     lines.push(format!("   Priority: {}", p));
                 }
                 if let Some(due) = &item.due {
+                    let due = output::format_date(due, &config.display.date_format);
                     lines.push(format!("   Due: {}", due));
                 }
                 if let Some(tags) = &item.tags {
@@ -81,7 +127,53 @@ fn render_row<'a>(row: &Row) -> ListItem<'a> {
                 }
             }
 
-            ListItem::new(lines.join("\n"))
+            let mut text = Text::from(Line::from(title));
+            text.extend(lines.into_iter().map(Line::from));
+            ListItem::new(text)
+        }
+    }
+}
+
+fn color(color: config::Color) -> Color {
+    match color {
+        config::Color::Black => Color::Black,
+        config::Color::Red => Color::Red,
+        config::Color::Green => Color::Green,
+        config::Color::Yellow => Color::Yellow,
+        config::Color::Blue => Color::Blue,
+        config::Color::Magenta => Color::Magenta,
+        config::Color::Cyan => Color::Cyan,
+        config::Color::White => Color::White,
+        config::Color::Gray => Color::Gray,
+    }
+}
+
+fn highlight_spans<'a>(text: &str, highlighted: &[usize], fg: Color) -> Vec<Span<'a>> {
+    let matched = Style::default().fg(fg).add_modifier(Modifier::UNDERLINED);
+
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_match = highlighted.contains(&i);
+        if is_match != run_matched && !run.is_empty() {
+            let style = if run_matched {
+                matched
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
         }
+        run_matched = is_match;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let style = if run_matched {
+            matched
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(run, style));
     }
+    spans
 }
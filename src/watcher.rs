@@ -0,0 +1,148 @@
+//! Noticing when the list is changed by another process.
+
+#[cfg(unix)]
+use crate::daemon;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::SystemTime;
+
+/// What a file or directory looked like when last checked. A directory,
+/// as used by the one-file-per-item backend, counts as changed when any
+/// file in it does.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    entries: usize,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Stamp> {
+        let metadata = fs::metadata(path).ok()?;
+        if !metadata.is_dir() {
+            return Some(Stamp {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+                entries: 1,
+            });
+        }
+
+        let mut stamp = Stamp {
+            modified: metadata.modified().ok(),
+            ..Stamp::default()
+        };
+        for entry in fs::read_dir(path).ok()?.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            stamp.modified = stamp.modified.max(metadata.modified().ok());
+            stamp.len += metadata.len();
+            stamp.entries += 1;
+        }
+        Some(stamp)
+    }
+}
+
+/// Notices when the todo file is changed by someone else, such as a
+/// `todo add` in another terminal. Polled from the UI loop.
+pub struct Watcher {
+    path: PathBuf,
+    stamp: Option<Stamp>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            stamp: Stamp::of(path),
+        }
+    }
+
+    /// Whether the file changed since it was last checked or synced.
+    pub fn changed(&mut self) -> bool {
+        let stamp = Stamp::of(&self.path);
+        if stamp == self.stamp {
+            return false;
+        }
+        self.stamp = stamp;
+        true
+    }
+
+    /// Accepts the file as it is now, after we wrote it ourselves.
+    pub fn sync(&mut self) {
+        self.stamp = Stamp::of(&self.path);
+    }
+}
+
+/// Notices changes to the list made by anyone: a running daemon reports
+/// them, and otherwise the file is watched directly.
+pub struct Changes {
+    watcher: Watcher,
+    #[cfg(unix)]
+    daemon: Option<Receiver<()>>,
+}
+
+impl Changes {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            watcher: Watcher::new(path),
+            #[cfg(unix)]
+            daemon: daemon::subscribe(&daemon::socket_path(&path.to_string_lossy())),
+        }
+    }
+
+    pub fn changed(&mut self) -> bool {
+        #[cfg(unix)]
+        if let Some(daemon) = &self.daemon {
+            match daemon.try_recv() {
+                Ok(()) => {
+                    while daemon.try_recv().is_ok() {}
+                    return true;
+                }
+                Err(TryRecvError::Empty) => return false,
+                // The daemon stopped; from now on the file is all there is.
+                Err(TryRecvError::Disconnected) => self.daemon = None,
+            }
+        }
+        self.watcher.changed()
+    }
+
+    pub fn sync(&mut self) {
+        self.watcher.sync();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn notices_changes_but_not_synced_writes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut watcher = Watcher::new(&path);
+        assert!(!watcher.changed());
+
+        fs::write(&path, "[]").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::write(&path, "[ ]").unwrap();
+        watcher.sync();
+        assert!(!watcher.changed());
+    }
+
+    #[test]
+    fn watches_files_inside_a_directory() {
+        let dir = TempDir::new().unwrap();
+        let mut watcher = Watcher::new(dir.path());
+
+        fs::write(dir.path().join("a.json"), "{}").unwrap();
+        assert!(watcher.changed());
+        fs::write(dir.path().join("a.json"), "{ }").unwrap();
+        assert!(watcher.changed());
+    }
+}